@@ -15,4 +15,113 @@ cargo build --features cpuprofile
 cargo build --features all_types
 ```
 
+**Latency with time-stamp-counter**
+
+Latency is sampled with a monotonic clock. For sub-100ns operations,
+like in-memory gets, on x86_64 Linux with an invariant TSC, sample with
+the time-stamp-counter instead, calibrated at startup:
+
+```bash
+ixperf --profile profiles/btree_map.toml --tsc
+```
+
+**Process stats**
+
+With `--stats`, the process is sampled every `--system-interval`
+milliseconds, 1000 by default, for cpu load, rss and virtual memory,
+threads, minor and major page faults, voluntary and involuntary context
+switches, and bytes read and written to disk. Except cpu and rss, these
+are read from `/proc/self` and are available only on Linux. `--plot`
+renders them along with the throughput and latency charts, and charts
+for op outcomes, inserted and updated for writes, hit and missed for
+deletes and gets, and errors:
+
+```bash
+ixperf --profile profiles/lmdb.toml --stats --system-interval 500
+```
+
+**Amplification**
+
+For disk based indexes, robt, dgm, wal and lmdb, every phase reports
+write amplification, physical bytes written per logical key and value
+byte written, read amplification for gets, and space amplification,
+size of the index directory per logical byte in the index. Physical
+bytes are from `/proc/self/io`, Linux only, for the whole process. Size
+of the index directory is also sampled along with the process stats,
+and plotted.
+
+**Live dashboard**
+
+With `--live`, progress and ETA of every phase, per-op throughput and
+latency, and cpu and rss of the process are redrawn on stderr every
+second. Logging continues as usual, log to a file for a clean view:
+
+```bash
+ixperf --profile profiles/lmdb.toml --live --log-file ixperf.log
+```
+
+**Metrics endpoint**
+
+For soak runs, `--metrics-port` serves per-op counters, latency
+quantiles, phases, index entries, and cpu and rss of the process, in
+Prometheus text format at `http://127.0.0.1:<port>/metrics`:
+
+```bash
+ixperf --profile profiles/rdms_robt.toml --metrics-port 9100
+```
+
+**Stopping a run**
+
+Ctrl-C, or SIGTERM, stops the run at the next operation boundary. Every
+thread reports its partial stats, remaining phases are skipped and the
+index is closed as on a normal run. Press Ctrl-C again to exit right
+away.
+
+**Benchmark your own index**
+
+ixperf is also a library. Implement `ixperf::adapter::IndexAdapter` for
+the index, register its perf function with `ixperf::Registry` and run a
+profile against it, refer to `src/lib.rs` for an example.
+
+**YCSB workloads**
+
+Run YCSB core workloads, `ycsb-a` to `ycsb-f`, against any index, the
+profile's `loads` is the record count:
+
+```bash
+ixperf --profile profiles/lmdb.toml --workload ycsb-b
+```
+
+**Compare two indexes**
+
+Run the same seeded workload on the index from `--profile` and then on
+the index from `--diff`, and fail on the first read that differs:
+
+```bash
+ixperf --profile profiles/btree_map.toml --diff profiles/llrb_index.toml
+```
+
+Both profiles must have the same key-type and value-type, generator
+options are taken from `--profile`. Only single threaded runs can be
+compared. Read results of the first index are recorded as a count and
+a digest per op, a mismatch reports both, re-run with `--verify` to
+locate the offending entry. `lmdb` supports only `bytes` key-type and
+value-type, to compare it with, say `robt`, set `key_type` of the robt
+profile to `bytes`, `profiles/rdms_robt.toml` uses `array` keys.
+
+**Harness overhead**
+
+The `null` index accepts all commands and does nothing, its latency is
+the overhead of ixperf itself, command generation, stats sampling and
+dispatch. With `--calibrate`, the profile is first run against the
+`null` index, and its mean latency for every op is subtracted from the
+latency samples, hence from the reported percentiles. Time spent by
+the harness in generating and dispatching each command, outside the
+timed index calls, is subtracted from the elapsed time of every phase,
+hence from the reported throughput:
+
+```bash
+ixperf --profile profiles/lmdb.toml --calibrate
+```
+
 [llrbindex]: http://github.com/bnclabs/llrb-index
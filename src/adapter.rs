@@ -0,0 +1,955 @@
+//! Adapter traits to plug an index into ixperf.
+//!
+//! An index under test implements [IndexAdapter], handing out
+//! [ReaderAdapter] and [WriterAdapter] handles. Rest of the benchmark,
+//! generating the workload, running initial-load, incremental and
+//! concurrent phases, collecting stats and periodic logging, is shared
+//! by every index and implemented in this module.
+
+use log::{debug, info, warn};
+
+use std::{
+    fmt, mem,
+    ops::Bound,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::amplify;
+use crate::differential::Differ;
+use crate::generator::{Cmd, IncrementalLoad, IncrementalRead, IncrementalWrite};
+use crate::generator::{CmdSource, InitialLoad, RandomKV};
+use crate::stats;
+use crate::verify::Verify;
+use crate::Profile;
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Stop the run, loader, reader and writer threads stop at the next op
+/// boundary and report their partial stats. Remaining phases are
+/// skipped and the index is closed as on a normal run.
+pub fn stop() {
+    STOP.store(true, Relaxed);
+}
+
+/// Return whether the run is stopped, refer to [stop].
+pub fn is_stopped() -> bool {
+    STOP.load(Relaxed)
+}
+
+/// Optional callback to receive entries returned by a read operation.
+/// Callers pass None when they are only interested in the count, so
+/// that adapters can skip materializing the entries.
+pub type Visit<'a, K, V> = Option<&'a mut dyn FnMut(&K, &V)>;
+
+/// Index under test.
+pub trait IndexAdapter<K, V> {
+    type R: ReaderAdapter<K, V>;
+    type W: WriterAdapter<K, V>;
+
+    /// Return a new read handle into the index.
+    fn to_reader(&mut self) -> Result<Self::R, String>;
+
+    /// Return a new write handle into the index.
+    fn to_writer(&mut self) -> Result<Self::W, String>;
+
+    /// Return the number of entries in the index, if known.
+    fn len(&mut self) -> Option<usize> {
+        None
+    }
+}
+
+/// Read handle into the index.
+pub trait ReaderAdapter<K, V> {
+    /// Get key, return true if key is found in the index.
+    fn get(&mut self, key: &K, visit: Visit<K, V>) -> Result<bool, String>;
+
+    /// Get a batch of keys, return the number of keys found. Found
+    /// entries are visited in the order of keys. Default implementation
+    /// calls get for every key, adapters can override this to look up
+    /// the whole batch with a single handle or transaction.
+    fn multi_get(&mut self, keys: &[K], mut visit: Visit<K, V>) -> Result<usize, String> {
+        let with_entries = visit.is_some();
+        let mut n = 0;
+        for key in keys.iter() {
+            let mut f = |key: &K, value: &V| do_visit(&mut visit, key, value);
+            let found = if with_entries {
+                self.get(key, Some(&mut f))?
+            } else {
+                self.get(key, None)?
+            };
+            n += found as usize;
+        }
+        Ok(n)
+    }
+
+    /// Iterate from low to high, return the number of entries.
+    fn range(&mut self, low: Bound<K>, high: Bound<K>, visit: Visit<K, V>)
+        -> Result<usize, String>;
+
+    /// Iterate from high to low, return the number of entries.
+    fn reverse(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String>;
+
+    /// Seek to `start` and iterate forward over at most `limit` entries,
+    /// starting from `start` if present, return the number of entries.
+    fn scan(&mut self, start: &K, limit: usize, visit: Visit<K, V>) -> Result<usize, String>;
+
+    /// Seek to `start` and iterate backward over at most `limit` entries,
+    /// starting from `start` if present, return the number of entries.
+    fn reverse_scan(
+        &mut self,
+        start: &K,
+        limit: usize,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String>;
+
+    /// Full table scan, return the number of entries.
+    fn iter(&mut self, visit: Visit<K, V>) -> Result<usize, String> {
+        self.range(Bound::Unbounded, Bound::Unbounded, visit)
+    }
+}
+
+/// Write handle into the index.
+pub trait WriterAdapter<K, V> {
+    /// Set key with value, return true if an older value was updated.
+    fn set(&mut self, key: K, value: V) -> Result<bool, String>;
+
+    /// Delete key, return true if key was found in the index.
+    fn delete(&mut self, key: &K) -> Result<bool, String>;
+
+    /// Same as set, but used during initial load. Adapters can batch
+    /// loads, in which case pending loads are flushed by commit.
+    fn load(&mut self, key: K, value: V) -> Result<bool, String> {
+        self.set(key, value)
+    }
+
+    /// Flush pending loads, if any.
+    fn commit(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Read key and set it with the value returned by `modify`, called
+    /// with the older value if key is present, as a single operation.
+    /// Return true if key was found.
+    fn read_modify_write(
+        &mut self,
+        _key: K,
+        _modify: &mut dyn FnMut(Option<&V>) -> V,
+    ) -> Result<bool, String> {
+        Err("read_modify_write not supported".to_string())
+    }
+
+    /// Set key only if it is not present, return true if value was set.
+    fn set_if_absent(&mut self, _key: K, _value: V) -> Result<bool, String> {
+        Err("set_if_absent not supported".to_string())
+    }
+
+    /// Set key only if it is present, return true if value was set.
+    fn set_if_present(&mut self, _key: K, _value: V) -> Result<bool, String> {
+        Err("set_if_present not supported".to_string())
+    }
+
+    /// Return and reset the number of compare-and-set conflicts since the
+    /// last call, for indexes that implement read_modify_write and
+    /// set_if_present with optimistic concurrency. A conflicting
+    /// read_modify_write is retried, a conflicting set_if_present returns
+    /// false, without the key being missing.
+    fn to_conflicts(&mut self) -> usize {
+        0
+    }
+}
+
+/// Read handle for write-only indexes, every operation fails.
+pub struct WriteOnly;
+
+impl<K, V> ReaderAdapter<K, V> for WriteOnly {
+    fn get(&mut self, _key: &K, _visit: Visit<K, V>) -> Result<bool, String> {
+        Err("write-only index".to_string())
+    }
+
+    fn range(&mut self, _: Bound<K>, _: Bound<K>, _: Visit<K, V>) -> Result<usize, String> {
+        Err("write-only index".to_string())
+    }
+
+    fn reverse(&mut self, _: Bound<K>, _: Bound<K>, _: Visit<K, V>) -> Result<usize, String> {
+        Err("write-only index".to_string())
+    }
+
+    fn scan(&mut self, _: &K, _: usize, _: Visit<K, V>) -> Result<usize, String> {
+        Err("write-only index".to_string())
+    }
+
+    fn reverse_scan(&mut self, _: &K, _: usize, _: Visit<K, V>) -> Result<usize, String> {
+        Err("write-only index".to_string())
+    }
+}
+
+#[inline]
+pub fn do_visit<K, V>(visit: &mut Visit<K, V>, key: &K, value: &V) {
+    if let Some(f) = visit {
+        (*f)(key, value)
+    }
+}
+
+/// Return true if there can be no entry between low and high. Some
+/// indexes, like BTreeMap, panic for such ranges.
+pub fn is_empty_range<K: Ord>(low: &Bound<K>, high: &Bound<K>) -> bool {
+    match (low, high) {
+        (Bound::Included(l), Bound::Included(h)) => l > h,
+        (Bound::Included(l), Bound::Excluded(h)) => l >= h,
+        (Bound::Excluded(l), Bound::Included(h)) => l >= h,
+        (Bound::Excluded(l), Bound::Excluded(h)) => l >= h,
+        _ => false,
+    }
+}
+
+/// What to do when an operation fails inside a measurement loop. Errors
+/// are always counted, per op type and error kind, in [stats::Op].
+#[derive(Clone, Debug)]
+pub enum ErrorPolicy {
+    /// Abort the run on the first error, after reporting the partial stats.
+    Abort,
+    /// Count the error and continue with the next operation.
+    Continue,
+    /// Continue until the number of errors, in a phase, exceeds the limit.
+    AbortAfter(usize),
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> ErrorPolicy {
+        ErrorPolicy::Abort
+    }
+}
+
+impl ErrorPolicy {
+    pub fn new(policy: &str, max_errors: usize) -> Result<ErrorPolicy, String> {
+        match policy {
+            "abort" => Ok(ErrorPolicy::Abort),
+            "continue" => Ok(ErrorPolicy::Continue),
+            "abort-after" => Ok(ErrorPolicy::AbortAfter(max_errors)),
+            policy => Err(format!("invalid error_policy {}", policy)),
+        }
+    }
+
+    /// Called with the number of errors so far, including `err`. Return
+    /// `err` if the run shall abort.
+    pub fn check(&self, errors: usize, err: String) -> Result<(), String> {
+        match self {
+            ErrorPolicy::Abort => Err(err),
+            ErrorPolicy::Continue => Ok(()),
+            ErrorPolicy::AbortAfter(max) if errors > *max => {
+                Err(format!("aborting after {} errors, {}", errors, err))
+            }
+            ErrorPolicy::AbortAfter(_) => Ok(()),
+        }
+    }
+}
+
+/// Run initial-load, full-table iteration and incremental-load,
+/// all in the calling thread. In verify mode, index is verified
+/// against a shadow BTreeMap. In diff mode, read results are recorded,
+/// or compared with the recorded results, refer [crate::differential].
+pub fn do_perf<I, K, V>(index: &mut I, p: &Profile) -> Result<stats::Ops, String>
+where
+    I: IndexAdapter<K, V>,
+    K: 'static + Clone + Default + Send + Ord + fmt::Debug + RandomKV,
+    V: 'static + Clone + Default + Send + PartialEq + fmt::Debug + RandomKV,
+{
+    if p.cmd_opts.verify && p.diff.is_some() {
+        Err("verify and diff modes are exclusive".to_string())
+    } else if let Some(opt) = &p.diff {
+        warn!(target: "ixperf", "diff mode, latency includes the comparison");
+
+        let mut index = Differ::new(index, opt.clone());
+        let fstats = do_phases::<_, K, V>(&mut index, p)?;
+        let n = index.validate::<K, V>()?;
+        info!(target: "ixperf", "diff {} entries, {:?}", n, opt.mode);
+        Ok(fstats)
+    } else if p.cmd_opts.verify {
+        warn!(target: "ixperf", "verify mode, latency includes the shadow");
+
+        let mut index = Verify::new(index);
+        let fstats = do_phases(&mut index, p)?;
+        let n = index.validate()?;
+        info!(target: "ixperf", "verified {} entries against shadow", n);
+        Ok(fstats)
+    } else {
+        do_phases(index, p)
+    }
+}
+
+fn do_phases<I, K, V>(index: &mut I, p: &Profile) -> Result<stats::Ops, String>
+where
+    I: IndexAdapter<K, V>,
+    K: 'static + Clone + Default + Send + RandomKV,
+    V: 'static + Clone + Default + Send + RandomKV,
+{
+    let mut fstats = do_initial_load(index, p)?;
+    fstats.merge(&do_iter(index, p)?);
+    fstats.merge(&do_incremental(index, p)?);
+    Ok(fstats)
+}
+
+/// Same as do_perf, except that initial-load is shared by `initial`
+/// writer threads, and incremental-load is applied by concurrent
+/// `readers` and `writers`. If there are no concurrent readers and
+/// writers, incremental-load is applied in the calling thread.
+/// Verify and diff modes are supported only for single threaded runs.
+pub fn do_concur_perf<I, K, V>(
+    index: &mut I,
+    initial: usize,
+    readers: usize,
+    writers: usize,
+    p: &Profile,
+) -> Result<stats::Ops, String>
+where
+    I: IndexAdapter<K, V>,
+    <I as IndexAdapter<K, V>>::R: 'static + Send,
+    <I as IndexAdapter<K, V>>::W: 'static + Send,
+    K: 'static + Clone + Default + Send + Ord + fmt::Debug + RandomKV,
+    V: 'static + Clone + Default + Send + PartialEq + fmt::Debug + RandomKV,
+{
+    if p.cmd_opts.verify || p.diff.is_some() {
+        if initial > 1 || (readers + writers) > 0 {
+            return Err(format!(
+                "verify/diff mode needs single thread, initial:{} readers:{} writers:{}",
+                initial, readers, writers
+            ));
+        }
+        return do_perf(index, p);
+    }
+
+    let mut fstats = do_concur_initial_load(index, initial, p)?;
+    fstats.merge(&do_iter(index, p)?);
+
+    let total_ops = p.g.read_ops() + p.g.write_ops();
+    if (readers + writers) == 0 && total_ops > 0 {
+        fstats.merge(&do_incremental(index, p)?);
+    } else if total_ops > 0 {
+        fstats.merge(&do_concurrent(index, readers, writers, p)?);
+    }
+
+    Ok(fstats)
+}
+
+pub fn do_initial_load<I, K, V>(index: &mut I, p: &Profile) -> Result<stats::Ops, String>
+where
+    I: IndexAdapter<K, V>,
+    K: 'static + Clone + Default + Send + RandomKV,
+    V: 'static + Clone + Default + Send + RandomKV,
+{
+    if p.g.loads == 0 {
+        return Ok(stats::Ops::new());
+    }
+
+    let mut w = index.to_writer()?;
+    let gen = InitialLoad::<K, V>::new(p.g.clone());
+    let amp = p.amplify.as_ref().map(|amplify| amplify.start());
+    let (fstats, elapsed) = do_phase("initial", p.g.loads, gen, p, |cmd, lstats| {
+        do_write_op(&mut w, cmd, lstats)
+    })?;
+    w.commit()?;
+    mem::drop(w);
+
+    info!(
+        target: "ixperf",
+        "initial-load load_ops:{} index.len:{} elapsed:{:?}",
+        p.g.loads, to_len_string(index), elapsed
+    );
+    set_index_len(index, p);
+    report_amplify("initial", amp, &fstats, index, p);
+
+    Ok(fstats)
+}
+
+pub fn do_concur_initial_load<I, K, V>(
+    index: &mut I,
+    n_threads: usize,
+    p: &Profile,
+) -> Result<stats::Ops, String>
+where
+    I: IndexAdapter<K, V>,
+    <I as IndexAdapter<K, V>>::W: 'static + Send,
+    K: 'static + Clone + Default + Send + RandomKV,
+    V: 'static + Clone + Default + Send + RandomKV,
+{
+    if p.g.loads == 0 {
+        return Ok(stats::Ops::new());
+    }
+
+    let amp = p.amplify.as_ref().map(|amplify| amplify.start());
+    let mut threads = vec![];
+    for i in 0..n_threads {
+        let w = index.to_writer()?;
+        let pr = p.clone();
+        threads.push(thread::spawn(move || do_initial(i, w, pr)));
+    }
+
+    let fstats = join_threads(threads)?;
+
+    stats!(&p.cmd_opts, "ixperf", "initial stats\n{:?}\n", fstats);
+    set_index_len(index, p);
+    report_amplify("initial", amp, &fstats, index, p);
+    Ok(fstats)
+}
+
+/// Full table scan, reported as a single iter op, with the number of
+/// entries as its items.
+pub fn do_iter<I, K, V>(index: &mut I, p: &Profile) -> Result<stats::Ops, String>
+where
+    I: IndexAdapter<K, V>,
+{
+    let mut fstats = stats::Ops::new();
+    if !p.g.iters || is_stopped() {
+        return Ok(fstats);
+    }
+
+    let mut r = index.to_reader()?;
+    let start = Instant::now();
+    fstats.iter.sample_start(true);
+    let count = r.iter(None).map_err(|e| fstats.iter.sample_error(e))?;
+    fstats.iter.sample_scan(count);
+    fstats.set_elapsed(start.elapsed());
+
+    stats!(&p.cmd_opts, "ixperf", "iter stats\n{:?}", fstats);
+    info!(
+        target: "ixperf",
+        "iter items:{} elapsed:{:?}", count, fstats.iter.elapsed
+    );
+
+    Ok(fstats)
+}
+
+pub fn do_incremental<I, K, V>(index: &mut I, p: &Profile) -> Result<stats::Ops, String>
+where
+    I: IndexAdapter<K, V>,
+    K: 'static + Clone + Default + Send + RandomKV,
+    V: 'static + Clone + Default + Send + RandomKV,
+{
+    if (p.g.read_ops() + p.g.write_ops()) == 0 {
+        return Ok(stats::Ops::new());
+    }
+
+    let mut w = index.to_writer()?;
+    let mut r = index.to_reader()?;
+    let gen = IncrementalLoad::<K, V>::new(p.g.clone());
+    let total = p.g.read_ops() + p.g.write_ops();
+    let amp = p.amplify.as_ref().map(|amplify| amplify.start());
+    let (fstats, elapsed) = do_phase("incremental", total, gen, p, |cmd, lstats| {
+        if cmd.is_write() {
+            do_write_op(&mut w, cmd, lstats)
+        } else {
+            do_read_op(&mut r, cmd, lstats)
+        }
+    })?;
+    mem::drop(w);
+    mem::drop(r);
+
+    info!(
+        target: "ixperf",
+        "incremental-load r_ops:{} w_ops:{} index.len:{} elapsed:{:?}",
+        p.g.read_ops(), p.g.write_ops(), to_len_string(index), elapsed
+    );
+    set_index_len(index, p);
+    report_amplify("incremental", amp, &fstats, index, p);
+
+    Ok(fstats)
+}
+
+pub fn do_concurrent<I, K, V>(
+    index: &mut I,
+    readers: usize,
+    writers: usize,
+    p: &Profile,
+) -> Result<stats::Ops, String>
+where
+    I: IndexAdapter<K, V>,
+    <I as IndexAdapter<K, V>>::R: 'static + Send,
+    <I as IndexAdapter<K, V>>::W: 'static + Send,
+    K: 'static + Clone + Default + Send + RandomKV,
+    V: 'static + Clone + Default + Send + RandomKV,
+{
+    let amp = p.amplify.as_ref().map(|amplify| amplify.start());
+    let mut w_threads = vec![];
+    for i in 0..writers {
+        let w = index.to_writer()?;
+        let pr = p.clone();
+        w_threads.push(thread::spawn(move || do_write(i, w, pr)));
+    }
+    let mut r_threads = vec![];
+    for i in 0..readers {
+        let r = index.to_reader()?;
+        let pr = p.clone();
+        r_threads.push(thread::spawn(move || do_read(i, r, pr)));
+    }
+
+    // join the readers even if a writer failed, before returning the error.
+    let (wstats, rstats) = (join_threads(w_threads), join_threads(r_threads));
+    let (wstats, rstats) = (wstats?, rstats?);
+    stats!(&p.cmd_opts, "ixperf", "all-writers stats\n{:?}", wstats);
+    stats!(&p.cmd_opts, "ixperf", "all-readers stats\n{:?}", rstats);
+
+    let mut fstats = stats::Ops::new();
+    fstats.merge(&wstats);
+    fstats.merge(&rstats);
+    set_index_len(index, p);
+    report_amplify("concurrent", amp, &fstats, index, p);
+
+    Ok(fstats)
+}
+
+/// Join all the `threads` and merge their stats. If any of the threads
+/// failed, return the first error, but only after all of them are done.
+pub fn join_threads(
+    threads: Vec<thread::JoinHandle<Result<stats::Ops, String>>>,
+) -> Result<stats::Ops, String> {
+    let mut fstats = stats::Ops::new();
+    let mut res = Ok(());
+    for t in threads {
+        match t.join().unwrap() {
+            Ok(stats) => fstats.merge(&stats),
+            Err(err) if res.is_ok() => res = Err(err),
+            Err(_) => (),
+        }
+    }
+    res.map(|_| fstats)
+}
+
+/// Initial-load thread, applying InitialLoad commands using writer `w`.
+pub fn do_initial<W, K, V>(id: usize, mut w: W, mut p: Profile) -> Result<stats::Ops, String>
+where
+    W: WriterAdapter<K, V>,
+    K: 'static + Clone + Default + Send + RandomKV,
+    V: 'static + Clone + Default + Send + RandomKV,
+{
+    p.g.seed += (id * 100) as u128; // change the seed
+
+    let gen = InitialLoad::<K, V>::with_id(p.g.clone(), id);
+    let name = format!("initial-{}", id);
+    let (fstats, elapsed) = do_phase(&name, p.g.loads, gen, &p, |cmd, lstats| {
+        do_write_op(&mut w, cmd, lstats)
+    })?;
+    w.commit()?;
+
+    info!(
+        target: "ixperf", "initial-{} load_ops:{} elapsed:{:?}",
+        id, p.g.loads, elapsed
+    );
+
+    Ok(fstats)
+}
+
+/// Writer thread, applying IncrementalWrite commands using writer `w`.
+pub fn do_write<W, K, V>(id: usize, mut w: W, mut p: Profile) -> Result<stats::Ops, String>
+where
+    W: WriterAdapter<K, V>,
+    K: 'static + Clone + Default + Send + RandomKV,
+    V: 'static + Clone + Default + Send + RandomKV,
+{
+    p.g.seed += (id * 100) as u128; // change the seed
+
+    if p.g.write_ops() == 0 {
+        return Ok(stats::Ops::new());
+    }
+
+    let gen = IncrementalWrite::<K, V>::new(p.g.clone());
+    let name = format!("writer-{}", id);
+    let (fstats, elapsed) = do_phase(&name, p.g.write_ops(), gen, &p, |cmd, lstats| {
+        do_write_op(&mut w, cmd, lstats)
+    })?;
+
+    info!(
+        target: "ixperf", "writer-{} w_ops:{} elapsed:{:?}",
+        id, p.g.write_ops(), elapsed
+    );
+
+    Ok(fstats)
+}
+
+/// Reader thread, applying IncrementalRead commands using reader `r`.
+pub fn do_read<R, K, V>(id: usize, mut r: R, mut p: Profile) -> Result<stats::Ops, String>
+where
+    R: ReaderAdapter<K, V>,
+    K: 'static + Clone + Default + Send + RandomKV,
+    V: 'static + Clone + Default + Send + RandomKV,
+{
+    p.g.seed += (id * 100) as u128; // change the seed
+
+    if p.g.read_ops() == 0 {
+        return Ok(stats::Ops::new());
+    }
+
+    let gen = IncrementalRead::<K, V>::new(p.g.clone());
+    let name = format!("reader-{}", id);
+    let (fstats, elapsed) = do_phase(&name, p.g.read_ops(), gen, &p, |cmd, lstats| {
+        do_read_op(&mut r, cmd, lstats)
+    })?;
+
+    info!(
+        target: "ixperf", "reader-{} r_ops:{} elapsed:{:?}",
+        id, p.g.read_ops(), elapsed
+    );
+
+    Ok(fstats)
+}
+
+/// Apply a write command, Load, Set, Insert, Update, Delete, Rmw,
+/// SetIfAbsent or SetIfPresent, and account it in `lstats`. Logical
+/// size of key and value is accounted for successful writes, only key
+/// for deletes.
+pub fn do_write_op<W, K, V>(
+    w: &mut W,
+    cmd: Cmd<K, V>,
+    lstats: &mut stats::Ops,
+) -> Result<(), String>
+where
+    W: WriterAdapter<K, V>,
+    K: RandomKV,
+    V: Clone + RandomKV,
+{
+    match cmd {
+        Cmd::Load { key, value } => {
+            let bytes = key.logical_size() + value.logical_size();
+            lstats.load.sample_start(false);
+            let res = w.load(key, value);
+            let updated = res.map_err(|e| lstats.load.sample_error(e))?;
+            lstats.load.sample_write(updated);
+            lstats.load.bytes += bytes;
+        }
+        Cmd::Set { key, value } => {
+            let bytes = key.logical_size() + value.logical_size();
+            lstats.set.sample_start(false);
+            let res = w.set(key, value);
+            let updated = res.map_err(|e| lstats.set.sample_error(e))?;
+            lstats.set.sample_write(updated);
+            lstats.set.bytes += bytes;
+        }
+        Cmd::Insert { key, value } => {
+            let bytes = key.logical_size() + value.logical_size();
+            lstats.insert.sample_start(false);
+            let res = w.set(key, value);
+            let updated = res.map_err(|e| lstats.insert.sample_error(e))?;
+            lstats.insert.sample_write(updated);
+            lstats.insert.bytes += bytes;
+        }
+        Cmd::Update { key, value } => {
+            let bytes = key.logical_size() + value.logical_size();
+            lstats.update.sample_start(false);
+            let res = w.set(key, value);
+            let updated = res.map_err(|e| lstats.update.sample_error(e))?;
+            lstats.update.sample_write(updated);
+            lstats.update.bytes += bytes;
+        }
+        Cmd::Delete { key } => {
+            lstats.delete.sample_start(false);
+            let res = w.delete(&key);
+            let found = res.map_err(|e| lstats.delete.sample_error(e))?;
+            lstats.delete.sample_read(found);
+            lstats.delete.bytes += key.logical_size();
+        }
+        Cmd::Rmw { key, value } => {
+            // modified value is of the same size as the generated value.
+            let bytes = key.logical_size() + value.logical_size();
+            lstats.rmw.sample_start(false);
+            let mut modify = |old: Option<&V>| match old {
+                Some(old) => old.modify(),
+                None => value.clone(),
+            };
+            let res = w.read_modify_write(key, &mut modify);
+            lstats.rmw.conflicts += w.to_conflicts();
+            let found = res.map_err(|e| lstats.rmw.sample_error(e))?;
+            lstats.rmw.sample_write(found);
+            lstats.rmw.bytes += bytes;
+        }
+        Cmd::SetIfAbsent { key, value } => {
+            let bytes = key.logical_size() + value.logical_size();
+            lstats.set_if_absent.sample_start(false);
+            let res = w.set_if_absent(key, value);
+            let ok = res.map_err(|e| lstats.set_if_absent.sample_error(e))?;
+            // skipped for an existing key.
+            match ok {
+                true => {
+                    lstats.set_if_absent.sample_write(false);
+                    lstats.set_if_absent.bytes += bytes;
+                }
+                false => lstats.set_if_absent.sample_read(true),
+            }
+        }
+        Cmd::SetIfPresent { key, value } => {
+            let bytes = key.logical_size() + value.logical_size();
+            lstats.set_if_present.sample_start(false);
+            let res = w.set_if_present(key, value);
+            let conflicts = w.to_conflicts();
+            let ok = res.map_err(|e| lstats.set_if_present.sample_error(e))?;
+            // skipped for a missing key, or lost to a concurrent write.
+            match ok {
+                true => {
+                    lstats.set_if_present.sample_write(true);
+                    lstats.set_if_present.bytes += bytes;
+                }
+                false if conflicts > 0 => lstats.set_if_present.sample_conflict(),
+                false => lstats.set_if_present.sample_read(false),
+            }
+        }
+        _ => unreachable!(),
+    };
+    Ok(())
+}
+
+/// Apply a read command, Get, Range, Reverse, Scan or ReverseScan, and
+/// account it in `lstats`. For scans, latency to the first entry is
+/// also accounted as seek, the rest is the cost of iterating entries.
+pub fn do_read_op<R, K, V>(r: &mut R, cmd: Cmd<K, V>, lstats: &mut stats::Ops) -> Result<(), String>
+where
+    R: ReaderAdapter<K, V>,
+{
+    match cmd {
+        Cmd::Get { key } => {
+            lstats.get.sample_start(false);
+            let res = r.get(&key, None);
+            let found = res.map_err(|e| lstats.get.sample_error(e))?;
+            lstats.get.sample_read(found);
+        }
+        Cmd::MultiGet { keys } => {
+            lstats.multi_get.sample_start(false);
+            let res = r.multi_get(&keys, None);
+            let hits = res.map_err(|e| lstats.multi_get.sample_error(e))?;
+            lstats.multi_get.sample_batch(hits, keys.len());
+        }
+        Cmd::Range { low, high } => {
+            lstats.range.sample_start(true);
+            let res = r.range(low, high, None);
+            let items = res.map_err(|e| lstats.range.sample_error(e))?;
+            lstats.range.sample_scan(items);
+        }
+        Cmd::Reverse { low, high } => {
+            lstats.reverse.sample_start(true);
+            let res = r.reverse(low, high, None);
+            let items = res.map_err(|e| lstats.reverse.sample_error(e))?;
+            lstats.reverse.sample_scan(items);
+        }
+        Cmd::Scan { start, limit } => {
+            let (scan, seek) = (&mut lstats.scan, &mut lstats.seek);
+            do_scan(scan, seek, |visit| r.scan(&start, limit, visit))?;
+        }
+        Cmd::ReverseScan { start, limit } => {
+            let (scan, seek) = (&mut lstats.reverse_scan, &mut lstats.seek);
+            do_scan(scan, seek, |visit| r.reverse_scan(&start, limit, visit))?;
+        }
+        _ => unreachable!(),
+    };
+    Ok(())
+}
+
+// Account latency to the first entry as seek, missed if there are no
+// entries, and the whole scan as scan. Timing the seek needs a visitor,
+// and adapters copy out every entry they visit, hence the visitor is
+// passed only when seek latency is sampled.
+fn do_scan<K, V, F>(scan: &mut stats::Op, seek: &mut stats::Op, apply: F) -> Result<(), String>
+where
+    F: FnOnce(Visit<K, V>) -> Result<usize, String>,
+{
+    scan.sample_start(true);
+    seek.sample_start(false);
+    let items = if seek.is_sampled() {
+        let mut seeked = false;
+        let res = {
+            let mut visit = |_: &K, _: &V| {
+                if !seeked {
+                    seek.sample_read(true);
+                    seeked = true;
+                }
+            };
+            apply(Some(&mut visit))
+        };
+        let items = res.map_err(|e| scan.sample_error(e))?;
+        if !seeked {
+            seek.sample_read(false);
+        }
+        items
+    } else {
+        let items = apply(None).map_err(|e| scan.sample_error(e))?;
+        seek.sample_read(items > 0);
+        items
+    };
+    scan.sample_scan(items);
+    Ok(())
+}
+
+// Apply every command from `gen`, `total` commands, log periodic-stats
+// every second, and return the final stats and the time taken to apply
+// all the commands, excluding the time spent waiting for the generator.
+fn do_phase<G, F, K, V>(
+    name: &str,
+    total: usize,
+    gen: G,
+    p: &Profile,
+    mut apply: F,
+) -> Result<(stats::Ops, Duration), String>
+where
+    G: 'static + Send + Iterator<Item = Cmd<K, V>>,
+    F: FnMut(Cmd<K, V>, &mut stats::Ops) -> Result<(), String>,
+    K: 'static + Send,
+    V: 'static + Send,
+{
+    let mut gen = CmdSource::new(gen, &p.g);
+
+    if let Some(live) = &p.live {
+        live.start_phase(name, total);
+    }
+    if let Some(metrics) = &p.metrics {
+        metrics.start_phase(name);
+    }
+
+    // on abort, partial stats are still merged and reported, before
+    // returning the error.
+    let (mut fstats, mut errors, mut n) = (stats::Ops::new(), 0, 0);
+    let mut abort: Option<String> = None;
+    let elapsed = {
+        let start = Instant::now();
+
+        let (mut lstats, mut lstart, mut ln) = (stats::Ops::new(), Instant::now(), 0);
+        for cmd in gen.by_ref() {
+            if is_stopped() {
+                warn!(target: "ixperf", "{} stopped, stats are partial", name);
+                break;
+            }
+            n += 1;
+            ln += 1;
+            match &p.live {
+                Some(live) if (n % 1024) == 0 => live.progress(name, n),
+                _ => (),
+            }
+            let is_insert = cmd.is_insert();
+            match apply(cmd, &mut lstats) {
+                Ok(_) if is_insert => p.g.insert_applied(),
+                Ok(_) => (),
+                Err(err) => {
+                    errors += 1;
+                    if errors == 1 {
+                        warn!(target: "ixperf", "{} first error {}", name, err);
+                    }
+                    if let Err(err) = p.error_policy.check(errors, err) {
+                        abort = Some(err);
+                        break;
+                    }
+                }
+            }
+            if lstats.is_sec_elapsed() {
+                calibrate(&mut lstats, p);
+                lstats.set_elapsed(to_elapsed(ln, lstart.elapsed(), p));
+                stats!(&p.cmd_opts, "ixperf", "{} periodic-stats\n{}", name, lstats);
+                if let Some(live) = &p.live {
+                    live.update(name, &lstats);
+                }
+                fstats.merge(&lstats);
+                if let Some(metrics) = &p.metrics {
+                    fstats.set_elapsed(to_elapsed(n, start.elapsed(), p));
+                    metrics.update(name, &fstats);
+                }
+                lstats = stats::Ops::new();
+                lstart = Instant::now();
+                ln = 0;
+            }
+        }
+        calibrate(&mut lstats, p);
+        fstats.merge(&lstats);
+        start.elapsed()
+    };
+
+    // generation overhead is reported separately, excluded from elapsed.
+    let stalled = gen.to_stalled();
+    let elapsed = elapsed.checked_sub(stalled).unwrap_or_default();
+    fstats.set_dispatch(n, elapsed);
+    let elapsed = to_elapsed(n, elapsed, p);
+    fstats.set_elapsed(elapsed);
+    if let Some(live) = &p.live {
+        live.update(name, &fstats);
+        live.finish_phase(name, n);
+    }
+    if let Some(metrics) = &p.metrics {
+        metrics.update(name, &fstats);
+        metrics.finish_phase(name);
+    }
+
+    stats!(&p.cmd_opts, "ixperf", "{} stats\n{:?}", name, fstats);
+
+    if stalled > Duration::from_nanos(0) {
+        info!(target: "ixperf", "{} waited {:?} for generator", name, stalled);
+    }
+    if errors > 0 {
+        warn!(
+            target: "ixperf",
+            "{} {} errors, policy {:?}", name, errors, p.error_policy
+        );
+    }
+    match abort {
+        Some(err) => Err(err),
+        None => Ok((fstats, elapsed)),
+    }
+}
+
+// Subtract harness overhead, if measured with --calibrate, from the
+// latencies in `stats`.
+fn calibrate(stats: &mut stats::Ops, p: &Profile) {
+    if let Some(overhead) = &p.overhead {
+        stats.calibrate(overhead);
+    }
+}
+
+// Return `elapsed` time for applying `cmds` commands, with the dispatch
+// overhead subtracted, if measured with --calibrate.
+fn to_elapsed(cmds: usize, elapsed: Duration, p: &Profile) -> Duration {
+    match &p.overhead {
+        Some(overhead) => overhead.to_elapsed(cmds, elapsed),
+        None => elapsed,
+    }
+}
+
+// Report number of entries in index to the metrics endpoint.
+fn set_index_len<I, K, V>(index: &mut I, p: &Profile)
+where
+    I: IndexAdapter<K, V>,
+{
+    match (&p.metrics, index.len()) {
+        (Some(metrics), Some(n)) => metrics.set_index_len(&p.index, n),
+        _ => (),
+    }
+}
+
+// Report write, read and space amplification at the end of a phase,
+// for disk based indexes, refer [crate::amplify].
+fn report_amplify<I, K, V>(
+    name: &str,
+    amp: Option<amplify::Phase>,
+    stats: &stats::Ops,
+    index: &mut I,
+    p: &Profile,
+) where
+    I: IndexAdapter<K, V>,
+{
+    if let (Some(amplify), Some(amp)) = (&p.amplify, amp) {
+        let report = amplify.finish(amp, stats, index.len());
+        info!(target: "ixperf", "{} amplification\n{}", name, report);
+    }
+}
+
+fn to_len_string<I, K, V>(index: &mut I) -> String
+where
+    I: IndexAdapter<K, V>,
+{
+    match index.len() {
+        Some(n) => n.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+#[path = "adapter_test.rs"]
+mod adapter_test;
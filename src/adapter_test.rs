@@ -0,0 +1,30 @@
+use super::*;
+
+#[test]
+fn test_error_policy() {
+    let err = "lmdb: MDB_MAP_FULL".to_string();
+
+    let policy = ErrorPolicy::new("abort", 10).unwrap();
+    assert_eq!(policy.check(1, err.clone()), Err(err.clone()));
+
+    let policy = ErrorPolicy::new("continue", 0).unwrap();
+    for errors in 1..100 {
+        assert_eq!(policy.check(errors, err.clone()), Ok(()));
+    }
+
+    // limit is on the number of errors, the run aborts on the next one.
+    let policy = ErrorPolicy::new("abort-after", 2).unwrap();
+    assert_eq!(policy.check(1, err.clone()), Ok(()));
+    assert_eq!(policy.check(2, err.clone()), Ok(()));
+    assert_eq!(
+        policy.check(3, err.clone()),
+        Err("aborting after 3 errors, lmdb: MDB_MAP_FULL".to_string())
+    );
+
+    let res = ErrorPolicy::new("retry", 0);
+    assert_eq!(res.unwrap_err(), "invalid error_policy retry".to_string());
+    match ErrorPolicy::default() {
+        ErrorPolicy::Abort => (),
+        policy => panic!("default policy {:?}", policy),
+    }
+}
@@ -0,0 +1,151 @@
+//! Write, read and space amplification for disk based indexes, robt,
+//! dgm, wal and lmdb, reported at the end of every phase.
+//!
+//! * Logical bytes written are the key and value sizes, from the
+//!   generator, of successful writes, refer [crate::stats::Op].
+//! * Physical bytes, read and written, are from `/proc/self/io`, hence
+//!   available only on Linux. They are accounted for the whole process,
+//!   including logging, and for all the threads in a phase.
+//! * Disk usage is the size of the index directory. It is also sampled
+//!   periodically by the system thread, refer [crate::system].
+//!
+//! Logical bytes read by gets are estimated as the number of hits times
+//! the mean size of an entry written so far. Likewise, logical size of
+//! the index is the number of entries times the mean size of an entry,
+//! or the logical bytes written so far for indexes that don't know their
+//! length, like wal.
+
+use std::{
+    ffi, fs, path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{stats, system, Profile};
+
+/// Amplification accounting for an index directory, cheap to clone and
+/// share with threads.
+#[derive(Clone)]
+pub struct Amplify {
+    dir: ffi::OsString,
+    state: Arc<Mutex<State>>,
+}
+
+// logical bytes written by all the phases so far, and entries written
+// with a value.
+#[derive(Default)]
+struct State {
+    bytes: usize,
+    entry_bytes: usize,
+    entries: usize,
+}
+
+/// Physical io at the start of a phase.
+pub struct Phase {
+    read_bytes: u64,
+    write_bytes: u64,
+}
+
+impl Amplify {
+    /// Return amplification accounting for the index in profile `p`,
+    /// None if it is not disk based.
+    pub fn from_profile(p: &Profile) -> Option<Amplify> {
+        let dir: ffi::OsString = match (p.index.as_str(), p.rdms.index.as_str()) {
+            ("lmdb", _) => p.lmdb.dir.clone().into(),
+            ("wal", _) => p.wal.dir.clone(),
+            ("rdms", "robt") => p.rdms_robt.dir.clone(),
+            ("rdms", "dgm") => p.rdms_dgm.dir.clone(),
+            _ => return None,
+        };
+        Some(Amplify {
+            dir,
+            state: Default::default(),
+        })
+    }
+
+    pub fn to_dir(&self) -> ffi::OsString {
+        self.dir.clone()
+    }
+
+    /// Phase is starting.
+    pub fn start(&self) -> Phase {
+        let (read_bytes, write_bytes) = system::read_io().unwrap_or((0, 0));
+        Phase {
+            read_bytes,
+            write_bytes,
+        }
+    }
+
+    /// Phase, that applied `stats`, is done, with `entries` in the
+    /// index, if known. Return the amplification report as a toml line.
+    /// Ratios are ZERO when there is nothing to compare with.
+    pub fn finish(&self, phase: Phase, stats: &stats::Ops, entries: Option<usize>) -> String {
+        let (read_bytes, write_bytes) = system::read_io().unwrap_or((0, 0));
+        let physical_read = read_bytes.saturating_sub(phase.read_bytes);
+        let physical_written = write_bytes.saturating_sub(phase.write_bytes);
+        let disk_size = dir_size(&self.dir);
+
+        let logical_written = stats.to_total_bytes();
+        let (logical_read, logical_size) = {
+            let mut state = self.state.lock().unwrap();
+            state.bytes += logical_written;
+            state.entry_bytes += logical_written - stats.delete.bytes;
+            state.entries += {
+                let iter = stats.to_ops().into_iter();
+                iter.map(|op| op.inserted + op.updated).sum::<usize>()
+            };
+
+            let entry_size = match state.entries {
+                0 => 0,
+                n => state.entry_bytes / n,
+            };
+            let hits = stats.get.hit + stats.multi_get.hit;
+            let logical_size = match entries {
+                Some(n) => n * entry_size,
+                None => state.bytes,
+            };
+            (hits * entry_size, logical_size)
+        };
+
+        format!(
+            concat!(
+                "amplification = {{ logical_written={}, physical_written={}, ",
+                "write_amp={:.2}, logical_read={}, physical_read={}, ",
+                "read_amp={:.2}, logical_size={}, disk_size={}, space_amp={:.2} }}"
+            ),
+            logical_written,
+            physical_written,
+            to_ratio(physical_written, logical_written),
+            logical_read,
+            physical_read,
+            to_ratio(physical_read, logical_read),
+            logical_size,
+            disk_size,
+            to_ratio(disk_size, logical_size),
+        )
+    }
+}
+
+/// Return the size of all the files under `dir`, in bytes.
+pub fn dir_size<P: AsRef<path::Path>>(dir: P) -> u64 {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    let mut size = 0;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        // files can be removed, say by compaction, while walking the dir.
+        match entry.metadata() {
+            Ok(meta) if meta.is_dir() => size += dir_size(entry.path()),
+            Ok(meta) => size += meta.len(),
+            Err(_) => (),
+        }
+    }
+    size
+}
+
+fn to_ratio(physical: u64, logical: usize) -> f64 {
+    match logical {
+        0 => 0.0,
+        logical => (physical as f64) / (logical as f64),
+    }
+}
@@ -0,0 +1,95 @@
+//! Monotonic clock for latency samples. By default it is backed by
+//! [std::time::Instant]. On x86_64 Linux, time-stamp-counter can be
+//! enabled with `--tsc`, for operations that take less than 100ns, like
+//! in-memory gets, where the cost of reading the clock dominates.
+//!
+//! TSC is calibrated against the monotonic clock when it is enabled, and
+//! it is enabled only when the CPU advertises an invariant TSC, that is,
+//! `constant_tsc` and `nonstop_tsc`.
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
+    time::{self, Duration},
+};
+
+static TSC: AtomicBool = AtomicBool::new(false);
+// nanoseconds per tick, as 32.32 fixed point.
+static TSC_SCALE: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy)]
+pub enum Instant {
+    Mono(time::Instant),
+    Tsc(u64),
+}
+
+impl Instant {
+    #[inline]
+    pub fn now() -> Instant {
+        if TSC.load(Relaxed) {
+            Instant::Tsc(rdtsc())
+        } else {
+            Instant::Mono(time::Instant::now())
+        }
+    }
+
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        match self {
+            Instant::Mono(start) => start.elapsed(),
+            Instant::Tsc(start) => {
+                let ticks = rdtsc().saturating_sub(*start) as u128;
+                let scale = TSC_SCALE.load(Relaxed) as u128;
+                Duration::from_nanos(((ticks * scale) >> 32) as u64)
+            }
+        }
+    }
+}
+
+/// Calibrate and enable TSC for all subsequent latency samples. Return
+/// the number of ticks per second.
+pub fn enable_tsc() -> Result<u64, String> {
+    is_invariant_tsc()?;
+
+    let (start, tsc_start) = (time::Instant::now(), rdtsc());
+    while start.elapsed() < Duration::from_millis(200) {}
+    let (nanos, ticks) = (start.elapsed().as_nanos(), rdtsc() - tsc_start);
+    if ticks == 0 {
+        return Err("tsc is not ticking".to_string());
+    }
+
+    TSC_SCALE.store(((nanos << 32) / (ticks as u128)) as u64, Relaxed);
+    TSC.store(true, Relaxed);
+    Ok(((ticks as u128) * 1_000_000_000 / nanos) as u64)
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+fn is_invariant_tsc() -> Result<(), String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").map_err(|e| format!("{:?}", e))?;
+    let flags: Vec<&str> = match cpuinfo.lines().find(|l| l.starts_with("flags")) {
+        Some(line) => line.split_whitespace().collect(),
+        None => return Err("no cpu flags in /proc/cpuinfo".to_string()),
+    };
+    for flag in ["constant_tsc", "nonstop_tsc"].iter() {
+        if !flags.contains(flag) {
+            return Err(format!("tsc is not invariant, missing {}", flag));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(all(target_arch = "x86_64", target_os = "linux")))]
+fn is_invariant_tsc() -> Result<(), String> {
+    Err("tsc is supported only on x86_64 linux".to_string())
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn rdtsc() -> u64 {
+    unsafe { std::arch::x86_64::_rdtsc() }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+#[inline]
+fn rdtsc() -> u64 {
+    0
+}
@@ -0,0 +1,362 @@
+//! Differential testing across two indexes.
+//!
+//! Same profile generator, hence the same seeded command stream, is
+//! applied to two indexes one after the other. While running the first
+//! index, every read result, and the final full table scan, is recorded
+//! as a count and a digest of its entries, so that memory does not grow
+//! with the size of the results. While running the second index, its
+//! read results are compared with the recorded ones, and the first
+//! mismatch is returned as error.
+//!
+//! Only the entries visible to readers are compared, refer to the
+//! [crate::verify] module for the rationale.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt,
+    hash::Hasher,
+    ops::Bound,
+    sync::{Arc, Mutex},
+};
+
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DiffMode {
+    Record,
+    Compare,
+}
+
+/// Shared between the runs of the two indexes.
+#[derive(Clone)]
+pub struct DiffOpt {
+    pub mode: DiffMode,
+    pub first: String,
+    pub second: String,
+    results: Arc<Mutex<Results>>,
+}
+
+impl DiffOpt {
+    pub fn new(first: &str, second: &str) -> DiffOpt {
+        DiffOpt {
+            mode: DiffMode::Record,
+            first: first.to_string(),
+            second: second.to_string(),
+            results: Default::default(),
+        }
+    }
+
+    pub fn to_compare(&self) -> DiffOpt {
+        let mut opt = self.clone();
+        opt.mode = DiffMode::Compare;
+        opt
+    }
+
+    /// In compare mode, check that every read op recorded from the first
+    /// index was compared, the second index might have run fewer ops.
+    pub fn finish(&self) -> Result<(), String> {
+        let rs = self.results.lock().unwrap();
+        match self.mode {
+            DiffMode::Compare if rs.off != rs.digests.len() => Err(format!(
+                "diff op:{} missing in {}, {} ops recorded",
+                rs.off,
+                self.second,
+                rs.digests.len()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn apply(&self, what: &dyn Fn() -> String, digest: Digest) -> Result<(), String> {
+        let mut rs = self.results.lock().unwrap();
+        match self.mode {
+            DiffMode::Record => rs.digests.push(digest.to_result()),
+            DiffMode::Compare => {
+                let off = rs.off;
+                rs.off += 1;
+                let (n1, d1) = match rs.digests.get(off) {
+                    Some(expected) => *expected,
+                    None => {
+                        let msg = format!("diff {} op:{} missing in {}", what(), off, self.first);
+                        return Err(msg);
+                    }
+                };
+                let (n2, d2) = digest.to_result();
+                if (n1, d1) != (n2, d2) {
+                    return Err(format!(
+                        "diff {} op:{} {}:{{ n={}, digest={:x} }} {}:{{ n={}, digest={:x} }}",
+                        what(),
+                        off,
+                        self.second,
+                        n2,
+                        d2,
+                        self.first,
+                        n1,
+                        d1
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// count and digest of entries returned by every read op of the first
+// index, in the order of ops.
+#[derive(Default)]
+struct Results {
+    digests: Vec<(usize, u64)>,
+    off: usize,
+}
+
+// count and digest of entries returned by a read op, computed from the
+// debug format of key and value, that is available for all key and
+// value types.
+#[derive(Default)]
+struct Digest {
+    n: usize,
+    hasher: DefaultHasher,
+}
+
+impl Digest {
+    fn add<K, V>(&mut self, key: &K, value: &V)
+    where
+        K: fmt::Debug,
+        V: fmt::Debug,
+    {
+        self.n += 1;
+        fmt::Write::write_fmt(self, format_args!("{:?}={:?};", key, value)).ok();
+    }
+
+    fn to_result(&self) -> (usize, u64) {
+        (self.n, self.hasher.finish())
+    }
+}
+
+impl fmt::Write for Digest {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.hasher.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+pub struct Differ<'a, I> {
+    index: &'a mut I,
+    opt: DiffOpt,
+}
+
+impl<'a, I> Differ<'a, I> {
+    pub fn new(index: &'a mut I, opt: DiffOpt) -> Differ<'a, I> {
+        Differ { index, opt }
+    }
+
+    /// Record or compare the full table scan of index.
+    pub fn validate<K, V>(&mut self) -> Result<usize, String>
+    where
+        I: IndexAdapter<K, V>,
+        K: Clone + PartialEq + fmt::Debug,
+        V: Clone + PartialEq + fmt::Debug,
+    {
+        let mut r = IndexAdapter::<K, V>::to_reader(self)?;
+        let mut digest: Digest = Default::default();
+        {
+            let mut visit = |key: &K, value: &V| digest.add(key, value);
+            r.inner.iter(Some(&mut visit))?;
+        }
+
+        let n = digest.n;
+        self.opt.apply(&|| "iter".to_string(), digest)?;
+        Ok(n)
+    }
+}
+
+impl<'a, I, K, V> IndexAdapter<K, V> for Differ<'a, I>
+where
+    I: IndexAdapter<K, V>,
+    K: Clone + PartialEq + fmt::Debug,
+    V: Clone + PartialEq + fmt::Debug,
+{
+    type R = DiffReader<I::R>;
+    type W = I::W;
+
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(DiffReader {
+            inner: self.index.to_reader()?,
+            opt: self.opt.clone(),
+        })
+    }
+
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        self.index.to_writer()
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        self.index.len()
+    }
+}
+
+pub struct DiffReader<R> {
+    inner: R,
+    opt: DiffOpt,
+}
+
+impl<R> DiffReader<R> {
+    /// Wrap reader `inner`, for indexes that hand out readers outside
+    /// of [Differ].
+    pub fn new(inner: R, opt: DiffOpt) -> DiffReader<R> {
+        DiffReader { inner, opt }
+    }
+
+    fn scan<K, V>(
+        &mut self,
+        reverse: bool,
+        low: Bound<K>,
+        high: Bound<K>,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String>
+    where
+        R: ReaderAdapter<K, V>,
+        K: Clone + PartialEq + fmt::Debug,
+        V: Clone + PartialEq + fmt::Debug,
+    {
+        let what = || {
+            let op = if reverse { "reverse" } else { "range" };
+            format!("{} low:{:?} high:{:?}", op, low, high)
+        };
+
+        let mut digest: Digest = Default::default();
+        let n = {
+            let mut collect = |key: &K, value: &V| {
+                digest.add(key, value);
+                adapter::do_visit(&mut visit, key, value);
+            };
+            let (l, h) = (low.clone(), high.clone());
+            if reverse {
+                self.inner.reverse(l, h, Some(&mut collect))?
+            } else {
+                self.inner.range(l, h, Some(&mut collect))?
+            }
+        };
+
+        self.opt.apply(&what, digest)?;
+        Ok(n)
+    }
+
+    fn scan_limit<K, V>(
+        &mut self,
+        reverse: bool,
+        start: &K,
+        limit: usize,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String>
+    where
+        R: ReaderAdapter<K, V>,
+        K: Clone + PartialEq + fmt::Debug,
+        V: Clone + PartialEq + fmt::Debug,
+    {
+        let what = || {
+            let op = if reverse { "reverse_scan" } else { "scan" };
+            format!("{} start:{:?} limit:{}", op, start, limit)
+        };
+
+        let mut digest: Digest = Default::default();
+        let n = {
+            let mut collect = |key: &K, value: &V| {
+                digest.add(key, value);
+                adapter::do_visit(&mut visit, key, value);
+            };
+            if reverse {
+                self.inner.reverse_scan(start, limit, Some(&mut collect))?
+            } else {
+                self.inner.scan(start, limit, Some(&mut collect))?
+            }
+        };
+
+        self.opt.apply(&what, digest)?;
+        Ok(n)
+    }
+}
+
+impl<R, K, V> ReaderAdapter<K, V> for DiffReader<R>
+where
+    R: ReaderAdapter<K, V>,
+    K: Clone + PartialEq + fmt::Debug,
+    V: Clone + PartialEq + fmt::Debug,
+{
+    fn get(&mut self, key: &K, mut visit: Visit<K, V>) -> Result<bool, String> {
+        let mut digest: Digest = Default::default();
+        let found = {
+            let mut collect = |key: &K, value: &V| {
+                digest.add(key, value);
+                adapter::do_visit(&mut visit, key, value);
+            };
+            self.inner.get(key, Some(&mut collect))?
+        };
+
+        self.opt.apply(&|| format!("get key:{:?}", key), digest)?;
+        Ok(found)
+    }
+
+    // forward the batch, so that adapter's own multi_get is compared.
+    fn multi_get(&mut self, keys: &[K], mut visit: Visit<K, V>) -> Result<usize, String> {
+        let mut digest: Digest = Default::default();
+        let n = {
+            let mut collect = |key: &K, value: &V| {
+                digest.add(key, value);
+                adapter::do_visit(&mut visit, key, value);
+            };
+            self.inner.multi_get(keys, Some(&mut collect))?
+        };
+        if n != digest.n {
+            return Err(format!(
+                "diff multi_get keys:{} found:{} visited:{}",
+                keys.len(),
+                n,
+                digest.n
+            ));
+        }
+
+        self.opt
+            .apply(&|| format!("multi_get keys:{}", keys.len()), digest)?;
+        Ok(n)
+    }
+
+    fn range(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        self.scan(false /*reverse*/, low, high, visit)
+    }
+
+    fn reverse(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        self.scan(true /*reverse*/, low, high, visit)
+    }
+
+    fn scan(&mut self, start: &K, limit: usize, visit: Visit<K, V>) -> Result<usize, String> {
+        self.scan_limit(false /*reverse*/, start, limit, visit)
+    }
+
+    fn reverse_scan(
+        &mut self,
+        start: &K,
+        limit: usize,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        self.scan_limit(true /*reverse*/, start, limit, visit)
+    }
+
+    fn iter(&mut self, visit: Visit<K, V>) -> Result<usize, String> {
+        self.inner.iter(visit)
+    }
+}
+
+#[cfg(test)]
+#[path = "differential_test.rs"]
+mod differential_test;
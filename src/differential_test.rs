@@ -0,0 +1,87 @@
+use std::{cell::RefCell, collections::BTreeMap, ops::Bound, rc::Rc};
+
+use crate::adapter::WriterAdapter;
+use crate::mod_btree_map::BTreeMapIndex;
+
+use super::*;
+
+#[test]
+fn test_diff() {
+    let opt = DiffOpt::new("first", "second");
+    assert_eq!(run(opt.clone(), &|_| ()), Ok(100));
+    assert_eq!(run(opt.to_compare(), &|_| ()), Ok(100));
+    assert_eq!(opt.to_compare().finish(), Ok(()));
+}
+
+#[test]
+fn test_diff_value() {
+    let opt = DiffOpt::new("first", "second");
+    run(opt.clone(), &|_| ()).unwrap();
+    let err = run(opt.to_compare(), &|map| {
+        map.insert(50, 0);
+    })
+    .unwrap_err();
+    assert!(
+        err.starts_with("diff get key:50 op:0 second:{ n=1, digest="),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_diff_missing() {
+    let opt = DiffOpt::new("first", "second");
+    run(opt.clone(), &|_| ()).unwrap();
+    let err = run(opt.to_compare(), &|map| {
+        map.remove(&99);
+    })
+    .unwrap_err();
+    let prefix = "diff reverse low:Excluded(40) high:Unbounded op:3 second:{ n=58, digest=";
+    assert!(err.starts_with(prefix), "{}", err);
+    assert!(err.contains(" first:{ n=59, digest="), "{}", err);
+}
+
+#[test]
+fn test_diff_more_ops() {
+    let opt = DiffOpt::new("first", "second");
+    run(opt.clone(), &|_| ()).unwrap();
+    run(opt.to_compare(), &|_| ()).unwrap();
+    // recorded results are consumed by the first compare.
+    let err = run(opt.to_compare(), &|_| ()).unwrap_err();
+    assert_eq!(err, "diff get key:50 op:7 missing in first");
+}
+
+#[test]
+fn test_diff_fewer_ops() {
+    let opt = DiffOpt::new("first", "second");
+    run(opt.clone(), &|_| ()).unwrap();
+    run(opt.clone(), &|_| ()).unwrap();
+    // second index runs half the ops recorded from the first index.
+    run(opt.to_compare(), &|_| ()).unwrap();
+    let err = opt.to_compare().finish().unwrap_err();
+    assert_eq!(err, "diff op:7 missing in second, 14 ops recorded");
+}
+
+// load keys 0..100, `modify` the index behind the differ, and apply a
+// read op of every kind, return the number of entries in the index.
+fn run(opt: DiffOpt, modify: &dyn Fn(&mut BTreeMap<u64, u64>)) -> Result<usize, String> {
+    let mut inner = BTreeMapIndex(Rc::new(RefCell::new(BTreeMap::new())));
+    let map = Rc::clone(&inner.0);
+    let mut index = Differ::new(&mut inner, opt);
+
+    let mut w = IndexAdapter::<u64, u64>::to_writer(&mut index)?;
+    for key in 0..100_u64 {
+        w.load(key, key)?;
+    }
+    modify(&mut map.borrow_mut());
+
+    let mut r = IndexAdapter::<u64, u64>::to_reader(&mut index)?;
+    r.get(&50, None)?;
+    r.multi_get(&[10, 50, 200], None)?;
+    r.range(Bound::Included(40), Bound::Excluded(60), None)?;
+    r.reverse(Bound::Excluded(40), Bound::Unbounded, None)?;
+    ReaderAdapter::scan(&mut r, &45, 10, None)?;
+    r.reverse_scan(&55, 10, None)?;
+
+    index.validate::<u64, u64>()
+}
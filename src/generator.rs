@@ -7,24 +7,68 @@ use std::{
     cmp,
     mem,
     ops::Bound,
-    time,
+    sync::{
+        atomic::{AtomicU64, Ordering::SeqCst},
+        mpsc, Arc,
+    },
+    thread, time, vec,
 };
 
 use crate::utils;
 
+// skew used by YCSB for zipfian and latest key distributions.
+const ZIPF_THETA: f64 = 0.99;
+
 #[derive(Default, Clone)]
 pub struct GenOptions {
     pub seed: u128,
+    pub workload: String,
+    pub ops: usize,
+    pub key_distribution: String,
     pub key_size: usize,
     pub val_size: usize,
     pub loads: usize,
     pub sets: usize,
     pub deletes: usize,
+    pub rmws: usize,
+    pub set_if_absents: usize,
+    pub set_if_presents: usize,
     pub gets: usize,
+    pub multi_gets: usize,
     pub ranges: usize,
     pub reverses: usize,
+    pub scans: usize,
+    pub reverse_scans: usize,
     pub iters: bool,
     pub channel_size: usize,
+    pub gen_mode: String,
+    pub prefetch: usize,
+    // range and reverse
+    pub range_selectivity: f64,
+    pub scan_length: usize,
+    pub bound_included: usize,
+    pub bound_excluded: usize,
+    pub bound_unbounded: usize,
+    // scan and reverse_scan
+    pub scan_limit: usize,
+    pub scan_limit_distribution: String,
+    // multi_get
+    pub multi_get_size: usize,
+    pub multi_get_min_size: usize,
+    pub multi_get_distribution: String,
+    // get and delete
+    pub get_miss_ratio: Option<f64>,
+    pub delete_miss_ratio: Option<f64>,
+    // set
+    pub insert_ratio: Option<f64>,
+    // number of keys inserted beyond the loaded keyspace, shared by all
+    // generators of the run. Inserts are numbered when generated, and
+    // counted as applied only after the index has accepted them.
+    inserts: Arc<AtomicU64>,
+    applied: Arc<AtomicU64>,
+    // number of keys deleted, from the start of the keyspace, shared by
+    // all generators of the run, refer [GenOptions::gen_delete_number].
+    deleted: Arc<AtomicU64>,
     // from rdms
     pub initial: usize,
 }
@@ -33,14 +77,234 @@ impl GenOptions {
     pub fn reset_writes(&mut self) {
         self.sets = 0;
         self.deletes = 0;
+        self.rmws = 0;
+        self.set_if_absents = 0;
+        self.set_if_presents = 0;
     }
 
     pub fn read_ops(&self) -> usize {
-        self.gets + self.ranges + self.reverses
+        self.gets + self.multi_gets + self.ranges + self.reverses + self.scans + self.reverse_scans
     }
 
     pub fn write_ops(&self) -> usize {
-        self.sets + self.deletes
+        self.sets + self.deletes + self.rmws + self.set_if_absents + self.set_if_presents
+    }
+
+    /// Number of unique keys that can be generated, keys are numbered
+    /// from 0 to keyspace-1. Keyspace grows with every applied insert,
+    /// generated inserts that are yet to be applied are not part of
+    /// the keyspace.
+    pub fn to_keyspace(&self) -> u64 {
+        self.to_loaded_keyspace() + self.applied.load(SeqCst)
+    }
+
+    /// Insert command was applied to the index, grow the keyspace.
+    pub fn insert_applied(&self) {
+        self.applied.fetch_add(1, SeqCst);
+    }
+
+    fn to_loaded_keyspace(&self) -> u64 {
+        cmp::max((self.loads * cmp::max(self.initial, 1)) as u64, 1)
+    }
+
+    // keys numbered below this are deleted.
+    fn to_deleted(&self) -> u64 {
+        self.deleted.load(SeqCst)
+    }
+
+    /// Start a new run, with keyspace same as the loaded keyspace.
+    pub fn reset_inserts(&mut self) {
+        self.inserts = Default::default();
+        self.applied = Default::default();
+        self.deleted = Default::default();
+    }
+
+    /// Hit and miss are controlled when get_miss_ratio, delete_miss_ratio
+    /// or insert_ratio is configured. Then every key in the keyspace is
+    /// loaded exactly once, as key number 2*n, and misses are drawn from
+    /// odd numbered keys that are never written.
+    pub fn is_controlled(&self) -> bool {
+        self.get_miss_ratio.is_some()
+            || self.delete_miss_ratio.is_some()
+            || self.insert_ratio.is_some()
+            || self.is_skewed()
+    }
+
+    // zipfian and latest key distributions pick from written keys.
+    fn is_skewed(&self) -> bool {
+        match self.key_distribution.as_str() {
+            "" | "uniform" => false,
+            _ => true,
+        }
+    }
+
+    /// Configure op mix and key distribution same as YCSB core
+    /// workloads, "ycsb-a" to "ycsb-f", for `ops` operations, or as many
+    /// operations as loads if `ops` is ZERO. Replaces the op mix
+    /// configured by other options.
+    pub fn set_workload(&mut self, workload: &str) -> Result<(), String> {
+        let n = if self.ops > 0 { self.ops } else { self.loads };
+        let percent = |p: usize| (n * p) / 100;
+
+        self.reset_writes();
+        self.gets = 0;
+        self.multi_gets = 0;
+        self.ranges = 0;
+        self.reverses = 0;
+        self.scans = 0;
+        self.reverse_scans = 0;
+        self.insert_ratio = None;
+        self.key_distribution = "zipfian".to_string();
+        match workload {
+            // update heavy.
+            "ycsb-a" => {
+                self.gets = percent(50);
+                self.sets = n - self.gets;
+                self.insert_ratio = Some(0.0);
+            }
+            // read mostly.
+            "ycsb-b" => {
+                self.gets = percent(95);
+                self.sets = n - self.gets;
+                self.insert_ratio = Some(0.0);
+            }
+            // read only.
+            "ycsb-c" => self.gets = n,
+            // read latest.
+            "ycsb-d" => {
+                self.gets = percent(95);
+                self.sets = n - self.gets;
+                self.insert_ratio = Some(1.0);
+                self.key_distribution = "latest".to_string();
+            }
+            // short ranges.
+            "ycsb-e" => {
+                self.scans = percent(95);
+                self.sets = n - self.scans;
+                self.insert_ratio = Some(1.0);
+                self.scan_limit = 100;
+                self.scan_limit_distribution = "uniform".to_string();
+            }
+            // read-modify-write.
+            "ycsb-f" => {
+                self.gets = percent(50);
+                self.rmws = n - self.gets;
+            }
+            workload => return Err(format!("invalid workload {}", workload)),
+        }
+        self.workload = workload.to_string();
+        self.validate()
+    }
+
+    // latest keys are the keys inserted, and applied, during the phase,
+    // pregen generates all the reads before the first insert is applied.
+    fn validate(&self) -> Result<(), String> {
+        match (self.key_distribution.as_str(), self.gen_mode.as_str()) {
+            ("latest", "pregen") => {
+                Err("latest key_distribution needs gen_mode thread or inline".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // number of a written key, between deleted and keyspace-1, picked
+    // as per key_distribution, so that it is never a deleted key.
+    fn gen_key_number(&self, rng: &mut SmallRng) -> u64 {
+        let (deleted, keyspace) = (self.to_deleted(), self.to_keyspace());
+        let live = keyspace - deleted;
+        match self.key_distribution.as_str() {
+            // popular keys are scattered across the keyspace.
+            "zipfian" => deleted + (fnv1a(zipf(rng, live, ZIPF_THETA)) % live),
+            // recently inserted keys are the most popular, keyspace
+            // grows only with applied inserts, so that latest reads
+            // never pick a key that is yet to be inserted.
+            "latest" => keyspace - zipf(rng, live, ZIPF_THETA),
+            _ => deleted + (rng.gen::<u64>() % live),
+        }
+    }
+
+    // number of the key to be deleted next. Keys are deleted in order,
+    // from the start of the keyspace, and are not picked by any other
+    // operation afterwards. None if that would delete the last key.
+    fn gen_delete_number(&self) -> Option<u64> {
+        let keyspace = self.to_keyspace();
+        let mut n = self.to_deleted();
+        loop {
+            if (n + 1) >= keyspace {
+                break None;
+            }
+            match self.deleted.compare_exchange(n, n + 1, SeqCst, SeqCst) {
+                Ok(_) => break Some(n),
+                Err(m) => n = m,
+            }
+        }
+    }
+
+    // number of the key for n-th load, (n * step) % keyspace walks the
+    // entire keyspace when step and keyspace are co-prime.
+    fn to_load_number(&self, n: u64) -> u64 {
+        let keyspace = self.to_loaded_keyspace();
+        let mut step = 0x9E37_79B9_7F4A_7C15_u64 % keyspace;
+        while gcd(step, keyspace) != 1 {
+            step += 1;
+        }
+        ((n as u128 * step as u128) % (keyspace as u128)) as u64
+    }
+
+    // width of range and reverse operations, as number of keys in the
+    // keyspace.
+    fn to_scan_width(&self, rng: &mut SmallRng) -> u64 {
+        let keyspace = self.to_keyspace() - self.to_deleted();
+        let width = if self.range_selectivity > 0.0 {
+            ((keyspace as f64) * self.range_selectivity).ceil() as u64
+        } else if self.scan_length > 0 {
+            self.scan_length as u64
+        } else {
+            rng.gen::<u64>() % (keyspace + 1)
+        };
+        cmp::min(width, keyspace)
+    }
+
+    // number of entries to iterate for scan and reverse_scan, constant
+    // or uniformly distributed between 1 and scan_limit.
+    fn to_scan_limit(&self, rng: &mut SmallRng) -> usize {
+        let limit = cmp::max(self.scan_limit, 1);
+        match self.scan_limit_distribution.as_str() {
+            "uniform" => (rng.gen::<usize>() % limit) + 1,
+            _ => limit,
+        }
+    }
+
+    // number of keys in a multi_get batch, constant or uniformly
+    // distributed between multi_get_min_size and multi_get_size.
+    fn to_multi_get_size(&self, rng: &mut SmallRng) -> usize {
+        let size = cmp::max(self.multi_get_size, 1);
+        let min_size = cmp::min(cmp::max(self.multi_get_min_size, 1), size);
+        match self.multi_get_distribution.as_str() {
+            "uniform" => min_size + (rng.gen::<usize>() % (size - min_size + 1)),
+            _ => size,
+        }
+    }
+
+    // pick Included (0), Excluded (1) or Unbounded (2), weighted by the
+    // bound_* options, equally weighted if none are configured.
+    fn to_bound_kind(&self, rng: &mut SmallRng) -> u8 {
+        let mut weights = [
+            self.bound_included,
+            self.bound_excluded,
+            self.bound_unbounded,
+        ];
+        if weights.iter().sum::<usize>() == 0 {
+            weights = [1, 1, 1];
+        }
+        let mut r = rng.gen::<usize>() % weights.iter().sum::<usize>();
+        for (kind, weight) in weights.iter().enumerate() {
+            if r < *weight {
+                return kind as u8;
+            }
+            r -= weight;
+        }
+        unreachable!()
     }
 }
 
@@ -52,26 +316,204 @@ impl TryFrom<toml::Value> for GenOptions {
         for (name, value) in section.as_table().unwrap().iter() {
             match name.as_str() {
                 "seed" => gen_opts.seed = utils::toml_to_u128(value),
+                "workload" => gen_opts.workload = utils::toml_to_string(value),
+                "ops" => gen_opts.ops = utils::toml_to_usize(value),
+                "key_distribution" => {
+                    let dist = utils::toml_to_string(value);
+                    match dist.as_str() {
+                        "uniform" | "zipfian" | "latest" => (),
+                        dist => return Err(format!("invalid key_distribution {}", dist)),
+                    }
+                    gen_opts.key_distribution = dist
+                }
                 "key_size" => gen_opts.key_size = utils::toml_to_usize(value),
                 "value_size" => gen_opts.val_size = utils::toml_to_usize(value),
                 "channel_size" => {
                     // something
                     gen_opts.channel_size = utils::toml_to_usize(value)
                 }
+                "gen_mode" => {
+                    let mode = utils::toml_to_string(value);
+                    match mode.as_str() {
+                        "thread" | "pregen" | "inline" => (),
+                        mode => return Err(format!("invalid gen_mode {}", mode)),
+                    }
+                    gen_opts.gen_mode = mode
+                }
+                "prefetch" => gen_opts.prefetch = utils::toml_to_usize(value),
                 "loads" => gen_opts.loads = utils::toml_to_usize(value),
                 "sets" => gen_opts.sets = utils::toml_to_usize(value),
                 "deletes" => gen_opts.deletes = utils::toml_to_usize(value),
+                "rmws" => gen_opts.rmws = utils::toml_to_usize(value),
+                "set_if_absents" => gen_opts.set_if_absents = utils::toml_to_usize(value),
+                "set_if_presents" => gen_opts.set_if_presents = utils::toml_to_usize(value),
                 "gets" => gen_opts.gets = utils::toml_to_usize(value),
+                "multi_gets" => gen_opts.multi_gets = utils::toml_to_usize(value),
+                "multi_get_size" => gen_opts.multi_get_size = utils::toml_to_usize(value),
+                "multi_get_min_size" => gen_opts.multi_get_min_size = utils::toml_to_usize(value),
+                "multi_get_distribution" => {
+                    let dist = utils::toml_to_string(value);
+                    match dist.as_str() {
+                        "constant" | "uniform" => (),
+                        dist => return Err(format!("invalid multi_get_distribution {}", dist)),
+                    }
+                    gen_opts.multi_get_distribution = dist
+                }
                 "ranges" => gen_opts.ranges = utils::toml_to_usize(value),
                 "reverses" => gen_opts.reverses = utils::toml_to_usize(value),
+                "scans" => gen_opts.scans = utils::toml_to_usize(value),
+                "reverse_scans" => gen_opts.reverse_scans = utils::toml_to_usize(value),
                 "iters" => gen_opts.iters = utils::toml_to_bool(value),
+                "range_selectivity" => {
+                    let selectivity = utils::toml_to_f64(value);
+                    if selectivity < 0.0 || selectivity > 1.0 {
+                        let msg = format!("invalid range_selectivity {}", selectivity);
+                        return Err(msg);
+                    }
+                    gen_opts.range_selectivity = selectivity
+                }
+                "scan_length" => gen_opts.scan_length = utils::toml_to_usize(value),
+                "bound_included" => gen_opts.bound_included = utils::toml_to_usize(value),
+                "bound_excluded" => gen_opts.bound_excluded = utils::toml_to_usize(value),
+                "bound_unbounded" => gen_opts.bound_unbounded = utils::toml_to_usize(value),
+                "scan_limit" => gen_opts.scan_limit = utils::toml_to_usize(value),
+                "scan_limit_distribution" => {
+                    let dist = utils::toml_to_string(value);
+                    match dist.as_str() {
+                        "constant" | "uniform" => (),
+                        dist => return Err(format!("invalid scan_limit_distribution {}", dist)),
+                    }
+                    gen_opts.scan_limit_distribution = dist
+                }
+                "get_miss_ratio" | "delete_miss_ratio" | "insert_ratio" => {
+                    let ratio = utils::toml_to_f64(value);
+                    if ratio < 0.0 || ratio > 1.0 {
+                        return Err(format!("invalid {} {}", name, ratio));
+                    }
+                    match name.as_str() {
+                        "get_miss_ratio" => gen_opts.get_miss_ratio = Some(ratio),
+                        "delete_miss_ratio" => gen_opts.delete_miss_ratio = Some(ratio),
+                        _ => gen_opts.insert_ratio = Some(ratio),
+                    }
+                }
                 _ => return Err(format!("invalid generator option {}", name)),
             }
         }
+        if gen_opts.workload.len() > 0 {
+            let workload = gen_opts.workload.clone();
+            gen_opts.set_workload(&workload)?;
+        }
+        gen_opts.validate()?;
         Ok(gen_opts)
     }
 }
 
+/// Commands for a phase, generated as per `gen_mode`:
+///
+/// * "thread", default, generate in a background thread, prefetching
+///   upto `prefetch` batches of `channel_size` commands.
+/// * "pregen", generate all the commands in memory before the phase.
+/// * "inline", generate in the calling thread, as part of the phase.
+///
+/// Time spent waiting for the generator is accounted as stalled.
+pub struct CmdSource<K, V> {
+    inner: Source<K, V>,
+    stalled: time::Duration,
+}
+
+enum Source<K, V> {
+    Thread {
+        rx: mpsc::Receiver<Vec<Cmd<K, V>>>,
+        batch: vec::IntoIter<Cmd<K, V>>,
+    },
+    Pregen(vec::IntoIter<Cmd<K, V>>),
+    Inline(Box<dyn Iterator<Item = Cmd<K, V>> + Send>),
+}
+
+impl<K, V> CmdSource<K, V>
+where
+    K: 'static + Send,
+    V: 'static + Send,
+{
+    pub fn new<G>(gen: G, g: &GenOptions) -> CmdSource<K, V>
+    where
+        G: 'static + Send + Iterator<Item = Cmd<K, V>>,
+    {
+        let inner = match g.gen_mode.as_str() {
+            "pregen" => {
+                let start = time::Instant::now();
+                let cmds: Vec<Cmd<K, V>> = gen.collect();
+                debug!(
+                    target: "genrtr",
+                    "pregen: generated {} items in {:?}",
+                    cmds.len(), start.elapsed()
+                );
+                Source::Pregen(cmds.into_iter())
+            }
+            "inline" => Source::Inline(Box::new(gen)),
+            _ => {
+                let size = cmp::max(g.channel_size, 1);
+                let (tx, rx) = mpsc::sync_channel(cmp::max(g.prefetch, 1));
+                thread::spawn(move || {
+                    let mut gen = gen;
+                    loop {
+                        let batch: Vec<Cmd<K, V>> = gen.by_ref().take(size).collect();
+                        // stop when generator is exhausted, or the
+                        // consumer is gone.
+                        if batch.len() == 0 || tx.send(batch).is_err() {
+                            break;
+                        }
+                    }
+                });
+                Source::Thread {
+                    rx,
+                    batch: vec![].into_iter(),
+                }
+            }
+        };
+        CmdSource {
+            inner,
+            stalled: Default::default(),
+        }
+    }
+
+    /// Return the time spent waiting for the generator.
+    pub fn to_stalled(&self) -> time::Duration {
+        self.stalled
+    }
+}
+
+impl<K, V> Iterator for CmdSource<K, V> {
+    type Item = Cmd<K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            Source::Thread { rx, batch } => match batch.next() {
+                Some(cmd) => Some(cmd),
+                None => {
+                    let start = time::Instant::now();
+                    let res = rx.recv();
+                    self.stalled += start.elapsed();
+                    match res {
+                        Ok(cmds) => {
+                            *batch = cmds.into_iter();
+                            batch.next()
+                        }
+                        Err(_) => None,
+                    }
+                }
+            },
+            Source::Pregen(cmds) => cmds.next(),
+            Source::Inline(gen) => {
+                let start = time::Instant::now();
+                let cmd = gen.next();
+                self.stalled += start.elapsed();
+                cmd
+            }
+        }
+    }
+}
+
 pub struct InitialLoad<K, V>
 where
     K: Clone + Default + RandomKV,
@@ -79,6 +521,7 @@ where
 {
     g: GenOptions,
     n_load: usize,
+    offset: u64,
     rng: SmallRng,
     items: Vec<Cmd<K,V>>,
     elapsed: time::Duration,
@@ -90,10 +533,17 @@ where
     V: Clone + Default + RandomKV,
 {
     pub fn new(g: GenOptions) -> InitialLoad<K, V> {
+        InitialLoad::with_id(g, 0)
+    }
+
+    /// Initial load by loader `id`, when hit and miss are controlled
+    /// each loader loads its own share of the keyspace.
+    pub fn with_id(g: GenOptions, id: usize) -> InitialLoad<K, V> {
         let rng = SmallRng::from_seed(g.seed.to_le_bytes());
         InitialLoad {
             g: g.clone(),
             n_load: g.loads,
+            offset: (id * g.loads) as u64,
             rng,
             items: Default::default(),
             elapsed: Default::default(),
@@ -124,12 +574,19 @@ where
                 None
             }
             None => {
-                let start = time::SystemTime::now();
+                let start = time::Instant::now();
                 let n = cmp::min(self.n_load, self.g.channel_size);
-                for _ in 0..n {
-                    self.items.push(Cmd::gen_load(&mut self.rng, &self.g));
+                let base = self.offset + (self.g.loads - self.n_load) as u64;
+                for i in 0..n {
+                    let cmd = if self.g.is_controlled() {
+                        Cmd::gen_load_nth(&mut self.rng, &self.g, base + (i as u64))
+                    } else {
+                        Cmd::gen_load(&mut self.rng, &self.g)
+                    };
+                    self.items.push(cmd);
                 }
-                self.elapsed += start.elapsed().unwrap();
+                self.items.reverse();
+                self.elapsed += start.elapsed();
                 self.n_load -= n;
                 self.items.pop()
             }
@@ -145,8 +602,11 @@ where
 {
     g: GenOptions,
     n_gets: usize,
+    n_multi_gets: usize,
     n_ranges: usize,
     n_reverses: usize,
+    n_scans: usize,
+    n_reverse_scans: usize,
     rng: SmallRng,
     items: Vec<Cmd<K,V>>,
     elapsed: time::Duration,
@@ -162,8 +622,11 @@ where
         IncrementalRead {
             g: g.clone(),
             n_gets: g.gets,
+            n_multi_gets: g.multi_gets,
             n_ranges: g.ranges,
             n_reverses: g.reverses,
+            n_scans: g.scans,
+            n_reverse_scans: g.reverse_scans,
             rng,
             items: Default::default(),
             elapsed: Default::default(),
@@ -179,11 +642,16 @@ where
     }
 
     fn to_n_total(&self) -> usize {
-        self.n_gets + self.n_ranges + self.n_reverses
+        self.n_gets
+            + self.n_multi_gets
+            + self.n_ranges
+            + self.n_reverses
+            + self.n_scans
+            + self.n_reverse_scans
     }
 
     fn to_total(&self) -> usize {
-        self.g.gets + self.g.ranges + self.g.reverses
+        self.g.read_ops()
     }
 }
 
@@ -202,25 +670,38 @@ where
                 None
             }
             None => {
-                let start = time::SystemTime::now();
+                let start = time::Instant::now();
                 let n = cmp::min(self.to_n_total(), self.g.channel_size);
                 for _ in 0..n {
                     let r: usize = self.rng.gen::<usize>() % self.to_n_total();
+                    let n_gets = self.n_gets + self.n_multi_gets;
                     let cmd = if r < self.n_gets {
                         self.n_gets -= 1;
                         Cmd::gen_get(&mut self.rng, &self.g)
-                    } else if r < (self.n_gets + self.n_ranges) {
+                    } else if r < n_gets {
+                        self.n_multi_gets -= 1;
+                        Cmd::gen_multi_get(&mut self.rng, &self.g)
+                    } else if r < (n_gets + self.n_ranges) {
                         self.n_ranges -= 1;
                         Cmd::gen_range(&mut self.rng, &self.g)
-                    } else if r < self.to_n_total() {
+                    } else if r < (n_gets + self.n_ranges + self.n_reverses) {
                         self.n_reverses -= 1;
                         Cmd::gen_reverse(&mut self.rng, &self.g)
+                    } else if r < (self.to_n_total() - self.n_reverse_scans) {
+                        self.n_scans -= 1;
+                        Cmd::gen_scan(&mut self.rng, &self.g)
+                    } else if r < self.to_n_total() {
+                        self.n_reverse_scans -= 1;
+                        Cmd::gen_reverse_scan(&mut self.rng, &self.g)
                     } else {
                         unreachable!();
                     };
                     self.items.push(cmd);
                 }
-                self.elapsed += start.elapsed().unwrap();
+                // deliver in the order of generation, inserts are
+                // numbered in that order.
+                self.items.reverse();
+                self.elapsed += start.elapsed();
                 self.items.pop()
             }
         }
@@ -235,6 +716,9 @@ where
     g: GenOptions,
     n_sets: usize,
     n_deletes: usize,
+    n_rmws: usize,
+    n_set_if_absents: usize,
+    n_set_if_presents: usize,
     rng: SmallRng,
     items: Vec<Cmd<K,V>>,
     elapsed: time::Duration,
@@ -251,6 +735,9 @@ where
             g: g.clone(),
             n_sets: g.sets,
             n_deletes: g.deletes,
+            n_rmws: g.rmws,
+            n_set_if_absents: g.set_if_absents,
+            n_set_if_presents: g.set_if_presents,
             rng,
             items: Default::default(),
             elapsed: Default::default(),
@@ -266,11 +753,11 @@ where
     }
 
     fn to_n_total(&self) -> usize {
-        self.n_sets + self.n_deletes
+        self.n_sets + self.n_deletes + self.n_rmws + self.n_set_if_absents + self.n_set_if_presents
     }
 
     fn to_total(&self) -> usize {
-        self.g.sets + self.g.deletes
+        self.g.write_ops()
     }
 }
 
@@ -289,22 +776,34 @@ where
                 None
             }
             None => {
-                let start = time::SystemTime::now();
+                let start = time::Instant::now();
                 let n = cmp::min(self.to_n_total(), self.g.channel_size);
                 for _ in 0..n {
                     let r: usize = self.rng.gen::<usize>() % self.to_n_total();
                     let cmd = if r < self.n_sets {
                         self.n_sets -= 1;
                         Cmd::gen_set(&mut self.rng, &self.g)
-                    } else if r < self.to_n_total() {
+                    } else if r < (self.n_sets + self.n_deletes) {
                         self.n_deletes -= 1;
                         Cmd::gen_del(&mut self.rng, &self.g)
+                    } else if r < (self.n_sets + self.n_deletes + self.n_rmws) {
+                        self.n_rmws -= 1;
+                        Cmd::gen_rmw(&mut self.rng, &self.g)
+                    } else if r < (self.to_n_total() - self.n_set_if_presents) {
+                        self.n_set_if_absents -= 1;
+                        Cmd::gen_set_if_absent(&mut self.rng, &self.g)
+                    } else if r < self.to_n_total() {
+                        self.n_set_if_presents -= 1;
+                        Cmd::gen_set_if_present(&mut self.rng, &self.g)
                     } else {
                         unreachable!();
                     };
                     self.items.push(cmd);
                 }
-                self.elapsed += start.elapsed().unwrap();
+                // deliver in the order of generation, inserts are
+                // numbered in that order.
+                self.items.reverse();
+                self.elapsed += start.elapsed();
                 self.items.pop()
             }
         }
@@ -318,10 +817,16 @@ where
 {
     g: GenOptions,
     n_gets: usize,
+    n_multi_gets: usize,
     n_ranges: usize,
     n_reverses: usize,
+    n_scans: usize,
+    n_reverse_scans: usize,
     n_sets: usize,
     n_deletes: usize,
+    n_rmws: usize,
+    n_set_if_absents: usize,
+    n_set_if_presents: usize,
     rng: SmallRng,
     items: Vec<Cmd<K,V>>,
     elapsed: time::Duration,
@@ -337,10 +842,16 @@ where
         IncrementalLoad {
             g: g.clone(),
             n_gets: g.gets,
+            n_multi_gets: g.multi_gets,
             n_ranges: g.ranges ,
             n_reverses: g.reverses,
+            n_scans: g.scans,
+            n_reverse_scans: g.reverse_scans,
             n_sets: g.sets,
             n_deletes: g.deletes,
+            n_rmws: g.rmws,
+            n_set_if_absents: g.set_if_absents,
+            n_set_if_presents: g.set_if_presents,
             rng,
             items: Default::default(),
             elapsed: Default::default(),
@@ -356,15 +867,21 @@ where
     }
 
     fn to_n_total(&self) -> usize {
-        self.n_gets + self.n_ranges + self.n_reverses +
+        self.n_gets + self.n_multi_gets + self.n_ranges + self.n_reverses +
         //
-        self.n_sets + self.n_deletes
+        self.n_scans + self.n_reverse_scans +
+        //
+        self.n_sets + self.n_deletes +
+        //
+        self.n_rmws + self.n_set_if_absents + self.n_set_if_presents
     }
 
     fn to_total(&self) -> usize {
-        self.g.gets + self.g.ranges + self.g.reverses +
-        //
-        self.g.sets + self.g.deletes
+        self.g.read_ops() + self.g.write_ops()
+    }
+
+    fn to_n_writes(&self) -> usize {
+        self.n_sets + self.n_deletes + self.n_rmws + self.n_set_if_absents + self.n_set_if_presents
     }
 }
 
@@ -383,31 +900,55 @@ where
                 None
             }
             None => {
-                let start = time::SystemTime::now();
+                let start = time::Instant::now();
                 let n = cmp::min(self.to_n_total(), self.g.channel_size);
                 for _ in 0..n {
                     let r: usize = self.rng.gen::<usize>() % self.to_n_total();
+                    let n_gets = self.n_gets + self.n_multi_gets;
                     let cmd = if r < self.n_gets {
                         self.n_gets -= 1;
                         Cmd::gen_get(&mut self.rng, &self.g)
-                    } else if r < (self.n_gets + self.n_ranges) {
+                    } else if r < n_gets {
+                        self.n_multi_gets -= 1;
+                        Cmd::gen_multi_get(&mut self.rng, &self.g)
+                    } else if r < (n_gets + self.n_ranges) {
                         self.n_ranges -= 1;
                         Cmd::gen_range(&mut self.rng, &self.g)
-                    } else if r < (self.n_gets + self.n_ranges + self.n_reverses) {
+                    } else if r < (n_gets + self.n_ranges + self.n_reverses) {
                         self.n_reverses -= 1;
                         Cmd::gen_reverse(&mut self.rng, &self.g)
-                    } else if r < (self.n_gets + self.n_ranges + self.n_reverses +self.n_sets) {
+                    } else if r < (self.to_n_total() - self.to_n_writes() - self.n_reverse_scans) {
+                        self.n_scans -= 1;
+                        Cmd::gen_scan(&mut self.rng, &self.g)
+                    } else if r < (self.to_n_total() - self.to_n_writes()) {
+                        self.n_reverse_scans -= 1;
+                        Cmd::gen_reverse_scan(&mut self.rng, &self.g)
+                    } else if r < (self.to_n_total() - self.to_n_writes() + self.n_sets) {
                         self.n_sets -= 1;
                         Cmd::gen_set(&mut self.rng, &self.g)
-                    } else if r < self.to_n_total() {
+                    } else if r < (self.to_n_total() - self.to_n_writes() + self.n_sets + self.n_deletes)
+                    {
                         self.n_deletes -= 1;
                         Cmd::gen_del(&mut self.rng, &self.g)
+                    } else if r < (self.to_n_total() - self.n_set_if_absents - self.n_set_if_presents)
+                    {
+                        self.n_rmws -= 1;
+                        Cmd::gen_rmw(&mut self.rng, &self.g)
+                    } else if r < (self.to_n_total() - self.n_set_if_presents) {
+                        self.n_set_if_absents -= 1;
+                        Cmd::gen_set_if_absent(&mut self.rng, &self.g)
+                    } else if r < self.to_n_total() {
+                        self.n_set_if_presents -= 1;
+                        Cmd::gen_set_if_present(&mut self.rng, &self.g)
                     } else {
                         unreachable!();
                     };
                     self.items.push(cmd);
                 }
-                self.elapsed += start.elapsed().unwrap();
+                // deliver in the order of generation, inserts are
+                // numbered in that order.
+                self.items.reverse();
+                self.elapsed += start.elapsed();
                 self.items.pop()
             }
         }
@@ -417,10 +958,41 @@ where
 pub enum Cmd<K, V> {
     Load { key: K, value: V },
     Set { key: K, value: V },
+    // set on a key beyond the keyspace.
+    Insert { key: K, value: V },
+    // set on a key within the keyspace.
+    Update { key: K, value: V },
     Delete { key: K },
     Get { key: K },
+    MultiGet { keys: Vec<K> },
     Range { low: Bound<K>, high: Bound<K> },
     Reverse { low: Bound<K>, high: Bound<K> },
+    Scan { start: K, limit: usize },
+    ReverseScan { start: K, limit: usize },
+    // value is used if key is missing, else the older value is modified.
+    Rmw { key: K, value: V },
+    SetIfAbsent { key: K, value: V },
+    SetIfPresent { key: K, value: V },
+}
+
+impl<K, V> Cmd<K, V> {
+    /// Return true if command inserts a key beyond the keyspace.
+    pub fn is_insert(&self) -> bool {
+        match self {
+            Cmd::Insert { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Return true if command mutates the index.
+    pub fn is_write(&self) -> bool {
+        match self {
+            Cmd::Load { .. } | Cmd::Set { .. } | Cmd::Delete { .. } => true,
+            Cmd::Insert { .. } | Cmd::Update { .. } => true,
+            Cmd::Rmw { .. } | Cmd::SetIfAbsent { .. } | Cmd::SetIfPresent { .. } => true,
+            _ => false,
+        }
+    }
 }
 
 impl<K, V> Cmd<K, V>
@@ -429,52 +1001,140 @@ where
     V: Clone + Default + RandomKV,
 {
     pub fn gen_load(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
+        let value: V = unsafe { mem::zeroed() };
+        Cmd::Load {
+            key: gen_write_key(rng, g),
+            value: value.gen_val(rng, g),
+        }
+    }
+
+    /// Load the n-th key of the keyspace, refer to
+    /// GenOptions::is_controlled.
+    pub fn gen_load_nth(rng: &mut SmallRng, g: &GenOptions, n: u64) -> Cmd<K, V> {
         let (key, value): (K, V) = unsafe { (mem::zeroed(), mem::zeroed()) };
         Cmd::Load {
-            key: key.gen_key(rng, g),
+            key: key.key_of(g.to_load_number(n) * 2, g),
             value: value.gen_val(rng, g),
         }
     }
 
+    /// Generate a Set command, or an Insert or Update command when
+    /// insert_ratio is configured.
     pub fn gen_set(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
-        let (key, value): (K, V) = unsafe { (mem::zeroed(), mem::zeroed()) };
-        Cmd::Set {
-            key: key.gen_key(rng, g),
+        let value: V = unsafe { mem::zeroed() };
+        match g.insert_ratio {
+            None => Cmd::Set {
+                key: gen_write_key(rng, g),
+                value: value.gen_val(rng, g),
+            },
+            Some(ratio) if rng.gen::<f64>() < ratio => Cmd::Insert {
+                key: gen_insert_key(g),
+                value: value.gen_val(rng, g),
+            },
+            Some(_) => Cmd::Update {
+                key: gen_write_key(rng, g),
+                value: value.gen_val(rng, g),
+            },
+        }
+    }
+
+    pub fn gen_rmw(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
+        let value: V = unsafe { mem::zeroed() };
+        Cmd::Rmw {
+            key: gen_write_key(rng, g),
+            value: value.gen_val(rng, g),
+        }
+    }
+
+    pub fn gen_set_if_absent(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
+        let value: V = unsafe { mem::zeroed() };
+        Cmd::SetIfAbsent {
+            key: gen_write_key(rng, g),
+            value: value.gen_val(rng, g),
+        }
+    }
+
+    pub fn gen_set_if_present(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
+        let value: V = unsafe { mem::zeroed() };
+        Cmd::SetIfPresent {
+            key: gen_write_key(rng, g),
             value: value.gen_val(rng, g),
         }
     }
 
+    // with hit and miss controlled, a hit deletes the next key in
+    // order, refer [GenOptions::gen_delete_number].
     pub fn gen_del(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
         let key: K = unsafe { mem::zeroed() };
-        Cmd::Delete {
-            key: key.gen_key(rng, g),
-        }
+        let key = if g.is_controlled() {
+            let miss = rng.gen::<f64>() < g.delete_miss_ratio.unwrap_or(0.0);
+            match g.gen_delete_number() {
+                Some(n) if !miss => key.key_of(n * 2, g),
+                _ => key.key_of((g.gen_key_number(rng) * 2) + 1, g),
+            }
+        } else {
+            key.gen_key(rng, g)
+        };
+        Cmd::Delete { key }
     }
 
     pub fn gen_get(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
-        let key: K = unsafe { mem::zeroed() };
         Cmd::Get {
-            key: key.gen_key(rng, g),
+            key: gen_read_key(rng, g, g.get_miss_ratio),
+        }
+    }
+
+    pub fn gen_multi_get(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
+        let n = g.to_multi_get_size(rng);
+        Cmd::MultiGet {
+            keys: (0..n).map(|_| gen_read_key(rng, g, g.get_miss_ratio)).collect(),
         }
     }
 
     pub fn gen_range(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
-        let low = bounded_key::<K>(rng, g);
-        let high = bounded_key::<K>(rng, g);
+        let (low, high) = range_bounds::<K>(rng, g);
         Cmd::Range { low, high }
     }
 
     pub fn gen_reverse(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
-        let low = bounded_key::<K>(rng, g);
-        let high = bounded_key::<K>(rng, g);
+        let (low, high) = range_bounds::<K>(rng, g);
         Cmd::Reverse { low, high }
     }
+
+    pub fn gen_scan(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
+        Cmd::Scan {
+            start: gen_write_key(rng, g),
+            limit: g.to_scan_limit(rng),
+        }
+    }
+
+    pub fn gen_reverse_scan(rng: &mut SmallRng, g: &GenOptions) -> Cmd<K, V> {
+        Cmd::ReverseScan {
+            start: gen_write_key(rng, g),
+            limit: g.to_scan_limit(rng),
+        }
+    }
 }
 
 pub trait RandomKV {
     fn gen_key(&self, rng: &mut SmallRng, g: &GenOptions) -> Self;
     fn gen_val(&self, rng: &mut SmallRng, g: &GenOptions) -> Self;
     fn next(&self, g: &GenOptions) -> Self;
+    /// Key numbered `n` in the keyspace, keys sort in the same order as
+    /// their numbers.
+    fn key_of(&self, n: u64, g: &GenOptions) -> Self;
+    /// Derive a new value from this value, for read-modify-write.
+    fn modify(&self) -> Self;
+    /// Logical size of key or value in bytes, as written by the
+    /// application, refer [crate::amplify].
+    fn logical_size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+    /// Heap memory owned by key or value in bytes, refer
+    /// [crate::utils::log_memory].
+    fn heap_size(&self) -> usize {
+        0
+    }
 }
 
 impl RandomKV for i32 {
@@ -490,6 +1150,13 @@ impl RandomKV for i32 {
     fn next(&self, _g: &GenOptions) -> i32 {
         *self + 1
     }
+    fn modify(&self) -> i32 {
+        self.wrapping_add(1)
+    }
+
+    fn key_of(&self, n: u64, _g: &GenOptions) -> i32 {
+        n as i32
+    }
 }
 
 impl RandomKV for i64 {
@@ -505,6 +1172,13 @@ impl RandomKV for i64 {
     fn next(&self, _g: &GenOptions) -> i64 {
         *self + 1
     }
+    fn modify(&self) -> i64 {
+        self.wrapping_add(1)
+    }
+
+    fn key_of(&self, n: u64, _g: &GenOptions) -> i64 {
+        n as i64
+    }
 }
 
 impl RandomKV for u64 {
@@ -520,16 +1194,20 @@ impl RandomKV for u64 {
     fn next(&self, _g: &GenOptions) -> u64 {
         *self + 1
     }
+    fn modify(&self) -> u64 {
+        self.wrapping_add(1)
+    }
+
+    fn key_of(&self, n: u64, _g: &GenOptions) -> u64 {
+        n
+    }
 }
 
 impl RandomKV for [u8; 32] {
     fn gen_key(&self, rng: &mut SmallRng, g: &GenOptions) -> [u8; 32] {
         let limit = (g.loads * std::cmp::max(g.initial, 1)) as i64;
         let num = i64::abs(rng.gen::<i64>() % limit);
-        let mut arr = [0_u8; 32];
-        let src = format!("{:032}", num).as_bytes().to_vec();
-        arr.copy_from_slice(&src);
-        arr
+        self.key_of(num as u64, g)
     }
 
     fn gen_val(&self, _rng: &mut SmallRng, _g: &GenOptions) -> [u8; 32] {
@@ -545,16 +1223,25 @@ impl RandomKV for [u8; 32] {
         arr.copy_from_slice(&src);
         arr
     }
+    fn modify(&self) -> [u8; 32] {
+        let mut arr = *self;
+        arr[32 - 1] = arr[32 - 1].wrapping_add(1);
+        arr
+    }
+
+    fn key_of(&self, n: u64, _g: &GenOptions) -> [u8; 32] {
+        let mut arr = [0_u8; 32];
+        let src = format!("{:032}", n).as_bytes().to_vec();
+        arr.copy_from_slice(&src);
+        arr
+    }
 }
 
 impl RandomKV for [u8; 20] {
     fn gen_key(&self, rng: &mut SmallRng, g: &GenOptions) -> [u8; 20] {
         let limit = (g.loads * std::cmp::max(g.initial, 1)) as i64;
         let num = i64::abs(rng.gen::<i64>() % limit);
-        let mut arr = [0_u8; 20];
-        let src = format!("{:020}", num).as_bytes().to_vec();
-        arr.copy_from_slice(&src);
-        arr
+        self.key_of(num as u64, g)
     }
 
     fn gen_val(&self, _rng: &mut SmallRng, _g: &GenOptions) -> [u8; 20] {
@@ -570,6 +1257,18 @@ impl RandomKV for [u8; 20] {
         arr.copy_from_slice(&src);
         arr
     }
+    fn modify(&self) -> [u8; 20] {
+        let mut arr = *self;
+        arr[20 - 1] = arr[20 - 1].wrapping_add(1);
+        arr
+    }
+
+    fn key_of(&self, n: u64, _g: &GenOptions) -> [u8; 20] {
+        let mut arr = [0_u8; 20];
+        let src = format!("{:020}", n).as_bytes().to_vec();
+        arr.copy_from_slice(&src);
+        arr
+    }
 }
 
 impl RandomKV for Vec<u8> {
@@ -579,8 +1278,7 @@ impl RandomKV for Vec<u8> {
 
         let limit = (g.loads * std::cmp::max(g.initial, 1)) as i64;
         let num = i64::abs(rng.gen::<i64>() % limit);
-        let src = format!("{:0width$}", num, width = g.key_size);
-        src.as_bytes().to_vec()
+        self.key_of(num as u64, g)
     }
 
     fn gen_val(&self, _rng: &mut SmallRng, g: &GenOptions) -> Vec<u8> {
@@ -598,6 +1296,26 @@ impl RandomKV for Vec<u8> {
         let src = format!("{:0width$}", n + 1, width = g.key_size);
         src.as_bytes().to_vec()
     }
+    fn modify(&self) -> Vec<u8> {
+        let mut value = self.clone();
+        if let Some(byte) = value.last_mut() {
+            *byte = byte.wrapping_add(1);
+        }
+        value
+    }
+
+    fn key_of(&self, n: u64, g: &GenOptions) -> Vec<u8> {
+        let src = format!("{:0width$}", n, width = g.key_size);
+        src.as_bytes().to_vec()
+    }
+
+    fn logical_size(&self) -> usize {
+        self.len()
+    }
+
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
 }
 
 pub struct IterKeys<K>
@@ -635,16 +1353,155 @@ where
     }
 }
 
-fn bounded_key<K>(rng: &mut SmallRng, g: &GenOptions) -> Bound<K>
+// generate well formed bounds, low <= high, spanning exactly
+// `to_scan_width` keys, at least one, whatever the bound kinds are.
+// Excluded bounds are placed on the key just outside the range,
+// unbounded low starts the range from the first key and unbounded high
+// ends the range at the last key, unbounded on both sides is a full
+// table scan. Width is exact only when hit and miss are controlled,
+// otherwise written keys are not contiguous.
+fn range_bounds<K>(rng: &mut SmallRng, g: &GenOptions) -> (Bound<K>, Bound<K>)
+where
+    K: RandomKV,
+{
+    // deleted keys are not part of the range.
+    let deleted = g.to_deleted();
+    let keyspace = g.to_keyspace() - deleted;
+    if keyspace < 2 {
+        return (Bound::Unbounded, Bound::Unbounded);
+    }
+
+    let width = g.to_scan_width(rng);
+    let (lkind, hkind) = (g.to_bound_kind(rng), g.to_bound_kind(rng));
+    // excluded low bound needs a key before the range.
+    let first = if lkind == 1 { 1 } else { 0 };
+    let width = cmp::min(cmp::max(width, 1), keyspace - first);
+    let start = deleted
+        + match (lkind, hkind) {
+            (2, _) => 0,
+            (_, 2) => keyspace - width,
+            _ => first + rng.gen::<u64>() % (keyspace - first - width + 1),
+        };
+    let last = start + width - 1;
+
+    // written keys are even numbered when hit and miss are controlled.
+    let scale = if g.is_controlled() { 2 } else { 1 };
+    let key: K = unsafe { mem::zeroed() };
+    let low = match lkind {
+        1 => key.key_of(start * scale - 1, g),
+        _ => key.key_of(start * scale, g),
+    };
+    let high = match hkind {
+        1 => key.key_of(last * scale + 1, g),
+        _ => key.key_of(last * scale, g),
+    };
+    (to_bound(lkind, low), to_bound(hkind, high))
+}
+
+// key for load, set, rmw, set_if_*, scan and reverse_scan.
+fn gen_write_key<K>(rng: &mut SmallRng, g: &GenOptions) -> K
+where
+    K: RandomKV,
+{
+    let key: K = unsafe { mem::zeroed() };
+    if g.is_controlled() {
+        let n = g.gen_key_number(rng);
+        key.key_of(n * 2, g)
+    } else {
+        key.gen_key(rng, g)
+    }
+}
+
+// fresh key beyond the keyspace, and grow the keyspace.
+fn gen_insert_key<K>(g: &GenOptions) -> K
+where
+    K: RandomKV,
+{
+    let key: K = unsafe { mem::zeroed() };
+    let n = g.to_loaded_keyspace() + g.inserts.fetch_add(1, SeqCst);
+    key.key_of(n * 2, g)
+}
+
+// key for get and multi_get, a never written key for `miss_ratio`
+// fraction of operations.
+fn gen_read_key<K>(rng: &mut SmallRng, g: &GenOptions, miss_ratio: Option<f64>) -> K
 where
     K: RandomKV,
 {
     let key: K = unsafe { mem::zeroed() };
-    let key = key.gen_key(rng, g);
-    match rng.gen::<u8>() % 3 {
+    if g.is_controlled() {
+        let n = g.gen_key_number(rng);
+        let miss = rng.gen::<f64>() < miss_ratio.unwrap_or(0.0);
+        key.key_of((n * 2) + (miss as u64), g)
+    } else {
+        key.gen_key(rng, g)
+    }
+}
+
+// rank between 1 and n, rank k is picked with probability proportional
+// to 1/k^theta. Uses rejection-inversion sampling by Hormann and
+// Derflinger, that needs no precomputation as the keyspace grows.
+fn zipf(rng: &mut SmallRng, n: u64, theta: f64) -> u64 {
+    fn helper1(x: f64) -> f64 {
+        if x.abs() > 1e-8 {
+            x.ln_1p() / x
+        } else {
+            1.0 - x * (0.5 - x * (1.0 / 3.0 - 0.25 * x))
+        }
+    }
+    fn helper2(x: f64) -> f64 {
+        if x.abs() > 1e-8 {
+            x.exp_m1() / x
+        } else {
+            1.0 + x * 0.5 * (1.0 + x * (1.0 / 3.0) * (1.0 + 0.25 * x))
+        }
+    }
+
+    let h = |x: f64| (-theta * x.ln()).exp();
+    let h_integral = |x: f64| helper2((1.0 - theta) * x.ln()) * x.ln();
+    let h_integral_inv = |x: f64| {
+        let t = (x * (1.0 - theta)).max(-1.0);
+        (helper1(t) * x).exp()
+    };
+
+    let h_x1 = h_integral(1.5) - 1.0;
+    let h_n = h_integral(n as f64 + 0.5);
+    let s = 2.0 - h_integral_inv(h_integral(2.5) - h(2.0));
+    loop {
+        let u = h_n + rng.gen::<f64>() * (h_x1 - h_n);
+        let x = h_integral_inv(u);
+        let k = cmp::min(cmp::max((x + 0.5) as u64, 1), n);
+        if (k as f64 - x) <= s || u >= (h_integral(k as f64 + 0.5) - h(k as f64)) {
+            break k;
+        }
+    }
+}
+
+fn fnv1a(n: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for byte in n.to_le_bytes().iter() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
+}
+
+fn to_bound<K>(kind: u8, key: K) -> Bound<K> {
+    match kind {
         0 => Bound::Included(key),
         1 => Bound::Excluded(key),
         2 => Bound::Unbounded,
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+#[path = "generator_test.rs"]
+mod generator_test;
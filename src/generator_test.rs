@@ -0,0 +1,143 @@
+use rand::{rngs::SmallRng, SeedableRng};
+
+use std::{collections::HashSet, ops::Bound};
+
+use crate::adapter;
+
+use super::*;
+
+#[test]
+fn test_zipf() {
+    let mut rng = SmallRng::from_seed(0x1234_5678_u128.to_le_bytes());
+    assert_eq!(zipf(&mut rng, 1, ZIPF_THETA), 1);
+
+    let n = 1000;
+    let mut counts = vec![0_usize; n as usize + 1];
+    for _ in 0..100_000 {
+        let k = zipf(&mut rng, n, ZIPF_THETA);
+        assert!(k >= 1 && k <= n, "rank {} out of 1..={}", k, n);
+        counts[k as usize] += 1;
+    }
+    // rank k is picked in proportion to 1/k^theta.
+    for k in [2_usize, 10].iter() {
+        let ratio = (counts[1] as f64) / (counts[*k] as f64);
+        let expected = (*k as f64).powf(ZIPF_THETA);
+        assert!(
+            (ratio - expected).abs() < (expected * 0.1),
+            "rank 1/{} ratio {} expected {}",
+            k,
+            ratio,
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_gen_key_number() {
+    let mut rng = SmallRng::from_seed(0x1234_5678_u128.to_le_bytes());
+    let mut g = controlled(1000);
+    for _ in 0..10 {
+        g.insert_applied();
+    }
+    for _ in 0..100 {
+        g.gen_delete_number().unwrap();
+    }
+    assert_eq!(g.to_keyspace(), 1010);
+    assert_eq!(g.to_deleted(), 100);
+
+    for distribution in ["uniform", "zipfian", "latest"].iter() {
+        g.key_distribution = distribution.to_string();
+        let mut recent = 0;
+        for _ in 0..10_000 {
+            let n = g.gen_key_number(&mut rng);
+            assert!(n >= 100 && n < 1010, "{} key {}", distribution, n);
+            recent += (n >= 1000) as usize;
+        }
+        // latest picks the 10 most recent inserts, about 1% of the
+        // keyspace, more than a quarter of the time.
+        match *distribution {
+            "latest" => assert!(recent > 2500, "{} recent {}", distribution, recent),
+            _ => assert!(recent < 500, "{} recent {}", distribution, recent),
+        }
+    }
+}
+
+#[test]
+fn test_gen_delete_number() {
+    let g = controlled(10);
+    // keys are deleted in order, and the last key is never deleted.
+    let deleted: Vec<u64> = (0..20).filter_map(|_| g.gen_delete_number()).collect();
+    assert_eq!(deleted, (0..9).collect::<Vec<u64>>());
+    g.insert_applied();
+    assert_eq!(g.gen_delete_number(), Some(9));
+    assert_eq!(g.gen_delete_number(), None);
+
+    let mut g = g.clone();
+    g.reset_inserts();
+    assert_eq!(g.gen_delete_number(), Some(0));
+}
+
+#[test]
+fn test_to_load_number() {
+    let loads: Vec<usize> = (1..100).chain(vec![1000, 1024, 4096, 10007]).collect();
+    for loads in loads.into_iter() {
+        let mut g: GenOptions = Default::default();
+        g.loads = loads;
+        // every key in the keyspace is loaded exactly once.
+        let nums: HashSet<u64> = (0..(loads as u64)).map(|n| g.to_load_number(n)).collect();
+        assert_eq!(nums.len(), loads, "loads {}", loads);
+        assert!(nums.iter().all(|n| *n < (loads as u64)), "loads {}", loads);
+    }
+}
+
+#[test]
+fn test_range_bounds() {
+    let mut rng = SmallRng::from_seed(0x1234_5678_u128.to_le_bytes());
+    let g = controlled(1);
+    let (low, high) = range_bounds::<u64>(&mut rng, &g);
+    assert_eq!((low, high), (Bound::Unbounded, Bound::Unbounded));
+
+    let mut g = controlled(100);
+    for _ in 0..20 {
+        g.gen_delete_number().unwrap();
+    }
+    // written keys, key number n is written as 2*n.
+    let keys: Vec<u64> = (20..100).map(|n| n * 2).collect();
+
+    for (scan_length, range_selectivity, width) in [(10, 0.0, 10), (0, 0.1, 8)].iter() {
+        g.scan_length = *scan_length;
+        g.range_selectivity = *range_selectivity;
+        for _ in 0..1000 {
+            let (low, high) = range_bounds::<u64>(&mut rng, &g);
+            let is_empty = adapter::is_empty_range(&low, &high);
+            assert!(!is_empty, "{:?} {:?}", low, high);
+            let n = keys.iter().filter(|k| is_within(**k, &low, &high)).count();
+            match (&low, &high) {
+                (Bound::Unbounded, Bound::Unbounded) => assert_eq!(n, keys.len()),
+                _ => assert_eq!(n, *width, "{:?} {:?}", low, high),
+            }
+        }
+    }
+}
+
+// hit and miss controlled options, with `loads` keys in the keyspace.
+fn controlled(loads: usize) -> GenOptions {
+    let mut g: GenOptions = Default::default();
+    g.loads = loads;
+    g.get_miss_ratio = Some(0.0);
+    g
+}
+
+fn is_within(key: u64, low: &Bound<u64>, high: &Bound<u64>) -> bool {
+    let after_low = match low {
+        Bound::Included(l) => key >= *l,
+        Bound::Excluded(l) => key > *l,
+        Bound::Unbounded => true,
+    };
+    let before_high = match high {
+        Bound::Included(h) => key <= *h,
+        Bound::Excluded(h) => key < *h,
+        Bound::Unbounded => true,
+    };
+    after_low && before_high
+}
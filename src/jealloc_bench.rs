@@ -8,7 +8,7 @@ use std::{
     mem,
     sync::{mpsc, Arc},
     thread,
-    time::SystemTime,
+    time::Instant,
 };
 
 struct Node {
@@ -22,14 +22,14 @@ struct Node {
 fn bench_je_alloc(b: &mut Bencher) {
     let je = jemallocator::Jemalloc;
 
-    let start = SystemTime::now();
+    let start = Instant::now();
     let n = 1_000_000;
     for _i in 0..n {
         unsafe { je.alloc(Layout::new::<Node>()) };
     }
     println!(
         "took {:?} to allocate {} blocks",
-        start.elapsed().unwrap(),
+        start.elapsed(),
         n
     );
 
@@ -39,7 +39,7 @@ fn bench_je_alloc(b: &mut Bencher) {
 #[bench]
 fn bench_je_alloc_free(b: &mut Bencher) {
     let je = jemallocator::Jemalloc;
-    let start = SystemTime::now();
+    let start = Instant::now();
     let n = 1_000_000;
     for _i in 0..n {
         unsafe {
@@ -50,7 +50,7 @@ fn bench_je_alloc_free(b: &mut Bencher) {
     }
     println!(
         "took {:?} to allocate/free {} blocks",
-        start.elapsed().unwrap(),
+        start.elapsed(),
         n
     );
 
@@ -75,7 +75,7 @@ fn bench_je_alloc_cc2(b: &mut Bencher) {
         }
     });
 
-    let start = SystemTime::now();
+    let start = Instant::now();
     let n = 1_000_000;
     for _i in 0..n {
         unsafe {
@@ -87,11 +87,11 @@ fn bench_je_alloc_cc2(b: &mut Bencher) {
 
     println!(
         "took {:?} to allocate {} blocks",
-        start.elapsed().unwrap(),
+        start.elapsed(),
         n
     );
     handle.join().unwrap();
-    println!("took {:?} to free {} blocks", start.elapsed().unwrap(), n);
+    println!("took {:?} to free {} blocks", start.elapsed(), n);
 
     let je = jemallocator::Jemalloc;
     b.iter(|| unsafe {
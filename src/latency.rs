@@ -1,31 +1,62 @@
-use std::{
-    fmt,
-    time::{Duration, SystemTime},
-};
+use std::{fmt, time::Duration};
+
+use crate::clock::Instant;
+
+// Histogram buckets are log-linear. Latencies below LINEAR nanoseconds
+// get a bucket each, 1ns resolution, so that sub-100ns ops timed with
+// `--tsc` are not lumped together. Beyond that, every power of two is
+// split into LINEAR/2 buckets, that is, within 0.2% of the latency.
+const LINEAR: u64 = 1024;
+const LINEAR_BITS: u32 = 10;
+const BUCKETS: usize = (LINEAR as usize) + ((64 - LINEAR_BITS as usize) * (LINEAR as usize / 2));
+
+// histogram bucket for latency `ns`.
+fn to_bucket(ns: u64) -> usize {
+    if ns < LINEAR {
+        ns as usize
+    } else {
+        let exp = 63 - ns.leading_zeros(); // >= LINEAR_BITS
+        let sub = (ns >> (exp - LINEAR_BITS + 1)) - (LINEAR / 2);
+        let base = (LINEAR as usize) + (((exp - LINEAR_BITS) as usize) * (LINEAR as usize / 2));
+        base + (sub as usize)
+    }
+}
+
+// lowest latency, in nanoseconds, that falls in histogram `bucket`.
+fn from_bucket(bucket: usize) -> u128 {
+    if bucket < (LINEAR as usize) {
+        bucket as u128
+    } else {
+        let n = bucket - (LINEAR as usize);
+        let exp = (n / (LINEAR as usize / 2)) as u32 + LINEAR_BITS;
+        let sub = (n % (LINEAR as usize / 2)) as u128 + (LINEAR as u128 / 2);
+        sub << (exp - LINEAR_BITS + 1)
+    }
+}
 
 pub struct Latency {
     name: String,
     samples: usize,
     total: Duration,
-    start: SystemTime,
+    start: Instant,
     min: u128,
     max: u128,
-    latencies: Vec<usize>, // NOTE: large value, can't be in stack.
+    // NOTE: large value, can't be in stack. Allocated on the first
+    // sample, most ops in a phase are never sampled.
+    latencies: Vec<usize>,
 }
 
 impl Default for Latency {
     fn default() -> Latency {
-        let mut lat = Latency {
+        Latency {
             name: "".to_string(),
             samples: Default::default(),
             total: Default::default(),
-            start: SystemTime::now(),
+            start: Instant::now(),
             min: std::u128::MAX,
             max: std::u128::MIN,
-            latencies: Vec::with_capacity(1_000_000),
-        };
-        lat.latencies.resize(lat.latencies.capacity(), 0);
-        lat
+            latencies: vec![],
+        }
     }
 }
 
@@ -38,20 +69,18 @@ impl Latency {
 
     pub fn start(&mut self) {
         self.samples += 1;
-        self.start = SystemTime::now();
+        self.start = Instant::now();
     }
 
     pub fn stop(&mut self) {
-        let elapsed = self.start.elapsed().unwrap().as_nanos();
+        let elapsed = self.start.elapsed().as_nanos();
         self.min = std::cmp::min(self.min, elapsed);
         self.max = std::cmp::max(self.max, elapsed);
-        let latency = (elapsed / 100) as usize;
-        let ln = self.latencies.len();
-        if latency < ln {
-            self.latencies[latency] += 1;
-        } else {
-            self.latencies[ln - 1] += 1;
+        if self.latencies.len() == 0 {
+            self.latencies = vec![0; BUCKETS];
         }
+        let elapsed = std::cmp::min(elapsed, std::u64::MAX as u128);
+        self.latencies[to_bucket(elapsed as u64)] += 1;
         self.total += Duration::from_nanos(elapsed as u64);
     }
 
@@ -63,11 +92,11 @@ impl Latency {
         let mut percentiles: Vec<(u8, u128)> = vec![];
         let (mut acc, mut prev_perc) = (0_f64, 90_u8);
         let iter = self.latencies.iter().enumerate().filter(|(_, &x)| x > 0);
-        for (latency, &samples) in iter {
+        for (bucket, &samples) in iter {
             acc += samples as f64;
             let perc = ((acc / (self.samples as f64)) * 100_f64) as u8;
             if perc > prev_perc {
-                percentiles.push((perc, latency as u128));
+                percentiles.push((perc, from_bucket(bucket)));
                 prev_perc = perc;
             }
         }
@@ -78,6 +107,28 @@ impl Latency {
         self.samples
     }
 
+    /// Return latency, in nanoseconds, at percentile `perc`, with the
+    /// same resolution as [Latency::to_percentiles].
+    pub fn to_percentile(&self, perc: u8) -> u128 {
+        let want = ((self.samples as f64) * (perc as f64) / 100_f64).ceil() as usize;
+        let mut acc = 0;
+        for (bucket, &samples) in self.latencies.iter().enumerate() {
+            acc += samples;
+            if samples > 0 && acc >= want {
+                return from_bucket(bucket);
+            }
+        }
+        0
+    }
+
+    pub fn to_max(&self) -> u128 {
+        if self.samples > 0 {
+            self.max
+        } else {
+            0
+        }
+    }
+
     pub fn to_mean(&self) -> u128 {
         if self.samples > 0 {
             self.total.as_nanos() / (self.samples as u128)
@@ -91,19 +142,44 @@ impl Latency {
         self.total += other.total;
         self.min = std::cmp::min(self.min, other.min);
         self.max = std::cmp::max(self.max, other.max);
+        if self.latencies.len() == 0 && other.latencies.len() > 0 {
+            self.latencies = vec![0; BUCKETS];
+        }
         self.latencies
             .iter_mut()
             .zip(other.latencies.iter())
             .for_each(|(x, y)| *x = *x + *y);
     }
 
+    /// Subtract harness `overhead`, in nanoseconds, from every sample,
+    /// saturating at zero, refer [crate::stats::Overhead].
+    pub fn calibrate(&mut self, overhead: u128) {
+        if self.latencies.len() == 0 || overhead == 0 {
+            return;
+        }
+        let mut latencies = vec![0; BUCKETS];
+        let iter = self.latencies.iter().enumerate().filter(|(_, &x)| x > 0);
+        for (bucket, &samples) in iter {
+            let latn = from_bucket(bucket).saturating_sub(overhead);
+            latencies[to_bucket(latn as u64)] += samples;
+        }
+        self.latencies = latencies;
+
+        let samples: usize = self.latencies.iter().sum();
+        let total = self.total.as_nanos();
+        let total = total.saturating_sub(overhead * (samples as u128));
+        self.total = Duration::from_nanos(total as u64);
+        self.min = self.min.saturating_sub(overhead);
+        self.max = self.max.saturating_sub(overhead);
+    }
+
     #[allow(dead_code)] // TODO: remove this once ixperf stabilizes.
     pub fn to_json(&self) -> String {
         let total = self.total.as_nanos();
         let ps: Vec<String> = self
             .to_percentiles()
             .into_iter()
-            .map(|(p, ns)| format!(r#""{}": {}"#, p, (ns * 100)))
+            .map(|(p, ns)| format!(r#""{}": {}"#, p, ns))
             .collect();
         let strs = [
             format!(r#""n": {}"#, self.samples),
@@ -126,7 +202,7 @@ impl fmt::Display for Latency {
         let props: Vec<String> = self
             .to_percentiles()
             .into_iter()
-            .map(|(perc, latn)| format!(r#""{}"={}"#, perc, (latn * 100)))
+            .map(|(perc, latn)| format!(r#""{}"={}"#, perc, latn))
             .collect();
         let latencies = props.join(", ");
         write!(
@@ -151,8 +227,8 @@ impl fmt::Debug for Latency {
             .to_percentiles()
             .into_iter()
             .map(|(perc, latn)| {
-                let latn = (latn * 100) as u64;
-                format!(r#""{}"={:?}"#, perc, Duration::from_nanos(latn))
+                let latn = Duration::from_nanos(latn as u64);
+                format!(r#""{}"={:?}"#, perc, latn)
             })
             .collect();
         let latencies = props.join(", ");
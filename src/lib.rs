@@ -0,0 +1,379 @@
+//! Performance measurement for index implementations.
+//!
+//! To benchmark an index that is not part of ixperf, implement the
+//! [adapter::IndexAdapter] trait for it, and register a perf function
+//! with [Registry]. Typically the perf function dispatches on
+//! [Profile::key_type] and [Profile::val_type] and calls
+//! [adapter::do_perf] or [adapter::do_concur_perf]:
+//!
+//! ```ignore
+//! fn perf(_name: &str, p: ixperf::Profile) -> Result<(), String> {
+//!     let mut index = MyIndex::new();
+//!     ixperf::adapter::do_perf::<_, Vec<u8>, Vec<u8>>(&mut index, &p)?;
+//!     Ok(())
+//! }
+//!
+//! let mut registry = ixperf::Registry::new();
+//! registry.register("my-index", perf);
+//! registry.perf(ixperf::Profile::from_opt(opt)?)?;
+//! ```
+
+use rand::random;
+use structopt::StructOpt;
+use toml;
+
+use std::{convert::TryFrom, fs};
+
+pub mod adapter;
+pub mod amplify;
+pub mod clock;
+pub mod differential;
+pub mod generator;
+pub mod latency;
+pub mod live;
+pub mod metrics;
+pub mod mod_btree_map;
+pub mod mod_external;
+pub mod mod_hash_map;
+pub mod mod_im_ordmap;
+pub mod mod_llrb;
+pub mod mod_lmdb;
+pub mod mod_null;
+pub mod mod_rdms;
+pub mod mod_rdms_dgm;
+pub mod mod_rdms_llrb;
+pub mod mod_rdms_mvcc;
+pub mod mod_rdms_robt;
+pub mod mod_rdms_shllrb;
+pub mod mod_rdms_shrobt;
+pub mod mod_wal;
+pub mod mod_xorfilter;
+pub mod plot;
+pub mod stats;
+pub mod system;
+#[macro_use]
+pub mod utils;
+pub mod verify;
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Opt {
+    #[structopt(long = "profile", default_value = "")]
+    pub profile: String,
+
+    #[structopt(long = "seed", default_value = "0")]
+    pub seed: u128,
+
+    #[structopt(long = "plot", default_value = "")]
+    pub plot: plot::PlotFiles,
+
+    #[structopt(long = "ignore-error", help = "Ignore log errors while plotting")]
+    pub ignore_error: bool,
+
+    #[structopt(long = "percentile", default_value = "99")]
+    pub percentile: String,
+
+    #[structopt(long = "log-file", default_value="")]
+    pub log_file: String,
+
+    #[structopt(short = "v", long = "verbose")]
+    pub verbose: bool,
+
+    #[structopt(long = "trace")]
+    pub trace: bool,
+
+    #[structopt(long = "stats")]
+    pub stats: bool,
+
+    #[structopt(long = "verify", help = "Verify index against a shadow BTreeMap")]
+    pub verify: bool,
+
+    #[structopt(
+        long = "diff",
+        default_value = "",
+        help = "Compare index from --profile with index from this profile"
+    )]
+    pub diff: String,
+
+    #[structopt(
+        long = "workload",
+        default_value = "",
+        help = "YCSB core workload, ycsb-a to ycsb-f, overrides the profile"
+    )]
+    pub workload: String,
+
+    #[structopt(
+        long = "calibrate",
+        help = "Subtract harness overhead, measured with the null index"
+    )]
+    pub calibrate: bool,
+
+    #[structopt(
+        long = "tsc",
+        help = "Sample latency with time-stamp-counter, x86_64 linux only"
+    )]
+    pub tsc: bool,
+
+    #[structopt(long = "live", help = "Show a live dashboard on stderr")]
+    pub live: bool,
+
+    #[structopt(
+        long = "metrics-port",
+        default_value = "0",
+        help = "Serve Prometheus metrics on this localhost port"
+    )]
+    pub metrics_port: u16,
+
+    #[structopt(
+        long = "system-interval",
+        default_value = "1000",
+        help = "Interval, in milliseconds, for sampling process stats"
+    )]
+    pub system_interval: u64,
+}
+
+// Same as the default_value for command line arguments.
+impl Default for Opt {
+    fn default() -> Opt {
+        Opt {
+            profile: Default::default(),
+            seed: Default::default(),
+            plot: Default::default(),
+            ignore_error: Default::default(),
+            percentile: "99".to_string(),
+            log_file: Default::default(),
+            verbose: Default::default(),
+            trace: Default::default(),
+            stats: Default::default(),
+            verify: Default::default(),
+            diff: Default::default(),
+            workload: Default::default(),
+            calibrate: Default::default(),
+            tsc: Default::default(),
+            live: Default::default(),
+            metrics_port: Default::default(),
+            system_interval: 1000,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct Profile {
+    pub index: String,
+    pub key_type: String,
+    pub val_type: String,
+    pub cmd_opts: Opt,
+    pub diff: Option<differential::DiffOpt>,
+    /// Harness overhead, measured with the null index, refer --calibrate.
+    pub overhead: Option<stats::Overhead>,
+    pub error_policy: adapter::ErrorPolicy,
+    /// Live dashboard, refer --live.
+    pub live: Option<live::Live>,
+    /// Metrics endpoint, refer --metrics-port.
+    pub metrics: Option<metrics::Metrics>,
+    /// Amplification accounting, for disk based indexes.
+    pub amplify: Option<amplify::Amplify>,
+
+    pub key_footprint: usize,
+    pub value_footprint: usize,
+
+    pub g: generator::GenOptions,
+    pub external: mod_external::ExternalOpt,
+    pub im_ordmap: mod_im_ordmap::ImOrdMapOpt,
+    pub lmdb: mod_lmdb::LmdbOpt,
+    pub rdms: mod_rdms::RdmsOpt,
+    pub rdms_llrb: mod_rdms_llrb::LlrbOpt,
+    pub rdms_mvcc: mod_rdms_mvcc::MvccOpt,
+    pub rdms_robt: mod_rdms_robt::RobtOpt,
+    pub rdms_shrobt: mod_rdms_shrobt::ShrobtOpt,
+    pub rdms_shllrb: mod_rdms_shllrb::ShllrbOpt,
+    pub rdms_dgm: mod_rdms_dgm::DgmOpt,
+    pub wal: mod_wal::WalOpt,
+}
+
+impl Profile {
+    /// Load profile from command line arguments.
+    pub fn new() -> Result<Profile, String> {
+        Profile::from_opt(Opt::from_args())
+    }
+
+    /// Load the second profile, from `opt.diff` file, for diff mode.
+    pub fn from_diff_opt(opt: Opt) -> Result<Profile, String> {
+        let mut opt = opt;
+        opt.profile = opt.diff.clone();
+        Profile::from_opt(opt)
+    }
+
+    /// Load profile from `opt.profile` file.
+    pub fn from_opt(opt: Opt) -> Result<Profile, String> {
+        let mut p: Profile = match opt.profile.as_str() {
+            "" => Err(format!("please provide a profile file")),
+            profile => match fs::read(profile) {
+                Ok(text) => {
+                    let text = std::str::from_utf8(&text).unwrap();
+                    let toml_value = match text.parse::<toml::Value>() {
+                        Ok(value) => Ok(value),
+                        Err(err) => Err(format!("{:}", err)),
+                    }?;
+                    Ok(TryFrom::try_from(toml_value)?)
+                }
+                Err(err) => Err(format!("{:?}", err)),
+            },
+        }?;
+        let seed = std::cmp::max(p.g.seed, opt.seed);
+        p.g.seed = match seed {
+            n if n > 0 => seed,
+            n if n == 0 => random(),
+            n => n,
+        };
+        if opt.workload.len() > 0 {
+            p.g.set_workload(&opt.workload)?;
+        }
+        p.cmd_opts = opt;
+        Ok(p)
+    }
+}
+
+impl TryFrom<toml::Value> for Profile {
+    type Error = String;
+    fn try_from(value: toml::Value) -> Result<Profile, String> {
+        let mut p: Profile = Default::default();
+        let (mut error_policy, mut max_errors) = ("abort".to_string(), 0);
+        let section = &value["ixperf"];
+        for (name, value) in section.as_table().unwrap().iter() {
+            match name.as_str() {
+                "index" => p.index = utils::toml_to_string(value),
+                "key_type" => p.key_type = utils::toml_to_string(value),
+                "value_type" => p.val_type = utils::toml_to_string(value),
+                "error_policy" => error_policy = utils::toml_to_string(value),
+                "max_errors" => max_errors = utils::toml_to_usize(value),
+                _ => return Err(format!("invalid option {}", name)),
+            }
+        }
+        p.error_policy = adapter::ErrorPolicy::new(&error_policy, max_errors)?;
+
+        p.g = TryFrom::try_from(value.clone())?;
+
+        p.external = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.im_ordmap = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.lmdb = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.rdms = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.rdms_llrb = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.rdms_mvcc = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.rdms_robt = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.rdms_shrobt = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.rdms_shllrb = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.rdms_dgm = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+        p.wal = TryFrom::try_from(value.clone())
+            .ok()
+            .unwrap_or(Default::default());
+
+        // keyspace depends on number of initial loaders.
+        p.g.initial = p.rdms.initial;
+        p.amplify = amplify::Amplify::from_profile(&p);
+        Ok(p)
+    }
+}
+
+/// Perf function for an index, called with a name for the index and the
+/// profile to run.
+pub type PerfFn = fn(&str, Profile) -> Result<(), String>;
+
+/// Index types that can be benchmarked, looked up by `[ixperf] index`
+/// in the profile.
+pub struct Registry {
+    indexes: Vec<(String, PerfFn)>,
+}
+
+impl Registry {
+    /// Create a registry with all the indexes supported by ixperf.
+    pub fn new() -> Registry {
+        let mut registry = Registry { indexes: vec![] };
+        registry
+            .register("llrb-index", mod_llrb::perf)
+            .register("btree-map", mod_btree_map::perf)
+            .register("hash-map", mod_hash_map::perf)
+            .register("external", mod_external::perf)
+            .register("im-ordmap", mod_im_ordmap::perf)
+            .register("lmdb", |_name, p| mod_lmdb::perf(p))
+            .register("null", mod_null::perf)
+            .register("xorfilter", |_name, p| mod_xorfilter::perf(p))
+            .register("rdms", |_name, p| mod_rdms::do_rdms_index(p))
+            .register("wal", mod_wal::perf);
+        registry
+    }
+
+    /// Register perf function for index, replacing the older one if
+    /// index is already registered.
+    pub fn register(&mut self, index: &str, perf: PerfFn) -> &mut Registry {
+        self.indexes.retain(|(name, _)| name != index);
+        self.indexes.push((index.to_string(), perf));
+        self
+    }
+
+    /// Run profile against the index it refers to. With --calibrate,
+    /// the profile is first run against the null index to measure the
+    /// harness overhead.
+    pub fn perf(&self, mut p: Profile) -> Result<(), String> {
+        if p.cmd_opts.calibrate && p.index != "null" && p.overhead.is_none() {
+            p.overhead = Some(mod_null::calibrate(p.clone())?);
+        }
+        match self.indexes.iter().find(|(name, _)| name == &p.index) {
+            Some((_, perf)) => perf("ixperf", p),
+            None => Err(format!("unsupported index-type {}", p.index)),
+        }
+    }
+
+    /// Run profile `p1` and then profile `p2`, with the same generator
+    /// options, and compare the read results from `p2`'s index with
+    /// the read results from `p1`'s index.
+    pub fn diff(&self, mut p1: Profile, mut p2: Profile) -> Result<(), String> {
+        if p1.key_type != p2.key_type || p1.val_type != p2.val_type {
+            return Err(format!(
+                "diff needs same key/value types, {}/{} vs {}/{}",
+                p1.key_type, p1.val_type, p2.key_type, p2.val_type
+            ));
+        }
+
+        let opt = differential::DiffOpt::new(&diff_name(&p1), &diff_name(&p2));
+        p2.g = p1.g.clone();
+        p2.g.reset_inserts();
+
+        p1.diff = Some(opt.clone());
+        self.perf(p1)?;
+        let opt = opt.to_compare();
+        p2.diff = Some(opt.clone());
+        self.perf(p2)?;
+        opt.finish()
+    }
+}
+
+fn diff_name(p: &Profile) -> String {
+    match p.index.as_str() {
+        "rdms" => format!("rdms-{}", p.rdms.index),
+        index => index.to_string(),
+    }
+}
+
+#[cfg(test)]
+#[path = "lib_test.rs"]
+mod lib_test;
@@ -0,0 +1,39 @@
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+use super::*;
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn perf(name: &str, _p: Profile) -> Result<(), String> {
+    assert_eq!(name, "ixperf");
+    CALLS.fetch_add(1, SeqCst);
+    Ok(())
+}
+
+fn to_profile(index: &str) -> Profile {
+    let mut p: Profile = Default::default();
+    p.index = index.to_string();
+    p
+}
+
+#[test]
+fn test_registry() {
+    let mut registry = Registry::new();
+    let res = registry.perf(to_profile("my-index"));
+    assert_eq!(res, Err("unsupported index-type my-index".to_string()));
+
+    registry.register("my-index", perf);
+    registry.perf(to_profile("my-index")).unwrap();
+    assert_eq!(CALLS.load(SeqCst), 1);
+
+    // registering again replaces the older perf function.
+    registry.register("my-index", |_name, p| Err(format!("replaced {}", p.index)));
+    let res = registry.perf(to_profile("my-index"));
+    assert_eq!(res, Err("replaced my-index".to_string()));
+    assert_eq!(CALLS.load(SeqCst), 1);
+
+    // so are the indexes supported by ixperf.
+    registry.register("btree-map", perf);
+    registry.perf(to_profile("btree-map")).unwrap();
+    assert_eq!(CALLS.load(SeqCst), 2);
+}
@@ -0,0 +1,187 @@
+//! Live terminal dashboard, enabled with `--live`. Every phase, or
+//! thread, reports its progress and periodic stats, and the system
+//! thread reports cpu and rss. Dashboard is redrawn in place, on stderr,
+//! every second. Normal logging continues on stdout, or to --log-file,
+//! use --log-file for a clean view.
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::stats;
+
+/// Handle to the dashboard, cheap to clone and share with threads.
+#[derive(Clone)]
+pub struct Live(Arc<Mutex<Board>>);
+
+struct Board {
+    start: Instant,
+    phases: Vec<(String, Phase)>,
+    system: Option<(f32, u64)>,
+    n_lines: usize, // number of lines drawn last time.
+}
+
+struct Phase {
+    start: Instant,
+    total: usize,
+    done: usize,
+    finished: Option<Duration>,
+    ops: Vec<String>,
+}
+
+impl Live {
+    /// Create the dashboard and start redrawing it every second.
+    pub fn start() -> Live {
+        let live = Live(Arc::new(Mutex::new(Board {
+            start: Instant::now(),
+            phases: vec![],
+            system: None,
+            n_lines: 0,
+        })));
+        {
+            let live = live.clone();
+            thread::spawn(move || loop {
+                thread::sleep(Duration::from_secs(1));
+                live.render();
+            });
+        }
+        live
+    }
+
+    /// Phase `name` is starting, with `total` ops to apply.
+    pub fn start_phase(&self, name: &str, total: usize) {
+        let phase = Phase {
+            start: Instant::now(),
+            total,
+            done: 0,
+            finished: None,
+            ops: vec![],
+        };
+        let mut board = self.0.lock().unwrap();
+        board.phases.retain(|(n, _)| n != name);
+        board.phases.push((name.to_string(), phase));
+    }
+
+    /// Phase `name` has applied `done` ops so far.
+    pub fn progress(&self, name: &str, done: usize) {
+        let mut board = self.0.lock().unwrap();
+        if let Some(phase) = board.to_phase(name) {
+            phase.done = done;
+        }
+    }
+
+    /// Latest stats for phase `name`, periodic or final.
+    pub fn update(&self, name: &str, stats: &stats::Ops) {
+        let ops: Vec<String> = stats
+            .to_ops()
+            .into_iter()
+            .filter(|op| op.count > 0)
+            .map(to_op_line)
+            .collect();
+        let mut board = self.0.lock().unwrap();
+        if let Some(phase) = board.to_phase(name) {
+            phase.ops = ops;
+        }
+    }
+
+    /// Phase `name` is done, applying `done` ops.
+    pub fn finish_phase(&self, name: &str, done: usize) {
+        let mut board = self.0.lock().unwrap();
+        if let Some(phase) = board.to_phase(name) {
+            phase.done = done;
+            phase.finished = Some(phase.start.elapsed());
+        }
+    }
+
+    /// Latest cpu load, in percentage, and rss, in MB, of the process.
+    pub fn set_system(&self, cpu_load: f32, mem_rss: u64) {
+        self.0.lock().unwrap().system = Some((cpu_load, mem_rss));
+    }
+
+    /// Redraw the dashboard, in place of the previous one.
+    pub fn render(&self) {
+        let mut board = self.0.lock().unwrap();
+
+        let mut lines = vec![];
+        let elapsed = board.start.elapsed().as_secs();
+        lines.push(match board.system {
+            Some((cpu, rss)) => format!(
+                "ixperf elapsed {}s, cpu_load {:.2}%, mem_rss {} MB",
+                elapsed, cpu, rss
+            ),
+            None => format!("ixperf elapsed {}s", elapsed),
+        });
+        for (name, phase) in board.phases.iter() {
+            lines.push(phase.to_progress_line(name));
+            lines.extend(phase.ops.iter().cloned());
+        }
+
+        let mut out = String::new();
+        if board.n_lines > 0 {
+            // move to the start of previous dashboard and clear it.
+            out.push_str(&format!("\x1b[{}F\x1b[J", board.n_lines));
+        }
+        for line in lines.iter() {
+            out.push_str(line);
+            out.push('\n');
+        }
+        board.n_lines = lines.len();
+        io::stderr().write_all(out.as_bytes()).ok();
+    }
+}
+
+impl Board {
+    fn to_phase(&mut self, name: &str) -> Option<&mut Phase> {
+        self.phases
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, phase)| phase)
+    }
+}
+
+impl Phase {
+    fn to_progress_line(&self, name: &str) -> String {
+        let ratio = match self.total {
+            0 => 1.0,
+            total => (self.done as f64) / (total as f64),
+        };
+        let width = 20;
+        let filled = ((ratio * (width as f64)) as usize).min(width);
+        let bar = "#".repeat(filled) + &".".repeat(width - filled);
+        let eta = match self.finished {
+            Some(elapsed) => format!("done in {}s", elapsed.as_secs()),
+            None if self.done > 0 && self.total > self.done => {
+                let elapsed = self.start.elapsed().as_secs_f64();
+                let left = elapsed * ((self.total - self.done) as f64) / (self.done as f64);
+                format!("eta {}s", left as u64)
+            }
+            None => "eta -".to_string(),
+        };
+        format!(
+            "{:<14} [{}] {:>5.1}% {}/{} {}",
+            name,
+            bar,
+            ratio * 100.0,
+            self.done,
+            self.total,
+            eta
+        )
+    }
+}
+
+fn to_op_line(op: &stats::Op) -> String {
+    let lat = &op.latency;
+    format!(
+        "  {:<14} ops={:<10} {:>10}/s mean={:?} p50={:?} p99={:?} max={:?}",
+        op.name,
+        op.count,
+        op.to_throughput(),
+        Duration::from_nanos(lat.to_mean() as u64),
+        Duration::from_nanos(lat.to_percentile(50) as u64),
+        Duration::from_nanos(lat.to_percentile(99) as u64),
+        Duration::from_nanos(lat.to_max() as u64),
+    )
+}
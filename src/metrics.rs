@@ -0,0 +1,331 @@
+//! Metrics endpoint, enabled with `--metrics-port`. Serves current
+//! per-op counters, latency quantiles, phases, index entries, and cpu
+//! and rss of the process, in Prometheus text exposition format, at
+//! `http://127.0.0.1:<port>/metrics`.
+
+use log::{error, info};
+
+use std::{
+    fmt::Write as FmtWrite,
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::stats;
+
+// requests are served one at a time, a stalled client shall not block
+// the scrapes that follow.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Handle to the metrics, cheap to clone and share with threads.
+#[derive(Clone)]
+pub struct Metrics(Arc<Mutex<State>>);
+
+#[derive(Default)]
+struct State {
+    phases: Vec<(String, Phase)>,
+    index: Option<(String, usize)>,
+    system: Option<(f32, u64)>,
+}
+
+#[derive(Default)]
+struct Phase {
+    active: bool,
+    ops: Vec<OpMetrics>,
+}
+
+struct OpMetrics {
+    name: String,
+    count: usize,
+    outcomes: Vec<(&'static str, usize)>,
+    error_kinds: Vec<(String, usize)>,
+    throughput: u128,
+    quantiles: Vec<(&'static str, u128)>,
+    latency_count: usize,
+    latency_sum: u64,
+}
+
+impl Metrics {
+    /// Start listening on localhost `port`, serving metrics from a
+    /// background thread.
+    pub fn start(port: u16) -> Result<Metrics, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+        info!(target: "ixperf", "metrics at http://127.0.0.1:{}/metrics", port);
+
+        let metrics = Metrics(Arc::new(Mutex::new(Default::default())));
+        {
+            let metrics = metrics.clone();
+            thread::spawn(move || {
+                for conn in listener.incoming() {
+                    let res = match conn {
+                        Ok(conn) => metrics.serve(conn),
+                        Err(err) => Err(err.to_string()),
+                    };
+                    if let Err(err) = res {
+                        error!(target: "ixperf", "metrics {}", err);
+                    }
+                }
+            });
+        }
+        Ok(metrics)
+    }
+
+    /// Phase `name` is starting.
+    pub fn start_phase(&self, name: &str) {
+        let mut state = self.0.lock().unwrap();
+        state.phases.retain(|(n, _)| n != name);
+        let phase = Phase {
+            active: true,
+            ops: vec![],
+        };
+        state.phases.push((name.to_string(), phase));
+    }
+
+    /// Cumulative stats for phase `name`, so far.
+    pub fn update(&self, name: &str, stats: &stats::Ops) {
+        let ops: Vec<OpMetrics> = stats
+            .to_ops()
+            .into_iter()
+            .filter(|op| op.count > 0)
+            .map(OpMetrics::new)
+            .collect();
+        let mut state = self.0.lock().unwrap();
+        if let Some((_, phase)) = state.phases.iter_mut().find(|(n, _)| n == name) {
+            phase.ops = ops;
+        }
+    }
+
+    /// Phase `name` is done.
+    pub fn finish_phase(&self, name: &str) {
+        let mut state = self.0.lock().unwrap();
+        if let Some((_, phase)) = state.phases.iter_mut().find(|(n, _)| n == name) {
+            phase.active = false;
+        }
+    }
+
+    /// Number of entries in `index`, after a phase.
+    pub fn set_index_len(&self, index: &str, n: usize) {
+        self.0.lock().unwrap().index = Some((index.to_string(), n));
+    }
+
+    /// Latest cpu load, in percentage, and rss, in MB, of the process.
+    pub fn set_system(&self, cpu_load: f32, mem_rss: u64) {
+        self.0.lock().unwrap().system = Some((cpu_load, mem_rss));
+    }
+
+    fn serve(&self, mut conn: TcpStream) -> Result<(), String> {
+        let timeout = Some(TIMEOUT);
+        conn.set_read_timeout(timeout).map_err(|e| e.to_string())?;
+        conn.set_write_timeout(timeout).map_err(|e| e.to_string())?;
+
+        // only the request line matters, rest of the request is ignored.
+        let mut buf = [0_u8; 1024];
+        let n = conn.read(&mut buf).map_err(|e| e.to_string())?;
+        let req = String::from_utf8_lossy(&buf[..n]);
+        let path = req.split_whitespace().nth(1).unwrap_or("");
+
+        let (status, body) = match path {
+            "/metrics" => ("200 OK", self.to_text()),
+            _ => ("404 Not Found", "not found\n".to_string()),
+        };
+        let resp = format!(
+            concat!(
+                "HTTP/1.1 {}\r\n",
+                "Content-Type: text/plain; version=0.0.4\r\n",
+                "Content-Length: {}\r\n",
+                "Connection: close\r\n\r\n{}"
+            ),
+            status,
+            body.len(),
+            body
+        );
+        conn.write_all(resp.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    fn to_text(&self) -> String {
+        let state = self.0.lock().unwrap();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "ixperf_phase_active",
+            "gauge",
+            "1 while phase is running.",
+        );
+        for (name, phase) in state.phases.iter() {
+            let active = if phase.active { 1 } else { 0 };
+            writeln!(out, r#"ixperf_phase_active{{phase="{}"}} {}"#, name, active).ok();
+        }
+
+        header(
+            &mut out,
+            "ixperf_ops_total",
+            "counter",
+            "Operations applied.",
+        );
+        for (phase, op) in state.to_ops() {
+            let labels = to_labels(phase, &op.name);
+            writeln!(out, "ixperf_ops_total{{{}}} {}", labels, op.count).ok();
+        }
+
+        header(
+            &mut out,
+            "ixperf_op_outcomes_total",
+            "counter",
+            "Operations by outcome, refer stats::Op.",
+        );
+        for (phase, op) in state.to_ops() {
+            let labels = to_labels(phase, &op.name);
+            for (outcome, n) in op.outcomes.iter() {
+                writeln!(
+                    out,
+                    r#"ixperf_op_outcomes_total{{{},outcome="{}"}} {}"#,
+                    labels, outcome, n
+                )
+                .ok();
+            }
+        }
+
+        header(
+            &mut out,
+            "ixperf_op_errors_total",
+            "counter",
+            "Failed operations.",
+        );
+        for (phase, op) in state.to_ops() {
+            let labels = to_labels(phase, &op.name);
+            for (kind, n) in op.error_kinds.iter() {
+                let kind = kind.replace('"', "'");
+                writeln!(
+                    out,
+                    r#"ixperf_op_errors_total{{{},kind="{}"}} {}"#,
+                    labels, kind, n
+                )
+                .ok();
+            }
+        }
+
+        header(
+            &mut out,
+            "ixperf_op_throughput",
+            "gauge",
+            "Operations per second, over the phase.",
+        );
+        for (phase, op) in state.to_ops() {
+            let labels = to_labels(phase, &op.name);
+            writeln!(out, "ixperf_op_throughput{{{}}} {}", labels, op.throughput).ok();
+        }
+
+        header(
+            &mut out,
+            "ixperf_op_latency_seconds",
+            "summary",
+            "Sampled latency of operations.",
+        );
+        for (phase, op) in state.to_ops() {
+            let labels = to_labels(phase, &op.name);
+            for (quantile, nanos) in op.quantiles.iter() {
+                writeln!(
+                    out,
+                    r#"ixperf_op_latency_seconds{{{},quantile="{}"}} {}"#,
+                    labels,
+                    quantile,
+                    to_seconds(*nanos)
+                )
+                .ok();
+            }
+            let sum = to_seconds(op.latency_sum as u128);
+            writeln!(out, "ixperf_op_latency_seconds_sum{{{}}} {}", labels, sum).ok();
+            let count = op.latency_count;
+            writeln!(
+                out,
+                "ixperf_op_latency_seconds_count{{{}}} {}",
+                labels, count
+            )
+            .ok();
+        }
+
+        if let Some((index, n)) = &state.index {
+            header(
+                &mut out,
+                "ixperf_index_entries",
+                "gauge",
+                "Entries in index.",
+            );
+            writeln!(out, r#"ixperf_index_entries{{index="{}"}} {}"#, index, n).ok();
+        }
+
+        if let Some((cpu_load, mem_rss)) = state.system {
+            header(
+                &mut out,
+                "ixperf_cpu_load",
+                "gauge",
+                "Process cpu, in percentage.",
+            );
+            writeln!(out, "ixperf_cpu_load {:.2}", cpu_load).ok();
+            header(&mut out, "ixperf_mem_rss_bytes", "gauge", "Process rss.");
+            writeln!(out, "ixperf_mem_rss_bytes {}", mem_rss * 1024 * 1024).ok();
+        }
+
+        out
+    }
+}
+
+impl State {
+    fn to_ops(&self) -> Vec<(&str, &OpMetrics)> {
+        let mut ops = vec![];
+        for (name, phase) in self.phases.iter() {
+            for op in phase.ops.iter() {
+                ops.push((name.as_str(), op));
+            }
+        }
+        ops
+    }
+}
+
+impl OpMetrics {
+    fn new(op: &stats::Op) -> OpMetrics {
+        let lat = &op.latency;
+        OpMetrics {
+            name: op.name.clone(),
+            count: op.count,
+            outcomes: vec![
+                ("inserted", op.inserted),
+                ("updated", op.updated),
+                ("hit", op.hit),
+                ("missed", op.missed),
+                ("items_scanned", op.items_scanned),
+            ],
+            error_kinds: op
+                .error_kinds
+                .iter()
+                .map(|(kind, n)| (kind.clone(), *n))
+                .collect(),
+            throughput: op.to_throughput(),
+            quantiles: vec![
+                ("0.5", lat.to_percentile(50)),
+                ("0.9", lat.to_percentile(90)),
+                ("0.99", lat.to_percentile(99)),
+                ("1", lat.to_max()),
+            ],
+            latency_count: lat.to_samples(),
+            latency_sum: lat.elapsed(),
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, typ: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).ok();
+    writeln!(out, "# TYPE {} {}", name, typ).ok();
+}
+
+fn to_labels(phase: &str, op: &str) -> String {
+    format!(r#"phase="{}",op="{}""#, phase, op)
+}
+
+fn to_seconds(nanos: u128) -> f64 {
+    (nanos as f64) / 1_000_000_000_f64
+}
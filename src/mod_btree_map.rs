@@ -1,27 +1,31 @@
 use std::{
-    collections::BTreeMap,
-    time::{Duration, SystemTime},
+    cell::RefCell,
+    collections::{btree_map::Entry, BTreeMap},
+    fmt, mem,
+    ops::Bound,
+    rc::Rc,
 };
 
-use log::{debug, info};
+use log::debug;
 
-use crate::generator::{Cmd, IncrementalLoad, InitialLoad, RandomKV};
-use crate::stats;
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit, WriterAdapter};
+use crate::generator::RandomKV;
+use crate::utils;
 use crate::Profile;
 
 #[cfg(feature = "all_types")]
 pub fn perf(name: &str, p: Profile) -> Result<(), String> {
     match (p.key_type.as_str(), p.val_type.as_str()) {
-        ("i32", "i32") => Ok(do_perf::<i32, i32>(name, p)),
-        ("i32", "i64") => Ok(do_perf::<i32, i64>(name, p)),
-        ("i32", "array") => Ok(do_perf::<i32, [u8; 20]>(name, p)),
-        ("i32", "bytes") => Ok(do_perf::<i32, Vec<u8>>(name, p)),
-        ("i64", "i64") => Ok(do_perf::<i64, i64>(name, p)),
-        ("i64", "array") => Ok(do_perf::<i64, [u8; 20]>(name, p)),
-        ("i64", "bytes") => Ok(do_perf::<i64, Vec<u8>>(name, p)),
-        ("array", "array") => Ok(do_perf::<[u8; 20], [u8; 20]>(name, p)),
-        ("array", "bytes") => Ok(do_perf::<[u8; 20], Vec<u8>>(name, p)),
-        ("bytes", "bytes") => Ok(do_perf::<Vec<u8>, Vec<u8>>(name, p)),
+        ("i32", "i32") => do_perf::<i32, i32>(name, p),
+        ("i32", "i64") => do_perf::<i32, i64>(name, p),
+        ("i32", "array") => do_perf::<i32, [u8; 20]>(name, p),
+        ("i32", "bytes") => do_perf::<i32, Vec<u8>>(name, p),
+        ("i64", "i64") => do_perf::<i64, i64>(name, p),
+        ("i64", "array") => do_perf::<i64, [u8; 20]>(name, p),
+        ("i64", "bytes") => do_perf::<i64, Vec<u8>>(name, p),
+        ("array", "array") => do_perf::<[u8; 20], [u8; 20]>(name, p),
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(name, p),
+        ("bytes", "bytes") => do_perf::<Vec<u8>, Vec<u8>>(name, p),
         _ => Err(format!(
             "unsupported key/value types {}/{}",
             p.key_type, p.val_type
@@ -31,7 +35,7 @@ pub fn perf(name: &str, p: Profile) -> Result<(), String> {
 #[cfg(not(feature = "all_types"))]
 pub fn perf(name: &str, p: Profile) -> Result<(), String> {
     match (p.key_type.as_str(), p.val_type.as_str()) {
-        ("array", "bytes") => Ok(do_perf::<[u8; 20], Vec<u8>>(name, p)),
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(name, p),
         _ => Err(format!(
             "unsupported key/value types {}/{}",
             p.key_type, p.val_type
@@ -39,116 +43,197 @@ pub fn perf(name: &str, p: Profile) -> Result<(), String> {
     }
 }
 
-fn do_perf<K, V>(_name: &str, p: Profile)
+fn do_perf<K, V>(_name: &str, p: Profile) -> Result<(), String>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + RandomKV,
-    V: 'static + Clone + Default + Send + Sync + RandomKV,
+    K: 'static + Clone + Default + Send + Sync + Ord + fmt::Debug + RandomKV,
+    V: 'static + Clone + Default + Send + Sync + PartialEq + fmt::Debug + RandomKV,
 {
     debug!(
         target: "ixperf",
         "intial load for type <{},{}>", p.key_type, p.val_type
     );
 
-    let mut map: BTreeMap<K, V> = BTreeMap::new();
-    do_initial_load(&mut map, &p);
-    do_incremental(&mut map, &p);
+    let mut index = BTreeMapIndex(Rc::new(RefCell::new(BTreeMap::new())));
+    adapter::do_perf(&mut index, &p)?;
+    log_memory(&index.0.borrow());
+    Ok(())
 }
 
-fn do_initial_load<K, V>(map: &mut BTreeMap<K, V>, p: &Profile)
+// std BTreeMap nodes hold up to 11 keys and 11 values inline, along
+// with parent link and length. Nodes are assumed to be 2/3 full, and
+// internal nodes, about a sixth of the leaf nodes, are ignored.
+fn log_memory<K, V>(map: &BTreeMap<K, V>)
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + RandomKV,
-    V: 'static + Clone + Default + Send + Sync + RandomKV,
+    K: RandomKV,
+    V: RandomKV,
 {
-    let load_ops = p.g.loads;
-    if load_ops == 0 {
-        return;
-    }
-
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = InitialLoad::<K, V>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Load { key, value } => {
-                    lstats.load.sample_start(false);
-                    let items = map.insert(key, value).map_or(0, |_| 1);
-                    lstats.load.sample_end(items);
-                }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(&p.cmd_opts, "ixperf", "initial periodic-stats\n{}", lstats);
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
+    let node = 11 * (mem::size_of::<K>() + mem::size_of::<V>()) + 16;
+    let nodes = (map.len() + 6) / 7;
+    let heap = map.iter().map(|(k, v)| k.heap_size() + v.heap_size());
+    utils::log_memory("btree-map", map.len(), nodes * node, heap.sum());
+}
+
+/// BTreeMap is a single threaded index, all readers and writers share
+/// the same map.
+pub(crate) struct BTreeMapIndex<K, V>(pub Rc<RefCell<BTreeMap<K, V>>>);
+
+impl<K, V> IndexAdapter<K, V> for BTreeMapIndex<K, V>
+where
+    K: Ord,
+{
+    type R = BTreeMapIndex<K, V>;
+    type W = BTreeMapIndex<K, V>;
+
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(BTreeMapIndex(Rc::clone(&self.0)))
+    }
+
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        Ok(BTreeMapIndex(Rc::clone(&self.0)))
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        Some(self.0.borrow().len())
+    }
+}
+
+impl<K, V> ReaderAdapter<K, V> for BTreeMapIndex<K, V>
+where
+    K: Ord,
+{
+    fn get(&mut self, key: &K, mut visit: Visit<K, V>) -> Result<bool, String> {
+        match self.0.borrow().get(key) {
+            Some(value) => {
+                adapter::do_visit(&mut visit, key, value);
+                Ok(true)
             }
+            None => Ok(false),
         }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+    }
 
-    stats!(&p.cmd_opts, "ixperf", "initial stats\n{:?}", fstats);
-    info!(
-        target: "ixperf",
-        "initial-load load_ops:{} map.len:{} elapsed:{:?}",
-        load_ops, map.len(), elapsed
-    );
+    fn multi_get(&mut self, keys: &[K], mut visit: Visit<K, V>) -> Result<usize, String> {
+        let map = self.0.borrow();
+        let mut n = 0;
+        for key in keys.iter() {
+            if let Some(value) = map.get(key) {
+                adapter::do_visit(&mut visit, key, value);
+                n += 1;
+            }
+        }
+        Ok(n)
+    }
+
+    fn range(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        if adapter::is_empty_range(&low, &high) {
+            return Ok(0);
+        }
+        let map = self.0.borrow();
+        let mut n = 0;
+        for (key, value) in map.range((low, high)) {
+            adapter::do_visit(&mut visit, key, value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn reverse(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        if adapter::is_empty_range(&low, &high) {
+            return Ok(0);
+        }
+        let map = self.0.borrow();
+        let mut n = 0;
+        for (key, value) in map.range((low, high)).rev() {
+            adapter::do_visit(&mut visit, key, value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn scan(&mut self, start: &K, limit: usize, mut visit: Visit<K, V>) -> Result<usize, String> {
+        let map = self.0.borrow();
+        let mut n = 0;
+        let iter = map.range((Bound::Included(start), Bound::Unbounded));
+        for (key, value) in iter.take(limit) {
+            adapter::do_visit(&mut visit, key, value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn reverse_scan(
+        &mut self,
+        start: &K,
+        limit: usize,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let map = self.0.borrow();
+        let mut n = 0;
+        let iter = map.range((Bound::Unbounded, Bound::Included(start)));
+        for (key, value) in iter.rev().take(limit) {
+            adapter::do_visit(&mut visit, key, value);
+            n += 1;
+        }
+        Ok(n)
+    }
 }
 
-fn do_incremental<K, V>(index: &mut BTreeMap<K, V>, p: &Profile)
+impl<K, V> WriterAdapter<K, V> for BTreeMapIndex<K, V>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + RandomKV,
-    V: 'static + Clone + Default + Send + Sync + RandomKV,
+    K: Ord,
 {
-    if (p.g.read_ops() + p.g.write_ops()) == 0 {
-        return;
-    }
-
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-        let mut lstats = stats::Ops::new();
-        let gen = IncrementalLoad::<K, V>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Set { key, value } => {
-                    lstats.set.sample_start(false);
-                    let n = index.insert(key, value.clone()).map_or(0, |_| 1);
-                    lstats.set.sample_end(n);
-                }
-                Cmd::Delete { key } => {
-                    lstats.delete.sample_start(false);
-                    let items = index.remove(&key).map_or(1, |_| 0);
-                    lstats.delete.sample_end(items);
-                }
-                Cmd::Get { key } => {
-                    lstats.get.sample_start(false);
-                    let items = index.get(&key).map_or(1, |_| 0);
-                    lstats.get.sample_end(items);
-                }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    &p.cmd_opts,
-                    "ixperf",
-                    "incremental periodic-stats\n{}",
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
+    fn set(&mut self, key: K, value: V) -> Result<bool, String> {
+        Ok(self.0.borrow_mut().insert(key, value).is_some())
+    }
+
+    fn delete(&mut self, key: &K) -> Result<bool, String> {
+        Ok(self.0.borrow_mut().remove(key).is_some())
+    }
+
+    fn read_modify_write(
+        &mut self,
+        key: K,
+        modify: &mut dyn FnMut(Option<&V>) -> V,
+    ) -> Result<bool, String> {
+        let mut map = self.0.borrow_mut();
+        match map.get_mut(&key) {
+            Some(value) => {
+                *value = modify(Some(value));
+                Ok(true)
+            }
+            None => {
+                map.insert(key, modify(None));
+                Ok(false)
             }
         }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+    }
 
-    stats!(&p.cmd_opts, "ixperf", "incremental stats\n{:?}", fstats);
-    info!(
-        target: "ixperf",
-        "incremental-load r_ops:{} w_ops:{}, map.len:{} elapsed:{:?}",
-        p.g.read_ops(), p.g.write_ops(), index.len(), elapsed
-    );
+    fn set_if_absent(&mut self, key: K, value: V) -> Result<bool, String> {
+        match self.0.borrow_mut().entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                Ok(true)
+            }
+            Entry::Occupied(_) => Ok(false),
+        }
+    }
+
+    fn set_if_present(&mut self, key: K, value: V) -> Result<bool, String> {
+        match self.0.borrow_mut().get_mut(&key) {
+            Some(old) => {
+                *old = value;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
 }
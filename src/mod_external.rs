@@ -0,0 +1,532 @@
+//! Benchmark an index running as an external process.
+//!
+//! ixperf spawns `command` with `args` and drives it with a framed
+//! binary protocol over the process's stdin and stdout. stderr is
+//! inherited. Latency is measured by ixperf, hence it includes the
+//! round-trip over the pipes. Only `bytes` key and value types are
+//! supported.
+//!
+//! All integers are big-endian. A byte-string is encoded as `u32`
+//! length followed by as many bytes. A bound is encoded as `u8` kind,
+//! 0 for unbounded, 1 for included and 2 for excluded, followed by the
+//! key byte-string for included and excluded bounds.
+//!
+//! Every request starts with a `u8` op, followed by its arguments:
+//!
+//! | op | request          | arguments                          |
+//! |----|------------------|------------------------------------|
+//! | 0  | close            |                                    |
+//! | 1  | load             | key, value                         |
+//! | 2  | set              | key, value                         |
+//! | 3  | delete           | key                                |
+//! | 4  | get              | u8 with_entries, key               |
+//! | 5  | range            | u8 with_entries, low, high         |
+//! | 6  | reverse          | u8 with_entries, low, high         |
+//! | 7  | iter             | u8 with_entries                    |
+//! | 8  | commit           |                                    |
+//! | 9  | len              |                                    |
+//! | 10 | scan             | u8 with_entries, start, u64 limit  |
+//! | 11 | reverse-scan     | u8 with_entries, start, u64 limit  |
+//!
+//! Every response starts with a `u8` status. Status 1 is an error,
+//! followed by the error message as byte-string. Status 0 is success,
+//! followed by:
+//!
+//! * close, commit: nothing, after responding to close the process
+//!   is expected to exit.
+//! * load, set: `u8` 1 if an older value was updated, else 0.
+//! * delete: `u8` 1 if key was found, else 0.
+//! * get: `u8` 1 if key was found, else 0. If found and with_entries
+//!   is 1, followed by the value.
+//! * range, reverse, iter, scan, reverse-scan: `u64` count of entries.
+//!   If with_entries is 1, followed by count number of key and value,
+//!   in iteration order. scan and reverse-scan return at most limit
+//!   entries, starting from the start key if present.
+//! * len: `u64` number of entries in the index.
+//!
+//! There are no ops for read-modify-write and conditional sets, they
+//! are composed by ixperf from get and set, hence they are not atomic
+//! and their latency includes two round-trips.
+
+use log::{info, warn};
+
+use std::{
+    cell::RefCell,
+    convert::{TryFrom, TryInto},
+    io::{self, BufReader, BufWriter, Read, Write},
+    ops::Bound,
+    process,
+    rc::Rc,
+};
+
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit, WriterAdapter};
+use crate::Profile;
+
+const OP_CLOSE: u8 = 0;
+const OP_LOAD: u8 = 1;
+const OP_SET: u8 = 2;
+const OP_DELETE: u8 = 3;
+const OP_GET: u8 = 4;
+const OP_RANGE: u8 = 5;
+const OP_REVERSE: u8 = 6;
+const OP_ITER: u8 = 7;
+const OP_COMMIT: u8 = 8;
+const OP_LEN: u8 = 9;
+const OP_SCAN: u8 = 10;
+const OP_REVERSE_SCAN: u8 = 11;
+
+const STATUS_OK: u8 = 0;
+const STATUS_ERROR: u8 = 1;
+
+#[derive(Default, Clone)]
+pub struct ExternalOpt {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl TryFrom<toml::Value> for ExternalOpt {
+    type Error = String;
+
+    fn try_from(value: toml::Value) -> Result<Self, Self::Error> {
+        let mut opt: ExternalOpt = Default::default();
+
+        let section = match &value.get("external") {
+            None => return Err("not found".to_string()),
+            Some(section) => section.clone(),
+        };
+        for (name, value) in section.as_table().unwrap().iter() {
+            match name.as_str() {
+                "command" => opt.command = value.as_str().unwrap().to_string(),
+                "args" => {
+                    opt.args = value
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|arg| arg.as_str().unwrap().to_string())
+                        .collect()
+                }
+                _ => panic!("invalid profile parameter {}", name),
+            }
+        }
+        Ok(opt)
+    }
+}
+
+pub fn perf(_name: &str, p: Profile) -> Result<(), String> {
+    match (p.key_type.as_str(), p.val_type.as_str()) {
+        ("bytes", "bytes") => do_perf(p),
+        _ => Err(format!(
+            "unsupported key/value types {}/{}",
+            p.key_type, p.val_type
+        )),
+    }
+}
+
+fn do_perf(p: Profile) -> Result<(), String> {
+    if p.external.command.len() == 0 {
+        return Err("please provide [external] command".to_string());
+    }
+
+    info!(
+        target: "ixperf",
+        "external index {} {:?}", p.external.command, p.external.args
+    );
+
+    let mut index = ExternalIndex(Rc::new(RefCell::new(Conn::spawn(&p.external)?)));
+    let res = adapter::do_perf(&mut index, &p);
+    let mut conn = index.0.borrow_mut();
+    match res {
+        // connection might be out of sync, process may never respond
+        // to close, kill it and report the run error.
+        Err(err) => {
+            conn.kill();
+            Err(err)
+        }
+        Ok(_) => conn.close(),
+    }
+}
+
+/// Connection to the external process, all readers and writers share
+/// the same connection.
+struct ExternalIndex(Rc<RefCell<Conn>>);
+
+impl IndexAdapter<Vec<u8>, Vec<u8>> for ExternalIndex {
+    type R = ExternalIndex;
+    type W = ExternalIndex;
+
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(ExternalIndex(Rc::clone(&self.0)))
+    }
+
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        Ok(ExternalIndex(Rc::clone(&self.0)))
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        let mut conn = self.0.borrow_mut();
+        conn.request(OP_LEN, |_| Ok(()))
+            .and_then(|conn| conn.read_u64())
+            .ok()
+            .map(|n| n as usize)
+    }
+}
+
+impl ReaderAdapter<Vec<u8>, Vec<u8>> for ExternalIndex {
+    fn get(&mut self, key: &Vec<u8>, mut visit: Visit<Vec<u8>, Vec<u8>>) -> Result<bool, String> {
+        let with_entries = visit.is_some();
+
+        let mut conn = self.0.borrow_mut();
+        let conn = conn.request(OP_GET, |w| {
+            write_bool(w, with_entries)?;
+            write_bytes(w, key)
+        })?;
+        let found = conn.read_bool()?;
+        if found && with_entries {
+            let value = conn.read_bytes()?;
+            adapter::do_visit(&mut visit, key, &value);
+        }
+        Ok(found)
+    }
+
+    fn range(
+        &mut self,
+        low: Bound<Vec<u8>>,
+        high: Bound<Vec<u8>>,
+        visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        self.0.borrow_mut().scan(OP_RANGE, &low, &high, visit)
+    }
+
+    fn reverse(
+        &mut self,
+        low: Bound<Vec<u8>>,
+        high: Bound<Vec<u8>>,
+        visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        self.0.borrow_mut().scan(OP_REVERSE, &low, &high, visit)
+    }
+
+    fn scan(
+        &mut self,
+        start: &Vec<u8>,
+        limit: usize,
+        visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        self.0.borrow_mut().scan_limit(OP_SCAN, start, limit, visit)
+    }
+
+    fn reverse_scan(
+        &mut self,
+        start: &Vec<u8>,
+        limit: usize,
+        visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        self.0
+            .borrow_mut()
+            .scan_limit(OP_REVERSE_SCAN, start, limit, visit)
+    }
+
+    fn iter(&mut self, mut visit: Visit<Vec<u8>, Vec<u8>>) -> Result<usize, String> {
+        let with_entries = visit.is_some();
+
+        let mut conn = self.0.borrow_mut();
+        let conn = conn.request(OP_ITER, |w| write_bool(w, with_entries))?;
+        conn.read_entries(with_entries, &mut visit)
+    }
+}
+
+impl WriterAdapter<Vec<u8>, Vec<u8>> for ExternalIndex {
+    fn set(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<bool, String> {
+        let mut conn = self.0.borrow_mut();
+        let conn = conn.request(OP_SET, |w| {
+            write_bytes(w, &key)?;
+            write_bytes(w, &value)
+        })?;
+        conn.read_bool()
+    }
+
+    fn delete(&mut self, key: &Vec<u8>) -> Result<bool, String> {
+        let mut conn = self.0.borrow_mut();
+        let conn = conn.request(OP_DELETE, |w| write_bytes(w, key))?;
+        conn.read_bool()
+    }
+
+    fn load(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<bool, String> {
+        let mut conn = self.0.borrow_mut();
+        let conn = conn.request(OP_LOAD, |w| {
+            write_bytes(w, &key)?;
+            write_bytes(w, &value)
+        })?;
+        conn.read_bool()
+    }
+
+    fn read_modify_write(
+        &mut self,
+        key: Vec<u8>,
+        modify: &mut dyn FnMut(Option<&Vec<u8>>) -> Vec<u8>,
+    ) -> Result<bool, String> {
+        let old = self.get_value(&key)?;
+        let value = modify(old.as_ref());
+        self.set(key, value)?;
+        Ok(old.is_some())
+    }
+
+    fn set_if_absent(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<bool, String> {
+        match self.get_value(&key)? {
+            Some(_) => Ok(false),
+            None => {
+                self.set(key, value)?;
+                Ok(true)
+            }
+        }
+    }
+
+    fn set_if_present(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<bool, String> {
+        match self.get_value(&key)? {
+            Some(_) => {
+                self.set(key, value)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn commit(&mut self) -> Result<(), String> {
+        self.0.borrow_mut().request(OP_COMMIT, |_| Ok(()))?;
+        Ok(())
+    }
+}
+
+impl ExternalIndex {
+    fn get_value(&mut self, key: &Vec<u8>) -> Result<Option<Vec<u8>>, String> {
+        let mut conn = self.0.borrow_mut();
+        let conn = conn.request(OP_GET, |w| {
+            write_bool(w, true /*with_entries*/)?;
+            write_bytes(w, key)
+        })?;
+        match conn.read_bool()? {
+            true => Ok(Some(conn.read_bytes()?)),
+            false => Ok(None),
+        }
+    }
+}
+
+struct Conn {
+    child: Option<process::Child>,
+    stdin: BufWriter<Box<dyn Write>>,
+    stdout: BufReader<Box<dyn Read>>,
+    // a request or response was partially transferred, or malformed,
+    // rest of the stream can't be trusted.
+    broken: bool,
+}
+
+impl Conn {
+    // connection over any stream, with no process to manage.
+    fn with_stream(stdin: Box<dyn Write>, stdout: Box<dyn Read>) -> Conn {
+        Conn {
+            child: None,
+            stdin: BufWriter::new(stdin),
+            stdout: BufReader::new(stdout),
+            broken: false,
+        }
+    }
+
+    fn spawn(opt: &ExternalOpt) -> Result<Conn, String> {
+        let mut child = process::Command::new(&opt.command)
+            .args(&opt.args)
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::piped())
+            .stderr(process::Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("{}: {}", opt.command, e))?;
+
+        let stdin = Box::new(child.stdin.take().unwrap());
+        let stdout = Box::new(child.stdout.take().unwrap());
+        let mut conn = Conn::with_stream(stdin, stdout);
+        conn.child = Some(child);
+        Ok(conn)
+    }
+
+    // close gracefully, if the connection is out of sync kill the process
+    // instead.
+    fn close(&mut self) -> Result<(), String> {
+        if self.broken {
+            self.kill();
+            return Ok(());
+        }
+        if let Err(err) = self.request(OP_CLOSE, |_| Ok(())) {
+            self.kill();
+            return Err(err);
+        }
+        if let Some(child) = &mut self.child {
+            let status = child.wait().map_err(to_error)?;
+            info!(target: "ixperf", "external index exited with {}", status);
+        }
+        Ok(())
+    }
+
+    fn kill(&mut self) {
+        if let Some(child) = &mut self.child {
+            match child.kill().and_then(|_| child.wait()) {
+                Ok(status) => warn!(target: "ixperf", "external index killed, {}", status),
+                Err(err) => warn!(target: "ixperf", "external index kill: {}", err),
+            }
+        }
+    }
+
+    // send request and read the response status, on success return
+    // self to read the rest of the response.
+    fn request<F>(&mut self, op: u8, args: F) -> Result<&mut Conn, String>
+    where
+        F: FnOnce(&mut BufWriter<Box<dyn Write>>) -> Result<(), String>,
+    {
+        if self.broken {
+            return Err(format!("external op:{} connection out of sync", op));
+        }
+        if let Err(err) = self.send(op, args) {
+            self.broken = true;
+            return Err(err);
+        }
+
+        match self.read_u8()? {
+            STATUS_OK => Ok(self),
+            STATUS_ERROR => {
+                let msg = self.read_bytes()?;
+                Err(format!(
+                    "external op:{} {}",
+                    op,
+                    String::from_utf8_lossy(&msg)
+                ))
+            }
+            status => {
+                self.broken = true;
+                Err(format!("external op:{} invalid status {}", op, status))
+            }
+        }
+    }
+
+    fn send<F>(&mut self, op: u8, args: F) -> Result<(), String>
+    where
+        F: FnOnce(&mut BufWriter<Box<dyn Write>>) -> Result<(), String>,
+    {
+        self.stdin.write_all(&[op]).map_err(to_error)?;
+        args(&mut self.stdin)?;
+        self.stdin.flush().map_err(to_error)
+    }
+
+    fn scan(
+        &mut self,
+        op: u8,
+        low: &Bound<Vec<u8>>,
+        high: &Bound<Vec<u8>>,
+        mut visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        let with_entries = visit.is_some();
+
+        let conn = self.request(op, |w| {
+            write_bool(w, with_entries)?;
+            write_bound(w, low)?;
+            write_bound(w, high)
+        })?;
+        conn.read_entries(with_entries, &mut visit)
+    }
+
+    fn scan_limit(
+        &mut self,
+        op: u8,
+        start: &[u8],
+        limit: usize,
+        mut visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        let with_entries = visit.is_some();
+
+        let conn = self.request(op, |w| {
+            write_bool(w, with_entries)?;
+            write_bytes(w, start)?;
+            w.write_all(&(limit as u64).to_be_bytes()).map_err(to_error)
+        })?;
+        conn.read_entries(with_entries, &mut visit)
+    }
+
+    fn read_entries(
+        &mut self,
+        with_entries: bool,
+        visit: &mut Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        let n: usize = self.read_u64()?.try_into().map_err(|_| "invalid count")?;
+        if with_entries {
+            for _ in 0..n {
+                let key = self.read_bytes()?;
+                let value = self.read_bytes()?;
+                adapter::do_visit(visit, &key, &value);
+            }
+        }
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), String> {
+        match self.stdout.read_exact(buf) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                self.broken = true;
+                Err(to_error(err))
+            }
+        }
+    }
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        let mut buf = [0_u8; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_bool(&mut self) -> Result<bool, String> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    fn read_u64(&mut self) -> Result<u64, String> {
+        let mut buf = [0_u8; 8];
+        self.read_exact(&mut buf)?;
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>, String> {
+        let mut buf = [0_u8; 4];
+        self.read_exact(&mut buf)?;
+        let mut data = vec![0_u8; u32::from_be_bytes(buf) as usize];
+        self.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+fn write_bool<W: Write>(w: &mut W, val: bool) -> Result<(), String> {
+    w.write_all(&[val as u8]).map_err(to_error)
+}
+
+fn write_bytes<W: Write>(w: &mut W, data: &[u8]) -> Result<(), String> {
+    let n: u32 = data.len().try_into().map_err(|_| "key/value too large")?;
+    w.write_all(&n.to_be_bytes()).map_err(to_error)?;
+    w.write_all(data).map_err(to_error)
+}
+
+fn write_bound<W: Write>(w: &mut W, bound: &Bound<Vec<u8>>) -> Result<(), String> {
+    match bound {
+        Bound::Unbounded => w.write_all(&[0]).map_err(to_error),
+        Bound::Included(key) => {
+            w.write_all(&[1]).map_err(to_error)?;
+            write_bytes(w, key)
+        }
+        Bound::Excluded(key) => {
+            w.write_all(&[2]).map_err(to_error)?;
+            write_bytes(w, key)
+        }
+    }
+}
+
+fn to_error(err: io::Error) -> String {
+    format!("external: {}", err)
+}
+
+#[cfg(test)]
+#[path = "mod_external_test.rs"]
+mod mod_external_test;
@@ -0,0 +1,214 @@
+use std::{collections::BTreeMap, net, thread};
+
+use super::*;
+
+#[test]
+fn test_external_protocol() {
+    let (mut index, server) = connect();
+    let mut w = index.to_writer().unwrap();
+    let mut r = index.to_reader().unwrap();
+
+    for i in 0..10_u8 {
+        assert_eq!(w.load(vec![i], vec![i * 10]).unwrap(), false);
+    }
+    w.commit().unwrap();
+    assert_eq!(w.set(vec![5], vec![55]).unwrap(), true);
+    assert_eq!(w.set(vec![10], vec![100]).unwrap(), false);
+    assert_eq!(w.delete(&vec![9]).unwrap(), true);
+    assert_eq!(w.delete(&vec![9]).unwrap(), false);
+    assert_eq!(index.len(), Some(10));
+
+    let mut entries = vec![];
+    {
+        let mut visit = |k: &Vec<u8>, v: &Vec<u8>| entries.push((k[0], v[0]));
+        assert_eq!(r.get(&vec![5], Some(&mut visit)).unwrap(), true);
+        assert_eq!(r.get(&vec![9], Some(&mut visit)).unwrap(), false);
+    }
+    assert_eq!(entries, vec![(5, 55)]);
+    assert_eq!(r.get(&vec![1], None).unwrap(), true);
+
+    entries.clear();
+    {
+        let mut visit = |k: &Vec<u8>, v: &Vec<u8>| entries.push((k[0], v[0]));
+        let (low, high) = (Bound::Excluded(vec![2]), Bound::Included(vec![4]));
+        assert_eq!(r.range(low, high, Some(&mut visit)).unwrap(), 2);
+        let (low, high) = (Bound::Included(vec![7]), Bound::Unbounded);
+        assert_eq!(r.reverse(low, high, Some(&mut visit)).unwrap(), 3);
+    }
+    assert_eq!(entries, vec![(3, 30), (4, 40), (10, 100), (8, 80), (7, 70)]);
+
+    entries.clear();
+    {
+        let mut visit = |k: &Vec<u8>, v: &Vec<u8>| entries.push((k[0], v[0]));
+        assert_eq!(r.scan(&vec![8], 2, Some(&mut visit)).unwrap(), 2);
+        assert_eq!(r.reverse_scan(&vec![1], 5, Some(&mut visit)).unwrap(), 2);
+    }
+    assert_eq!(entries, vec![(8, 80), (10, 100), (1, 10), (0, 0)]);
+    assert_eq!(r.iter(None).unwrap(), 10);
+
+    // composed from get and set.
+    let mut modify = |old: Option<&Vec<u8>>| vec![old.map(|v| v[0] + 1).unwrap_or(0)];
+    assert_eq!(w.read_modify_write(vec![3], &mut modify).unwrap(), true);
+    assert_eq!(w.set_if_absent(vec![3], vec![0]).unwrap(), false);
+    assert_eq!(w.set_if_present(vec![9], vec![0]).unwrap(), false);
+    assert_eq!(w.get_value(&vec![3]).unwrap(), Some(vec![31]));
+
+    drop(w);
+    drop(r);
+    index.0.borrow_mut().close().unwrap();
+    server.join().unwrap();
+}
+
+#[test]
+fn test_external_error() {
+    let (mut index, server) = connect();
+    let mut w = index.to_writer().unwrap();
+
+    // error response keeps the connection in sync.
+    let res = w.0.borrow_mut().request(99, |_| Ok(())).map(|_| ());
+    assert_eq!(res, Err("external op:99 invalid op 99".to_string()));
+    assert_eq!(w.set(vec![1], vec![1]).unwrap(), false);
+
+    drop(w);
+    index.0.borrow_mut().close().unwrap();
+    server.join().unwrap();
+}
+
+#[test]
+fn test_external_kill() {
+    let opt = ExternalOpt {
+        command: "sleep".to_string(),
+        args: vec!["60".to_string()],
+    };
+    let mut conn = Conn::spawn(&opt).unwrap();
+    conn.broken = true;
+    assert!(conn.request(OP_LEN, |_| Ok(())).is_err());
+    // must not wait for sleep to respond.
+    conn.close().unwrap();
+    assert!(conn.child.as_mut().unwrap().try_wait().unwrap().is_some());
+}
+
+// connect to a minimal external index, over loopback, running in a
+// thread.
+fn connect() -> (ExternalIndex, thread::JoinHandle<()>) {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || serve(listener.accept().unwrap().0));
+
+    let stream = net::TcpStream::connect(addr).unwrap();
+    let stdin = Box::new(stream.try_clone().unwrap());
+    let conn = Conn::with_stream(stdin, Box::new(stream));
+    (ExternalIndex(Rc::new(RefCell::new(conn))), server)
+}
+
+// serve the protocol from a BTreeMap, until close.
+fn serve(stream: net::TcpStream) {
+    let mut r = BufReader::new(stream.try_clone().unwrap());
+    let mut w = BufWriter::new(stream);
+    let mut index: BTreeMap<Vec<u8>, Vec<u8>> = BTreeMap::new();
+
+    loop {
+        let op = read_u8(&mut r);
+        let mut resp = vec![STATUS_OK];
+        match op {
+            OP_CLOSE => (),
+            OP_LOAD | OP_SET => {
+                let (key, value) = (read_bytes(&mut r), read_bytes(&mut r));
+                resp.push(index.insert(key, value).is_some() as u8);
+            }
+            OP_DELETE => {
+                let key = read_bytes(&mut r);
+                resp.push(index.remove(&key).is_some() as u8);
+            }
+            OP_GET => {
+                let with_entries = read_u8(&mut r) == 1;
+                match index.get(&read_bytes(&mut r)) {
+                    Some(value) if with_entries => {
+                        resp.push(1);
+                        put_bytes(&mut resp, value);
+                    }
+                    Some(_) => resp.push(1),
+                    None => resp.push(0),
+                }
+            }
+            OP_RANGE | OP_REVERSE => {
+                let with_entries = read_u8(&mut r) == 1;
+                let (low, high) = (read_bound(&mut r), read_bound(&mut r));
+                let mut entries: Vec<_> = index.range((low, high)).collect();
+                if op == OP_REVERSE {
+                    entries.reverse();
+                }
+                put_entries(&mut resp, with_entries, entries);
+            }
+            OP_ITER => {
+                let with_entries = read_u8(&mut r) == 1;
+                put_entries(&mut resp, with_entries, index.iter().collect());
+            }
+            OP_COMMIT => (),
+            OP_LEN => resp.extend_from_slice(&(index.len() as u64).to_be_bytes()),
+            OP_SCAN | OP_REVERSE_SCAN => {
+                let with_entries = read_u8(&mut r) == 1;
+                let start = read_bytes(&mut r);
+                let limit = read_u64(&mut r) as usize;
+                let entries: Vec<_> = match op {
+                    OP_SCAN => index.range(start..).take(limit).collect(),
+                    _ => index.range(..=start).rev().take(limit).collect(),
+                };
+                put_entries(&mut resp, with_entries, entries);
+            }
+            op => {
+                resp = vec![STATUS_ERROR];
+                put_bytes(&mut resp, format!("invalid op {}", op).as_bytes());
+            }
+        }
+        w.write_all(&resp).unwrap();
+        w.flush().unwrap();
+        if op == OP_CLOSE {
+            break;
+        }
+    }
+}
+
+fn read_u8<R: Read>(r: &mut R) -> u8 {
+    let mut buf = [0_u8; 1];
+    r.read_exact(&mut buf).unwrap();
+    buf[0]
+}
+
+fn read_u64<R: Read>(r: &mut R) -> u64 {
+    let mut buf = [0_u8; 8];
+    r.read_exact(&mut buf).unwrap();
+    u64::from_be_bytes(buf)
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> Vec<u8> {
+    let mut buf = [0_u8; 4];
+    r.read_exact(&mut buf).unwrap();
+    let mut data = vec![0_u8; u32::from_be_bytes(buf) as usize];
+    r.read_exact(&mut data).unwrap();
+    data
+}
+
+fn read_bound<R: Read>(r: &mut R) -> Bound<Vec<u8>> {
+    match read_u8(r) {
+        0 => Bound::Unbounded,
+        1 => Bound::Included(read_bytes(r)),
+        2 => Bound::Excluded(read_bytes(r)),
+        kind => panic!("invalid bound {}", kind),
+    }
+}
+
+fn put_bytes(resp: &mut Vec<u8>, data: &[u8]) {
+    resp.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    resp.extend_from_slice(data);
+}
+
+fn put_entries(resp: &mut Vec<u8>, with_entries: bool, entries: Vec<(&Vec<u8>, &Vec<u8>)>) {
+    resp.extend_from_slice(&(entries.len() as u64).to_be_bytes());
+    if with_entries {
+        for (key, value) in entries.into_iter() {
+            put_bytes(resp, key);
+            put_bytes(resp, value);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
+    hash::Hash,
+    mem,
+    ops::Bound,
+    rc::Rc,
+};
+
+use log::debug;
+
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit, WriterAdapter};
+use crate::generator::RandomKV;
+use crate::utils;
+use crate::Profile;
+
+#[cfg(feature = "all_types")]
+pub fn perf(name: &str, p: Profile) -> Result<(), String> {
+    match (p.key_type.as_str(), p.val_type.as_str()) {
+        ("i32", "i32") => do_perf::<i32, i32>(name, p),
+        ("i32", "i64") => do_perf::<i32, i64>(name, p),
+        ("i32", "array") => do_perf::<i32, [u8; 20]>(name, p),
+        ("i32", "bytes") => do_perf::<i32, Vec<u8>>(name, p),
+        ("i64", "i64") => do_perf::<i64, i64>(name, p),
+        ("i64", "array") => do_perf::<i64, [u8; 20]>(name, p),
+        ("i64", "bytes") => do_perf::<i64, Vec<u8>>(name, p),
+        ("array", "array") => do_perf::<[u8; 20], [u8; 20]>(name, p),
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(name, p),
+        ("bytes", "bytes") => do_perf::<Vec<u8>, Vec<u8>>(name, p),
+        _ => Err(format!(
+            "unsupported key/value types {}/{}",
+            p.key_type, p.val_type
+        )),
+    }
+}
+#[cfg(not(feature = "all_types"))]
+pub fn perf(name: &str, p: Profile) -> Result<(), String> {
+    match (p.key_type.as_str(), p.val_type.as_str()) {
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(name, p),
+        _ => Err(format!(
+            "unsupported key/value types {}/{}",
+            p.key_type, p.val_type
+        )),
+    }
+}
+
+fn do_perf<K, V>(_name: &str, p: Profile) -> Result<(), String>
+where
+    K: 'static + Clone + Default + Send + Sync + Eq + Hash + RandomKV,
+    V: 'static + Clone + Default + Send + Sync + RandomKV,
+{
+    if p.cmd_opts.verify || p.diff.is_some() {
+        return Err("hash-map does not support verify/diff".to_string());
+    }
+    let n_scans = p.g.scans + p.g.reverse_scans;
+    if p.g.ranges > 0 || p.g.reverses > 0 || n_scans > 0 {
+        return Err(format!(
+            "hash-map does not support ranges:{} reverses:{} scans:{}",
+            p.g.ranges, p.g.reverses, n_scans
+        ));
+    }
+
+    debug!(
+        target: "ixperf",
+        "intial load for type <{},{}>", p.key_type, p.val_type
+    );
+
+    let mut index = HashMapIndex(Rc::new(RefCell::new(HashMap::new())));
+    adapter::do_initial_load(&mut index, &p)?;
+    log_memory(&index.0.borrow());
+
+    adapter::do_iter(&mut index, &p)?;
+
+    adapter::do_incremental(&mut index, &p)?;
+    log_memory(&index.0.borrow());
+
+    Ok(())
+}
+
+/// HashMap is a single threaded index, all readers and writers share
+/// the same map. Ranges are not supported.
+struct HashMapIndex<K, V>(Rc<RefCell<HashMap<K, V>>>);
+
+impl<K, V> IndexAdapter<K, V> for HashMapIndex<K, V>
+where
+    K: Eq + Hash,
+{
+    type R = HashMapIndex<K, V>;
+    type W = HashMapIndex<K, V>;
+
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(HashMapIndex(Rc::clone(&self.0)))
+    }
+
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        Ok(HashMapIndex(Rc::clone(&self.0)))
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        Some(self.0.borrow().len())
+    }
+}
+
+impl<K, V> ReaderAdapter<K, V> for HashMapIndex<K, V>
+where
+    K: Eq + Hash,
+{
+    fn get(&mut self, key: &K, mut visit: Visit<K, V>) -> Result<bool, String> {
+        match self.0.borrow().get(key) {
+            Some(value) => {
+                adapter::do_visit(&mut visit, key, value);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn range(&mut self, _: Bound<K>, _: Bound<K>, _: Visit<K, V>) -> Result<usize, String> {
+        Err("hash-map does not support range".to_string())
+    }
+
+    fn reverse(&mut self, _: Bound<K>, _: Bound<K>, _: Visit<K, V>) -> Result<usize, String> {
+        Err("hash-map does not support reverse".to_string())
+    }
+
+    fn scan(&mut self, _: &K, _: usize, _: Visit<K, V>) -> Result<usize, String> {
+        Err("hash-map does not support scan".to_string())
+    }
+
+    fn reverse_scan(&mut self, _: &K, _: usize, _: Visit<K, V>) -> Result<usize, String> {
+        Err("hash-map does not support reverse_scan".to_string())
+    }
+
+    fn iter(&mut self, mut visit: Visit<K, V>) -> Result<usize, String> {
+        let map = self.0.borrow();
+        let mut n = 0;
+        for (key, value) in map.iter() {
+            adapter::do_visit(&mut visit, key, value);
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+impl<K, V> WriterAdapter<K, V> for HashMapIndex<K, V>
+where
+    K: Eq + Hash,
+{
+    fn set(&mut self, key: K, value: V) -> Result<bool, String> {
+        Ok(self.0.borrow_mut().insert(key, value).is_some())
+    }
+
+    fn delete(&mut self, key: &K) -> Result<bool, String> {
+        Ok(self.0.borrow_mut().remove(key).is_some())
+    }
+
+    fn read_modify_write(
+        &mut self,
+        key: K,
+        modify: &mut dyn FnMut(Option<&V>) -> V,
+    ) -> Result<bool, String> {
+        let mut map = self.0.borrow_mut();
+        match map.get_mut(&key) {
+            Some(value) => {
+                *value = modify(Some(value));
+                Ok(true)
+            }
+            None => {
+                map.insert(key, modify(None));
+                Ok(false)
+            }
+        }
+    }
+
+    fn set_if_absent(&mut self, key: K, value: V) -> Result<bool, String> {
+        match self.0.borrow_mut().entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                Ok(true)
+            }
+            Entry::Occupied(_) => Ok(false),
+        }
+    }
+
+    fn set_if_present(&mut self, key: K, value: V) -> Result<bool, String> {
+        match self.0.borrow_mut().get_mut(&key) {
+            Some(old) => {
+                *old = value;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+// std HashMap is a swiss-table, every bucket holds an inline (K, V) slot
+// and one control byte, and the number of buckets is a power of two
+// sized for a load factor of 7/8.
+fn log_memory<K, V>(map: &HashMap<K, V>)
+where
+    K: Eq + Hash + RandomKV,
+    V: RandomKV,
+{
+    let buckets = ((map.capacity() * 8) / 7).next_power_of_two();
+    let table = buckets * (mem::size_of::<(K, V)>() + 1);
+    let heap = map.iter().map(|(k, v)| k.heap_size() + v.heap_size());
+    utils::log_memory("hash-map", map.len(), table, heap.sum());
+}
@@ -0,0 +1,275 @@
+use im::OrdMap;
+use log::{debug, info};
+
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt, mem,
+    ops::Bound,
+    sync::{Arc, Mutex},
+};
+
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit, WriterAdapter};
+use crate::generator::RandomKV;
+use crate::utils;
+use crate::Profile;
+
+#[derive(Default, Clone)]
+pub struct ImOrdMapOpt {
+    pub readers: usize,
+    pub writers: usize,
+}
+
+impl TryFrom<toml::Value> for ImOrdMapOpt {
+    type Error = String;
+
+    fn try_from(value: toml::Value) -> Result<Self, Self::Error> {
+        let mut opt: ImOrdMapOpt = Default::default();
+
+        let section = match &value.get("im-ordmap") {
+            None => return Err("not found".to_string()),
+            Some(section) => section.clone(),
+        };
+        for (name, value) in section.as_table().unwrap().iter() {
+            match name.as_str() {
+                "readers" => {
+                    let v = value.as_integer().unwrap();
+                    opt.readers = v.try_into().unwrap();
+                }
+                "writers" => {
+                    let v = value.as_integer().unwrap();
+                    opt.writers = v.try_into().unwrap();
+                }
+                _ => panic!("invalid profile parameter {}", name),
+            }
+        }
+        Ok(opt)
+    }
+}
+
+#[cfg(feature = "all_types")]
+pub fn perf(name: &str, p: Profile) -> Result<(), String> {
+    match (p.key_type.as_str(), p.val_type.as_str()) {
+        ("i32", "i32") => do_perf::<i32, i32>(name, p),
+        ("i32", "i64") => do_perf::<i32, i64>(name, p),
+        ("i32", "array") => do_perf::<i32, [u8; 20]>(name, p),
+        ("i32", "bytes") => do_perf::<i32, Vec<u8>>(name, p),
+        ("i64", "i64") => do_perf::<i64, i64>(name, p),
+        ("i64", "array") => do_perf::<i64, [u8; 20]>(name, p),
+        ("i64", "bytes") => do_perf::<i64, Vec<u8>>(name, p),
+        ("array", "array") => do_perf::<[u8; 20], [u8; 20]>(name, p),
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(name, p),
+        ("bytes", "bytes") => do_perf::<Vec<u8>, Vec<u8>>(name, p),
+        _ => Err(format!(
+            "unsupported key/value types {}/{}",
+            p.key_type, p.val_type
+        )),
+    }
+}
+#[cfg(not(feature = "all_types"))]
+pub fn perf(name: &str, p: Profile) -> Result<(), String> {
+    match (p.key_type.as_str(), p.val_type.as_str()) {
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(name, p),
+        _ => Err(format!(
+            "unsupported key/value types {}/{}",
+            p.key_type, p.val_type
+        )),
+    }
+}
+
+fn do_perf<K, V>(_name: &str, p: Profile) -> Result<(), String>
+where
+    K: 'static + Clone + Default + Send + Sync + Ord + fmt::Debug + RandomKV,
+    V: 'static + Clone + Default + Send + Sync + PartialEq + fmt::Debug + RandomKV,
+{
+    debug!(
+        target: "ixperf",
+        "intial load for type <{},{}>", p.key_type, p.val_type
+    );
+
+    let mut index = ImOrdMapIndex(Arc::new(Mutex::new(OrdMap::new())));
+    let (readers, writers) = (p.im_ordmap.readers, p.im_ordmap.writers);
+    adapter::do_concur_perf(&mut index, 1, readers, writers, &p)?;
+
+    info!(
+        target: "ixperf",
+        "im-ordmap final map.len:{}", index.0.lock().unwrap().len()
+    );
+    log_memory(&index.0.lock().unwrap());
+    Ok(())
+}
+
+// im OrdMap is a B-tree, nodes hold up to 64 (K, V) entries inline and
+// 65 child links, each chunk with its bounds, and are reference counted.
+// Nodes are assumed to be 2/3 full.
+fn log_memory<K, V>(map: &OrdMap<K, V>)
+where
+    K: Clone + Ord + RandomKV,
+    V: Clone + RandomKV,
+{
+    let word = mem::size_of::<usize>();
+    let node = (64 * mem::size_of::<(K, V)>()) + (65 * word) + (6 * word);
+    let nodes = (map.len() + 41) / 42;
+    let heap = map.iter().map(|(k, v)| k.heap_size() + v.heap_size());
+    utils::log_memory("im-ordmap", map.len(), nodes * node, heap.sum());
+}
+
+// Writers mutate the shared map under the lock, readers take a
+// structural-sharing snapshot of the map, which is O(1), and read from
+// the snapshot without holding the lock.
+struct ImOrdMapIndex<K, V>(Arc<Mutex<OrdMap<K, V>>>);
+
+impl<K, V> ImOrdMapIndex<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    fn to_snapshot(&self) -> OrdMap<K, V> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl<K, V> IndexAdapter<K, V> for ImOrdMapIndex<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    type R = ImOrdMapIndex<K, V>;
+    type W = ImOrdMapIndex<K, V>;
+
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(ImOrdMapIndex(Arc::clone(&self.0)))
+    }
+
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        Ok(ImOrdMapIndex(Arc::clone(&self.0)))
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        Some(self.0.lock().unwrap().len())
+    }
+}
+
+impl<K, V> ReaderAdapter<K, V> for ImOrdMapIndex<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    fn get(&mut self, key: &K, mut visit: Visit<K, V>) -> Result<bool, String> {
+        let snap = self.to_snapshot();
+        match snap.get(key) {
+            Some(value) => {
+                adapter::do_visit(&mut visit, key, value);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn range(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let snap = self.to_snapshot();
+        let mut n = 0;
+        for (key, value) in snap.range((low, high)) {
+            adapter::do_visit(&mut visit, key, value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn reverse(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let snap = self.to_snapshot();
+        let mut n = 0;
+        for (key, value) in snap.range((low, high)).rev() {
+            adapter::do_visit(&mut visit, key, value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn scan(&mut self, start: &K, limit: usize, mut visit: Visit<K, V>) -> Result<usize, String> {
+        let snap = self.to_snapshot();
+        let mut n = 0;
+        let iter = snap.range((Bound::Included(start.clone()), Bound::Unbounded));
+        for (key, value) in iter.take(limit) {
+            adapter::do_visit(&mut visit, key, value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn reverse_scan(
+        &mut self,
+        start: &K,
+        limit: usize,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let snap = self.to_snapshot();
+        let mut n = 0;
+        let iter = snap.range((Bound::Unbounded, Bound::Included(start.clone())));
+        for (key, value) in iter.rev().take(limit) {
+            adapter::do_visit(&mut visit, key, value);
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+impl<K, V> WriterAdapter<K, V> for ImOrdMapIndex<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    fn set(&mut self, key: K, value: V) -> Result<bool, String> {
+        let mut map = self.0.lock().unwrap();
+        Ok(map.insert(key, value).is_some())
+    }
+
+    fn delete(&mut self, key: &K) -> Result<bool, String> {
+        let mut map = self.0.lock().unwrap();
+        Ok(map.remove(key).is_some())
+    }
+
+    fn read_modify_write(
+        &mut self,
+        key: K,
+        modify: &mut dyn FnMut(Option<&V>) -> V,
+    ) -> Result<bool, String> {
+        let mut map = self.0.lock().unwrap();
+        let (found, value) = match map.get(&key) {
+            Some(old) => (true, modify(Some(old))),
+            None => (false, modify(None)),
+        };
+        map.insert(key, value);
+        Ok(found)
+    }
+
+    fn set_if_absent(&mut self, key: K, value: V) -> Result<bool, String> {
+        let mut map = self.0.lock().unwrap();
+        if map.contains_key(&key) {
+            Ok(false)
+        } else {
+            map.insert(key, value);
+            Ok(true)
+        }
+    }
+
+    fn set_if_present(&mut self, key: K, value: V) -> Result<bool, String> {
+        let mut map = self.0.lock().unwrap();
+        match map.get_mut(&key) {
+            Some(old) => {
+                *old = value;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
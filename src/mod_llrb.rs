@@ -1,26 +1,27 @@
-use std::time::{Duration, SystemTime};
+use std::{cell::RefCell, fmt, ops::Bound, rc::Rc};
 
 use llrb_index::Llrb;
 use log::{debug, info};
 
-use crate::generator::{Cmd, IncrementalLoad, InitialLoad, RandomKV};
-use crate::stats;
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit, WriterAdapter};
+use crate::generator::RandomKV;
+use crate::utils;
 use crate::Profile;
 
 #[cfg(feature = "all_types")]
 pub fn perf(name: &str, p: Profile) -> Result<(), String> {
     match (p.key_type.as_str(), p.val_type.as_str()) {
-        ("i32", "i32") => Ok(do_perf::<i32, i32>(name, p)),
-        ("i32", "i64") => Ok(do_perf::<i32, i64>(name, p)),
-        ("i32", "array") => Ok(do_perf::<i32, [u8; 20]>(name, p)),
-        ("i32", "bytes") => Ok(do_perf::<i32, Vec<u8>>(name, p)),
-        ("i64", "i64") => Ok(do_perf::<i64, i64>(name, p)),
-        ("i64", "array") => Ok(do_perf::<i64, [u8; 20]>(name, p)),
-        ("i64", "bytes") => Ok(do_perf::<i64, Vec<u8>>(name, p)),
-        ("array", "array") => Ok(do_perf::<[u8; 20], [u8; 20]>(name, p)),
-        ("array", "bytes") => Ok(do_perf::<[u8; 20], Vec<u8>>(name, p)),
-        ("bytes", "bytes") => Ok(do_perf::<Vec<u8>, Vec<u8>>(name, p)),
-        ("bytes", "i64") => Ok(do_perf::<Vec<u8>, i64>(name, p)),
+        ("i32", "i32") => do_perf::<i32, i32>(name, p),
+        ("i32", "i64") => do_perf::<i32, i64>(name, p),
+        ("i32", "array") => do_perf::<i32, [u8; 20]>(name, p),
+        ("i32", "bytes") => do_perf::<i32, Vec<u8>>(name, p),
+        ("i64", "i64") => do_perf::<i64, i64>(name, p),
+        ("i64", "array") => do_perf::<i64, [u8; 20]>(name, p),
+        ("i64", "bytes") => do_perf::<i64, Vec<u8>>(name, p),
+        ("array", "array") => do_perf::<[u8; 20], [u8; 20]>(name, p),
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(name, p),
+        ("bytes", "bytes") => do_perf::<Vec<u8>, Vec<u8>>(name, p),
+        ("bytes", "i64") => do_perf::<Vec<u8>, i64>(name, p),
         _ => Err(format!(
             "unsupported key/value types {}/{}",
             p.key_type, p.val_type
@@ -30,7 +31,7 @@ pub fn perf(name: &str, p: Profile) -> Result<(), String> {
 #[cfg(not(feature = "all_types"))]
 pub fn perf(name: &str, p: Profile) -> Result<(), String> {
     match (p.key_type.as_str(), p.val_type.as_str()) {
-        ("array", "bytes") => Ok(do_perf::<[u8; 20], Vec<u8>>(name, p)),
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(name, p),
         _ => Err(format!(
             "unsupported key/value types {}/{}",
             p.key_type, p.val_type
@@ -38,12 +39,12 @@ pub fn perf(name: &str, p: Profile) -> Result<(), String> {
     }
 }
 
-fn do_perf<K, V>(name: &str, p: Profile)
+fn do_perf<K, V>(name: &str, p: Profile) -> Result<(), String>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + RandomKV,
-    V: 'static + Clone + Default + Send + Sync + RandomKV,
+    K: 'static + Clone + Default + Send + Sync + Ord + fmt::Debug + RandomKV,
+    V: 'static + Clone + Default + Send + Sync + PartialEq + fmt::Debug + RandomKV,
 {
-    let mut index: Llrb<K, V> = Llrb::new(name);
+    let index: Llrb<K, V> = Llrb::new(name);
     debug!(
         target: "ixperf",
         "node overhead for llrb: {}", index.stats().node_size()
@@ -53,141 +54,196 @@ where
         "intial load for type <{},{}>", p.key_type, p.val_type
     );
 
-    do_initial_load(&mut index, &p);
-
-    let (iter_elapsed, iter_count) = if p.g.iters {
-        let start = SystemTime::now();
-        let count = index.iter().map(|_| 1).collect::<Vec<u8>>().len();
-        assert_eq!(count, index.len());
-        (
-            Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64),
-            count,
-        )
-    } else {
-        (Default::default(), Default::default())
+    let mut index = LlrbIndex(Rc::new(RefCell::new(index)));
+    adapter::do_perf(&mut index, &p)?;
+
+    let index = match Rc::try_unwrap(index.0) {
+        Ok(index) => index.into_inner(),
+        Err(_) => unreachable!(),
     };
+    log_memory(&index);
+    validate(index, p);
+    Ok(())
+}
+
+/// llrb-index is a single threaded index, all readers and writers
+/// share the same tree.
+struct LlrbIndex<K, V>(Rc<RefCell<Llrb<K, V>>>);
 
-    do_incremental(&mut index, &p);
+impl<K, V> IndexAdapter<K, V> for LlrbIndex<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    type R = LlrbIndex<K, V>;
+    type W = LlrbIndex<K, V>;
 
-    if p.g.iters {
-        info!(
-            target: "ixperf",
-            "took {:?} to iter over {} items", iter_elapsed, iter_count
-        );
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(LlrbIndex(Rc::clone(&self.0)))
     }
 
-    validate(index, p);
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        Ok(LlrbIndex(Rc::clone(&self.0)))
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        Some(self.0.borrow().len())
+    }
 }
 
-fn do_initial_load<K, V>(index: &mut Llrb<K, V>, p: &Profile)
+impl<K, V> ReaderAdapter<K, V> for LlrbIndex<K, V>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + RandomKV,
-    V: 'static + Clone + Default + Send + Sync + RandomKV,
+    K: Clone + Ord,
+    V: Clone,
 {
-    let load_ops = p.g.loads;
-    if load_ops == 0 {
-        return;
-    }
-
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = InitialLoad::<K, V>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Load { key, value } => {
-                    lstats.load.sample_start(false);
-                    let items = index.set(key, value).map_or(0, |_| 1);
-                    lstats.load.sample_end(items);
-                }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(&p.cmd_opts, "ixperf", "initial periodic-stats\n{}", lstats);
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
+    fn get(&mut self, key: &K, mut visit: Visit<K, V>) -> Result<bool, String> {
+        match self.0.borrow().get(key) {
+            Some(value) => {
+                adapter::do_visit(&mut visit, key, &value);
+                Ok(true)
             }
+            None => Ok(false),
         }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+    }
 
-    stats!(&p.cmd_opts, "ixperf", "initial stats\n{:?}", fstats);
-    info!(
-        target: "ixperf",
-        "initial-load load:{} index.len:{} elapsed:{:?}",
-        load_ops, index.len(), elapsed
-    );
+    fn multi_get(&mut self, keys: &[K], mut visit: Visit<K, V>) -> Result<usize, String> {
+        let index = self.0.borrow();
+        let mut n = 0;
+        for key in keys.iter() {
+            if let Some(value) = index.get(key) {
+                adapter::do_visit(&mut visit, key, &value);
+                n += 1;
+            }
+        }
+        Ok(n)
+    }
+
+    fn range(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let index = self.0.borrow();
+        let mut n = 0;
+        for (key, value) in index.range((low, high)) {
+            adapter::do_visit(&mut visit, &key, &value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn reverse(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let index = self.0.borrow();
+        let mut n = 0;
+        for (key, value) in index.reverse((low, high)) {
+            adapter::do_visit(&mut visit, &key, &value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn scan(&mut self, start: &K, limit: usize, mut visit: Visit<K, V>) -> Result<usize, String> {
+        let index = self.0.borrow();
+        let mut n = 0;
+        let iter = index.range((Bound::Included(start.clone()), Bound::Unbounded));
+        for (key, value) in iter.take(limit) {
+            adapter::do_visit(&mut visit, &key, &value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn reverse_scan(
+        &mut self,
+        start: &K,
+        limit: usize,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let index = self.0.borrow();
+        let mut n = 0;
+        let iter = index.reverse((Bound::Unbounded, Bound::Included(start.clone())));
+        for (key, value) in iter.take(limit) {
+            adapter::do_visit(&mut visit, &key, &value);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn iter(&mut self, mut visit: Visit<K, V>) -> Result<usize, String> {
+        let index = self.0.borrow();
+        let mut n = 0;
+        for (key, value) in index.iter() {
+            adapter::do_visit(&mut visit, &key, &value);
+            n += 1;
+        }
+        assert_eq!(n, index.len());
+        Ok(n)
+    }
 }
 
-fn do_incremental<K, V>(index: &mut Llrb<K, V>, p: &Profile)
+impl<K, V> WriterAdapter<K, V> for LlrbIndex<K, V>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + RandomKV,
-    V: 'static + Clone + Default + Send + Sync + RandomKV,
+    K: Clone + Ord,
+    V: Clone,
 {
-    let rw_ops = p.g.read_ops() + p.g.write_ops();
-    if rw_ops == 0 {
-        return;
-    }
-
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = IncrementalLoad::<K, V>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Set { key, value } => {
-                    lstats.set.sample_start(false);
-                    let n = index.set(key, value.clone()).map_or(0, |_| 1);
-                    lstats.set.sample_end(n);
-                }
-                Cmd::Delete { key } => {
-                    lstats.delete.sample_start(false);
-                    let items = index.delete(&key).map_or(1, |_| 0);
-                    lstats.delete.sample_end(items);
-                }
-                Cmd::Get { key } => {
-                    lstats.get.sample_start(false);
-                    let items = index.get(&key).map_or(1, |_| 0);
-                    lstats.get.sample_end(items);
-                }
-                Cmd::Range { low, high } => {
-                    let iter = index.range((low, high));
-                    lstats.range.sample_start(true);
-                    lstats.range.sample_end(iter.fold(0, |acc, _| acc + 1));
-                }
-                Cmd::Reverse { low, high } => {
-                    let iter = index.reverse((low, high));
-                    lstats.reverse.sample_start(true);
-                    lstats.reverse.sample_end(iter.fold(0, |acc, _| acc + 1));
-                }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    &p.cmd_opts,
-                    "ixperf",
-                    "incremental periodic-stats\n{}",
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
+    fn set(&mut self, key: K, value: V) -> Result<bool, String> {
+        Ok(self.0.borrow_mut().set(key, value).is_some())
+    }
+
+    fn delete(&mut self, key: &K) -> Result<bool, String> {
+        Ok(self.0.borrow_mut().delete(key).is_some())
+    }
+
+    fn read_modify_write(
+        &mut self,
+        key: K,
+        modify: &mut dyn FnMut(Option<&V>) -> V,
+    ) -> Result<bool, String> {
+        let mut index = self.0.borrow_mut();
+        let old = index.get(&key);
+        index.set(key, modify(old.as_ref()));
+        Ok(old.is_some())
+    }
+
+    fn set_if_absent(&mut self, key: K, value: V) -> Result<bool, String> {
+        let mut index = self.0.borrow_mut();
+        match index.get(&key) {
+            Some(_) => Ok(false),
+            None => {
+                index.set(key, value);
+                Ok(true)
             }
         }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+    }
 
-    stats!(&p.cmd_opts, "ixperf", "incremental stats\n{:?}", fstats);
-    info!(
-        target: "ixperf",
-        "incremental-load r_ops:{} w_ops:{} map.len:{} elapsed:{:?}",
-        p.g.read_ops(), p.g.write_ops(), index.len(), elapsed
-    );
+    fn set_if_present(&mut self, key: K, value: V) -> Result<bool, String> {
+        let mut index = self.0.borrow_mut();
+        match index.get(&key) {
+            Some(_) => {
+                index.set(key, value);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+// every entry is a node, holding key and value inline, and links to
+// its children.
+fn log_memory<K, V>(index: &Llrb<K, V>)
+where
+    K: Clone + Ord + RandomKV,
+    V: Clone + RandomKV,
+{
+    let nodes = index.len() * index.stats().node_size();
+    let heap = index.iter().map(|(k, v)| k.heap_size() + v.heap_size());
+    utils::log_memory("llrb", index.len(), nodes, heap.sum());
 }
 
 fn validate<K, V>(index: Llrb<K, V>, _p: Profile)
@@ -1,19 +1,17 @@
 use lmdb::{self, Cursor, Transaction};
-use log::{debug, info};
+use lmdb_sys::{MDB_LAST, MDB_PREV, MDB_SET_RANGE};
+use log::info;
 
 use std::{
+    cmp,
     convert::{TryFrom, TryInto},
     ffi, io,
     ops::Bound,
     path,
     sync::Arc,
-    thread,
-    time::{Duration, SystemTime},
 };
 
-use crate::generator::InitialLoad;
-use crate::generator::{Cmd, IncrementalLoad, IncrementalRead, IncrementalWrite};
-use crate::stats;
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit, WriterAdapter};
 use crate::Profile;
 
 #[derive(Default, Clone)]
@@ -25,12 +23,6 @@ pub struct LmdbOpt {
     pub load_batch: usize,
 }
 
-impl LmdbOpt {
-    fn concur_threads(&self) -> usize {
-        self.readers + self.writers
-    }
-}
-
 impl TryFrom<toml::Value> for LmdbOpt {
     type Error = String;
 
@@ -77,377 +69,408 @@ impl TryFrom<toml::Value> for LmdbOpt {
 
 pub fn perf(p: Profile) -> Result<(), String> {
     info!(target: "ixperf", "for type <{},{}>", p.key_type, p.val_type);
-
-    {
-        let (env, db) = init_lmdb(&p, "lmdb");
-        do_initial(&p, env, db);
+    // lmdb is benchmarked only with byte-string keys and values, reject
+    // the rest, instead of silently running with bytes.
+    match (p.key_type.as_str(), p.val_type.as_str()) {
+        ("bytes", "bytes") => (),
+        _ => {
+            return Err(format!(
+                "unsupported key/value types {}/{}",
+                p.key_type, p.val_type
+            ))
+        }
     }
 
-    let (iter_elapsed, iter_count) = if p.g.iters {
-        let (env, db) = open_lmdb(&p, "lmdb");
-        let start = SystemTime::now();
-        let txn = env.begin_ro_txn().unwrap();
-        let iter = txn.open_ro_cursor(db).unwrap().iter();
-        let count = iter.map(|_| true).collect::<Vec<bool>>().len();
-        (
-            Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64),
-            count,
-        )
-    } else {
-        (Default::default(), Default::default())
-    };
+    let mut index = LmdbIndex::new(&p, "lmdb");
+    let (readers, writers) = (p.lmdb.readers, p.lmdb.writers);
+    let res = adapter::do_concur_perf(&mut index, 1, readers, writers, &p);
+    // sync to disk even if the run failed, or is stopped, but report the
+    // run error ahead of close error.
+    let close_res = index.close();
 
-    let total_ops = p.g.read_ops() + p.g.write_ops();
-    let (mut env, db) = if p.lmdb.concur_threads() == 0 && total_ops > 0 {
-        let (env, db) = open_lmdb(&p, "lmdb");
-        do_incremental(&p, env, db);
-        let (env, db) = open_lmdb(&p, "lmdb");
-        (Arc::new(env), db)
-    } else if total_ops > 0 {
-        let (env, db) = open_lmdb(&p, "lmdb");
-        let env = Arc::new(env);
-
-        let mut w_threads = vec![];
-        for i in 0..p.lmdb.writers {
-            let pp = p.clone();
-            let envv = Arc::clone(&env);
-            w_threads.push(thread::spawn(move || do_write(i, pp, envv, db)));
-        }
-        let mut r_threads = vec![];
-        for i in 0..p.lmdb.readers {
-            let pp = p.clone();
-            let envv = Arc::clone(&env);
-            r_threads.push(thread::spawn(move || do_read(i, pp, envv, db)));
-        }
-        {
-            let mut fstats = stats::Ops::new();
-            for t in w_threads {
-                fstats.merge(&t.join().unwrap());
-            }
-            stats!(&p.cmd_opts, "ixperf", "all-writers stats\n{:?}", fstats);
+    res?;
+    close_res
+}
+
+struct LmdbIndex {
+    env: Arc<lmdb::Environment>,
+    db: lmdb::Database,
+    load_batch: usize,
+}
+
+impl LmdbIndex {
+    fn new(p: &Profile, name: &str) -> LmdbIndex {
+        let (env, db) = init_lmdb(p, name);
+        LmdbIndex {
+            env: Arc::new(env),
+            db,
+            load_batch: cmp::max(p.lmdb.load_batch, 1),
         }
-        {
-            let mut fstats = stats::Ops::new();
-            for t in r_threads {
-                fstats.merge(&t.join().unwrap());
-            }
-            stats!(&p.cmd_opts, "ixperf", "all-readers stats\n{:?}", fstats);
+    }
+
+    // sync to disk and close the db, fail if readers or writers are still
+    // holding on to the environment.
+    fn close(mut self) -> Result<(), String> {
+        self.env.sync(true).map_err(to_error)?;
+        match Arc::get_mut(&mut self.env) {
+            Some(env) => unsafe { env.close_db(self.db) },
+            None => return Err("lmdb env still shared, db not closed".to_string()),
         }
-        (env, db)
-    } else {
-        let (env, db) = open_lmdb(&p, "lmdb");
-        (Arc::new(env), db)
-    };
+        Ok(())
+    }
+}
 
-    unsafe { Arc::get_mut(&mut env).unwrap().close_db(db) };
-    env.sync(true).unwrap();
+impl IndexAdapter<Vec<u8>, Vec<u8>> for LmdbIndex {
+    type R = LmdbReader;
+    type W = LmdbWriter;
 
-    if p.g.iters {
-        info!(
-            target: "ixperf",
-            "took {:?} to iter over {} items", iter_elapsed, iter_count
-        );
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(LmdbReader {
+            env: Arc::clone(&self.env),
+            db: self.db,
+        })
     }
 
-    Ok(())
-}
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        Ok(LmdbWriter {
+            batch: vec![],
+            n_loads: 0,
+            load_batch: self.load_batch,
+            env: Arc::clone(&self.env),
+            db: self.db,
+        })
+    }
 
-fn do_initial(
-    p: &Profile,
-    mut env: lmdb::Environment,
-    db: lmdb::Database, // index
-) -> stats::Ops {
-    if p.g.loads == 0 {
-        return stats::Ops::new();
+    fn len(&mut self) -> Option<usize> {
+        self.env.stat().ok().map(|stat| stat.entries())
     }
+}
 
-    let mut txn = env.begin_rw_txn().unwrap();
-    let write_flags: lmdb::WriteFlags = Default::default();
-    let mut load_count = 0;
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = InitialLoad::<Vec<u8>, Vec<u8>>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Load { key, value } => {
-                    lstats.load.sample_start(false);
-                    txn.put(db, &key, &value, write_flags.clone()).unwrap();
-                    lstats.load.sample_end(0);
-                    load_count += 1;
-                }
-                _ => unreachable!(),
-            };
-            if (load_count % p.lmdb.load_batch) == 0 {
-                txn.commit().unwrap();
-                txn = env.begin_rw_txn().unwrap();
+struct LmdbWriter {
+    // batched initial-load, written in a single transaction, and number
+    // of entries loaded since the last sync.
+    batch: Vec<(Vec<u8>, Vec<u8>)>,
+    n_loads: usize,
+    load_batch: usize,
+    env: Arc<lmdb::Environment>,
+    db: lmdb::Database,
+}
+
+impl LmdbWriter {
+    // write the batched initial-load in a single transaction. A failed
+    // batch is kept, and retried by the next flush.
+    fn flush(&mut self) -> Result<(), String> {
+        if self.batch.len() == 0 {
+            return Ok(());
+        }
+        match self.write_batch() {
+            Ok(_) => {
+                self.batch.clear();
+                Ok(())
             }
-            if lstats.is_sec_elapsed() {
-                stats!(&p.cmd_opts, "ixperf", "initial periodic-stats\n{}", lstats);
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
+            Err(err) => {
+                let n = self.batch.len();
+                Err(format!("{}, {} loads not written", to_error(err), n))
             }
         }
+    }
 
-        txn.commit().unwrap();
-        fstats.merge(&lstats);
-        unsafe { env.close_db(db) };
-        env.sync(true).unwrap();
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
-
-    let stat = {
-        let (env, _) = open_lmdb(&p, "lmdb");
-        env.stat().unwrap()
-    };
-    stats!(&p.cmd_opts, "ixperf", "initial stats\n{:?}", fstats);
-    info!(
-        target: "ixperf",
-        "initial-load load:{} index.len:{} elapsed:{:?}",
-        p.g.loads, stat.entries(), elapsed
-    );
-
-    fstats
+    fn write_batch(&self) -> Result<(), lmdb::Error> {
+        let mut txn = self.env.begin_rw_txn()?;
+        for (key, value) in self.batch.iter() {
+            txn.put(self.db, key, value, lmdb::WriteFlags::empty())?;
+        }
+        txn.commit()
+    }
 }
 
-fn do_incremental(
-    p: &Profile,
-    env: lmdb::Environment,
-    db: lmdb::Database, // lmdb index
-) -> stats::Ops {
-    if (p.g.read_ops() + p.g.write_ops()) == 0 {
-        return stats::Ops::new();
+impl WriterAdapter<Vec<u8>, Vec<u8>> for LmdbWriter {
+    // lmdb does not tell whether the key was updated, try to insert and
+    // overwrite if the key exists, within the same write transaction.
+    fn set(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<bool, String> {
+        self.commit()?;
+
+        let mut txn = self.env.begin_rw_txn().map_err(to_error)?;
+        let flags = lmdb::WriteFlags::NO_OVERWRITE;
+        let updated = match txn.put(self.db, &key, &value, flags) {
+            Ok(_) => false,
+            Err(lmdb::Error::KeyExist) => {
+                txn.put(self.db, &key, &value, lmdb::WriteFlags::empty())
+                    .map_err(to_error)?;
+                true
+            }
+            Err(err) => return Err(to_error(err)),
+        };
+        txn.commit().map_err(to_error)?;
+        Ok(updated)
     }
 
-    let write_flags: lmdb::WriteFlags = Default::default();
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-        let mut lstats = stats::Ops::new();
-        let gen = IncrementalLoad::<Vec<u8>, Vec<u8>>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Set { key, value } => {
-                    lstats.set.sample_start(false);
-                    let mut txn = env.begin_rw_txn().unwrap();
-                    txn.put(db, &key, &value, write_flags.clone()).unwrap();
-                    txn.commit().unwrap();
-                    lstats.set.sample_end(0);
-                }
-                Cmd::Delete { key } => {
-                    lstats.delete.sample_start(false);
-                    let mut txn = env.begin_rw_txn().unwrap();
-                    let n = match txn.del(db, &key, None /*data*/) {
-                        Ok(_) => 0,
-                        Err(lmdb::Error::NotFound) => 1,
-                        res @ _ => panic!("lmdb del: {:?}", res),
-                    };
-                    txn.commit().unwrap();
-                    lstats.delete.sample_end(n);
-                }
-                Cmd::Get { key } => {
-                    lstats.get.sample_start(false);
-                    let txn = env.begin_ro_txn().unwrap();
-                    let n = match txn.get(db, &key) {
-                        Ok(_) => 0,
-                        Err(lmdb::Error::NotFound) => 1,
-                        Err(err) => panic!(err),
-                    };
-                    lstats.get.sample_end(n);
-                }
-                Cmd::Range { low, high } => {
-                    let txn = env.begin_ro_txn().unwrap();
-                    let mut cur = txn.open_ro_cursor(db).unwrap();
-                    let iter = match low {
-                        Bound::Included(low) => cur.iter_from(low.clone()),
-                        Bound::Excluded(low) => cur.iter_from(low.clone()),
-                        _ => cur.iter(),
-                    };
-
-                    let mut iter_count = 0;
-                    for (key, _) in iter {
-                        match high {
-                            Bound::Included(h) if key.gt(&h) => break,
-                            Bound::Excluded(h) if key.ge(&h) => break,
-                            _ => iter_count += 1,
-                        };
-                    }
+    fn delete(&mut self, key: &Vec<u8>) -> Result<bool, String> {
+        self.commit()?;
 
-                    lstats.range.sample_start(true);
-                    lstats.range.sample_end(iter_count);
-                }
-                Cmd::Reverse { .. } => (),
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    p.cmd_opts,
-                    "ixperf",
-                    "incremental periodic-stats\n{}",
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
-            }
+        let mut txn = self.env.begin_rw_txn().map_err(to_error)?;
+        let found = match txn.del(self.db, key, None /*data*/) {
+            Ok(_) => true,
+            Err(lmdb::Error::NotFound) => false,
+            Err(err) => return Err(to_error(err)),
+        };
+        txn.commit().map_err(to_error)?;
+        Ok(found)
+    }
+
+    // get and put happen within the same write transaction.
+    fn read_modify_write(
+        &mut self,
+        key: Vec<u8>,
+        modify: &mut dyn FnMut(Option<&Vec<u8>>) -> Vec<u8>,
+    ) -> Result<bool, String> {
+        self.commit()?;
+
+        let mut txn = self.env.begin_rw_txn().map_err(to_error)?;
+        let (found, value) = match txn.get(self.db, &key) {
+            Ok(old) => (true, modify(Some(&old.to_vec()))),
+            Err(lmdb::Error::NotFound) => (false, modify(None)),
+            Err(err) => return Err(to_error(err)),
+        };
+        txn.put(self.db, &key, &value, lmdb::WriteFlags::empty())
+            .map_err(to_error)?;
+        txn.commit().map_err(to_error)?;
+        Ok(found)
+    }
+
+    fn set_if_absent(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<bool, String> {
+        self.commit()?;
+
+        let mut txn = self.env.begin_rw_txn().map_err(to_error)?;
+        let flags = lmdb::WriteFlags::NO_OVERWRITE;
+        let ok = match txn.put(self.db, &key, &value, flags) {
+            Ok(_) => true,
+            Err(lmdb::Error::KeyExist) => false,
+            Err(err) => return Err(to_error(err)),
+        };
+        txn.commit().map_err(to_error)?;
+        Ok(ok)
+    }
+
+    fn set_if_present(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<bool, String> {
+        self.commit()?;
+
+        let mut txn = self.env.begin_rw_txn().map_err(to_error)?;
+        let ok = match txn.get(self.db, &key) {
+            Ok(_) => true,
+            Err(lmdb::Error::NotFound) => false,
+            Err(err) => return Err(to_error(err)),
+        };
+        if ok {
+            txn.put(self.db, &key, &value, lmdb::WriteFlags::empty())
+                .map_err(to_error)?;
         }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+        txn.commit().map_err(to_error)?;
+        Ok(ok)
+    }
 
-    let stat = {
-        let (env, _) = open_lmdb(&p, "lmdb");
-        env.stat().unwrap()
-    };
-    stats!(&p.cmd_opts, "ixperf", "incremental stats\n{:?}", fstats);
-    info!(
-        target: "ixperf",
-        "incremental-load r_ops:{} w_ops:{} index.len:{}, elapsed:{:?}",
-        p.g.read_ops(), p.g.write_ops(), stat.entries(), elapsed
-    );
-
-    fstats
+    // initial-load is batched, load_batch entries per transaction, the
+    // load that fills the batch accounts for the whole transaction. If
+    // the transaction fails, the error names the loads not yet written.
+    fn load(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<bool, String> {
+        self.batch.push((key, value));
+        self.n_loads += 1;
+        if self.batch.len() >= self.load_batch {
+            self.flush()?;
+        }
+        Ok(false)
+    }
+
+    // write the last batch, and sync the loaded entries to disk, env is
+    // opened with NO_SYNC.
+    fn commit(&mut self) -> Result<(), String> {
+        self.flush()?;
+        if self.n_loads > 0 {
+            self.n_loads = 0;
+            self.env.sync(true).map_err(to_error)?;
+        }
+        Ok(())
+    }
 }
 
-fn do_write(
-    i: usize,
-    p: Profile,
+struct LmdbReader {
     env: Arc<lmdb::Environment>,
-    db: lmdb::Database, // index
-) -> stats::Ops {
-    if p.g.write_ops() == 0 {
-        return stats::Ops::new();
-    }
+    db: lmdb::Database,
+}
 
-    let write_flags: lmdb::WriteFlags = Default::default();
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-        let mut lstats = stats::Ops::new();
-        let gen = IncrementalWrite::<Vec<u8>, Vec<u8>>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Set { key, value } => {
-                    lstats.set.sample_start(false);
-                    let mut txn = env.begin_rw_txn().unwrap();
-                    txn.put(db, &key, &value, write_flags.clone()).unwrap();
-                    txn.commit().unwrap();
-                    lstats.set.sample_end(0);
+impl ReaderAdapter<Vec<u8>, Vec<u8>> for LmdbReader {
+    fn get(&mut self, key: &Vec<u8>, mut visit: Visit<Vec<u8>, Vec<u8>>) -> Result<bool, String> {
+        let txn = self.env.begin_ro_txn().map_err(to_error)?;
+        match txn.get(self.db, key) {
+            Ok(value) => {
+                if visit.is_some() {
+                    adapter::do_visit(&mut visit, key, &value.to_vec());
                 }
-                Cmd::Delete { key } => {
-                    lstats.delete.sample_start(false);
-                    let mut txn = env.begin_rw_txn().unwrap();
-                    let n = match txn.del(db, &key, None /*data*/) {
-                        Ok(_) => 0,
-                        Err(lmdb::Error::NotFound) => 1,
-                        res @ _ => panic!("lmdb del: {:?}", res),
-                    };
-                    txn.commit().unwrap();
-                    lstats.delete.sample_end(n);
+                Ok(true)
+            }
+            Err(lmdb::Error::NotFound) => Ok(false),
+            Err(err) => Err(to_error(err)),
+        }
+    }
+
+    // all keys are looked up within the same read transaction.
+    fn multi_get(
+        &mut self,
+        keys: &[Vec<u8>],
+        mut visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        let txn = self.env.begin_ro_txn().map_err(to_error)?;
+        let mut n = 0;
+        for key in keys.iter() {
+            match txn.get(self.db, key) {
+                Ok(value) => {
+                    if visit.is_some() {
+                        adapter::do_visit(&mut visit, key, &value.to_vec());
+                    }
+                    n += 1;
                 }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    &p.cmd_opts,
-                    "ixperf",
-                    "writer-{} periodic-stats\n{}",
-                    i,
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
+                Err(lmdb::Error::NotFound) => (),
+                Err(err) => return Err(to_error(err)),
             }
         }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+        Ok(n)
+    }
 
-    stats!(&p.cmd_opts, "ixperf", "writer-{} stats\n{:?}", i, fstats);
-    info!(
-        target: "ixperf", "writer-{} w_ops:{} elapsed:{:?}",
-        i, p.g.write_ops(), elapsed
-    );
+    fn range(
+        &mut self,
+        low: Bound<Vec<u8>>,
+        high: Bound<Vec<u8>>,
+        visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        self.do_range(&low, &high, std::usize::MAX, visit)
+    }
 
-    fstats
+    fn reverse(
+        &mut self,
+        low: Bound<Vec<u8>>,
+        high: Bound<Vec<u8>>,
+        visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        self.do_reverse(&low, &high, std::usize::MAX, visit)
+    }
+
+    fn scan(
+        &mut self,
+        start: &Vec<u8>,
+        limit: usize,
+        visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        let low = Bound::Included(start.clone());
+        self.do_range(&low, &Bound::Unbounded, limit, visit)
+    }
+
+    fn reverse_scan(
+        &mut self,
+        start: &Vec<u8>,
+        limit: usize,
+        visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        let high = Bound::Included(start.clone());
+        self.do_reverse(&Bound::Unbounded, &high, limit, visit)
+    }
 }
 
-fn do_read(
-    i: usize,
-    p: Profile,
-    env: Arc<lmdb::Environment>,
-    db: lmdb::Database, // index handle
-) -> stats::Ops {
-    if p.g.read_ops() == 0 {
-        return stats::Ops::new();
+impl LmdbReader {
+    fn do_range(
+        &mut self,
+        low: &Bound<Vec<u8>>,
+        high: &Bound<Vec<u8>>,
+        limit: usize,
+        mut visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        let txn = self.env.begin_ro_txn().map_err(to_error)?;
+        let mut cur = txn.open_ro_cursor(self.db).map_err(to_error)?;
+        let iter = match low {
+            Bound::Included(l) | Bound::Excluded(l) => cur.iter_from(l),
+            Bound::Unbounded => cur.iter(),
+        };
+
+        let mut n = 0;
+        for (key, value) in iter {
+            match low {
+                Bound::Excluded(l) if key == l.as_slice() => continue,
+                _ => (),
+            }
+            match high {
+                Bound::Included(h) if key > h.as_slice() => break,
+                Bound::Excluded(h) if key >= h.as_slice() => break,
+                _ => (),
+            }
+            if n >= limit {
+                break;
+            }
+            if visit.is_some() {
+                adapter::do_visit(&mut visit, &key.to_vec(), &value.to_vec());
+            }
+            n += 1;
+        }
+        Ok(n)
     }
 
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = IncrementalRead::<Vec<u8>, Vec<u8>>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Get { key } => {
-                    lstats.get.sample_start(false);
-                    let txn = env.begin_ro_txn().unwrap();
-                    let n = match txn.get(db, &key) {
-                        Ok(_) => 0,
-                        Err(lmdb::Error::NotFound) => 1,
-                        Err(err) => panic!(err),
-                    };
-                    lstats.get.sample_end(n);
+    fn do_reverse(
+        &mut self,
+        low: &Bound<Vec<u8>>,
+        high: &Bound<Vec<u8>>,
+        limit: usize,
+        mut visit: Visit<Vec<u8>, Vec<u8>>,
+    ) -> Result<usize, String> {
+        let txn = self.env.begin_ro_txn().map_err(to_error)?;
+        let cur = txn.open_ro_cursor(self.db).map_err(to_error)?;
+
+        // position the cursor on the last entry within high.
+        let mut item = match high {
+            Bound::Unbounded => cursor_get(&cur, None, MDB_LAST)?,
+            Bound::Included(h) | Bound::Excluded(h) => {
+                match cursor_get(&cur, Some(h), MDB_SET_RANGE)? {
+                    None => cursor_get(&cur, None, MDB_LAST)?,
+                    Some((key, value)) => match high {
+                        Bound::Included(h) if key == h.as_slice() => Some((key, value)),
+                        _ => cursor_get(&cur, None, MDB_PREV)?,
+                    },
                 }
-                Cmd::Range { low, high } => {
-                    let txn = env.begin_ro_txn().unwrap();
-                    let mut cur = txn.open_ro_cursor(db).unwrap();
-                    let iter = match low {
-                        Bound::Included(low) => cur.iter_from(low.clone()),
-                        Bound::Excluded(low) => cur.iter_from(low.clone()),
-                        _ => cur.iter(),
-                    };
-
-                    let mut iter_count = 0;
-                    for (key, _) in iter {
-                        match high {
-                            Bound::Included(h) if key.gt(&h) => break,
-                            Bound::Excluded(h) if key.ge(&h) => break,
-                            _ => iter_count += 1,
-                        };
-                    }
+            }
+        };
 
-                    lstats.range.sample_start(true);
-                    lstats.range.sample_end(iter_count);
-                }
-                Cmd::Reverse { .. } => (),
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    &p.cmd_opts,
-                    "ixperf",
-                    "reader-{} periodic-stats\n{}",
-                    i,
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
+        let mut n = 0;
+        while let Some((key, value)) = item {
+            match low {
+                Bound::Included(l) if key < l.as_slice() => break,
+                Bound::Excluded(l) if key <= l.as_slice() => break,
+                _ => (),
+            }
+            if n >= limit {
+                break;
             }
+            if visit.is_some() {
+                adapter::do_visit(&mut visit, &key.to_vec(), &value.to_vec());
+            }
+            n += 1;
+            item = cursor_get(&cur, None, MDB_PREV)?;
         }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+        Ok(n)
+    }
+}
 
-    stats!(&p.cmd_opts, "ixperf", "reader-{} stats\n{:?}", i, fstats);
-    info!(
-        target: "ixperf", "reader-{} r_ops:{} elapsed:{:?}",
-        i, p.g.read_ops(), elapsed
-    );
+fn cursor_get<'txn>(
+    cur: &lmdb::RoCursor<'txn>,
+    key: Option<&[u8]>,
+    op: u32,
+) -> Result<Option<(&'txn [u8], &'txn [u8])>, String> {
+    match cur.get(key, None, op) {
+        Ok((Some(key), value)) => Ok(Some((key, value))),
+        Ok((None, _)) => Err(format!("lmdb cursor op:{} missing key", op)),
+        Err(lmdb::Error::NotFound) => Ok(None),
+        Err(err) => Err(to_error(err)),
+    }
+}
 
-    fstats
+fn to_error(err: lmdb::Error) -> String {
+    format!("lmdb: {}", err)
 }
 
 fn init_lmdb(p: &Profile, name: &str) -> (lmdb::Environment, lmdb::Database) {
@@ -460,24 +483,6 @@ fn init_lmdb(p: &Profile, name: &str) -> (lmdb::Environment, lmdb::Database) {
     let path = std::path::Path::new(&p.lmdb.dir).join(name);
     std::fs::create_dir_all(&path).unwrap();
 
-    // create the environment
-    let mut flags = lmdb::EnvironmentFlags::empty();
-    flags.insert(lmdb::EnvironmentFlags::NO_SYNC);
-    flags.insert(lmdb::EnvironmentFlags::NO_META_SYNC);
-    let env = lmdb::Environment::new()
-        .set_flags(flags)
-        .set_map_size(10_000_000_000)
-        .open(&path)
-        .unwrap();
-
-    let db = env.open_db(None).unwrap();
-
-    (env, db)
-}
-
-fn open_lmdb(p: &Profile, name: &str) -> (lmdb::Environment, lmdb::Database) {
-    let path = std::path::Path::new(&p.lmdb.dir).join(name);
-
     // create the environment
     let mut flags = lmdb::EnvironmentFlags::empty();
     flags.insert(lmdb::EnvironmentFlags::NO_SYNC);
@@ -0,0 +1,154 @@
+//! Null index, accepts all commands and does nothing, the index stays
+//! empty. Running a profile against the null index measures the harness
+//! overhead, generator, stats sampling and command dispatch, which can
+//! be subtracted from other runs, refer to `--calibrate`.
+
+use log::{debug, info};
+
+use std::{fmt, ops::Bound};
+
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit, WriterAdapter};
+use crate::generator::RandomKV;
+use crate::stats;
+use crate::Profile;
+
+pub fn perf(_name: &str, p: Profile) -> Result<(), String> {
+    run(&p)?;
+    Ok(())
+}
+
+/// Run profile `p` against the null index and return the harness
+/// overhead for every op, and for dispatching a command.
+pub fn calibrate(mut p: Profile) -> Result<stats::Overhead, String> {
+    // calibration run shall not be mistaken for the actual run.
+    p.cmd_opts.stats = false;
+    p.cmd_opts.verify = false;
+    p.diff = None;
+    p.live = None;
+    p.metrics = None;
+    p.amplify = None;
+    p.g.reset_inserts();
+
+    let ops = run(&p)?;
+    let overhead = stats::Overhead::new(&ops);
+    info!(target: "ixperf", "harness overhead, in nanoseconds\n{:?}", overhead);
+    Ok(overhead)
+}
+
+#[cfg(feature = "all_types")]
+fn run(p: &Profile) -> Result<stats::Ops, String> {
+    match (p.key_type.as_str(), p.val_type.as_str()) {
+        ("i32", "i32") => do_perf::<i32, i32>(p),
+        ("i32", "i64") => do_perf::<i32, i64>(p),
+        ("i32", "array") => do_perf::<i32, [u8; 20]>(p),
+        ("i32", "bytes") => do_perf::<i32, Vec<u8>>(p),
+        ("i64", "i64") => do_perf::<i64, i64>(p),
+        ("i64", "array") => do_perf::<i64, [u8; 20]>(p),
+        ("i64", "bytes") => do_perf::<i64, Vec<u8>>(p),
+        ("array", "array") => do_perf::<[u8; 20], [u8; 20]>(p),
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(p),
+        ("bytes", "bytes") => do_perf::<Vec<u8>, Vec<u8>>(p),
+        _ => Err(format!(
+            "unsupported key/value types {}/{}",
+            p.key_type, p.val_type
+        )),
+    }
+}
+#[cfg(not(feature = "all_types"))]
+fn run(p: &Profile) -> Result<stats::Ops, String> {
+    match (p.key_type.as_str(), p.val_type.as_str()) {
+        ("array", "bytes") => do_perf::<[u8; 20], Vec<u8>>(p),
+        _ => Err(format!(
+            "unsupported key/value types {}/{}",
+            p.key_type, p.val_type
+        )),
+    }
+}
+
+fn do_perf<K, V>(p: &Profile) -> Result<stats::Ops, String>
+where
+    K: 'static + Clone + Default + Send + Ord + fmt::Debug + RandomKV,
+    V: 'static + Clone + Default + Send + PartialEq + fmt::Debug + RandomKV,
+{
+    debug!(
+        target: "ixperf",
+        "null index for type <{},{}>", p.key_type, p.val_type
+    );
+
+    adapter::do_perf(&mut NullIndex, p)
+}
+
+struct NullIndex;
+
+impl<K, V> IndexAdapter<K, V> for NullIndex {
+    type R = NullIndex;
+    type W = NullIndex;
+
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(NullIndex)
+    }
+
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        Ok(NullIndex)
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        Some(0)
+    }
+}
+
+impl<K, V> ReaderAdapter<K, V> for NullIndex {
+    fn get(&mut self, _key: &K, _visit: Visit<K, V>) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn multi_get(&mut self, _keys: &[K], _visit: Visit<K, V>) -> Result<usize, String> {
+        Ok(0)
+    }
+
+    fn range(&mut self, _: Bound<K>, _: Bound<K>, _: Visit<K, V>) -> Result<usize, String> {
+        Ok(0)
+    }
+
+    fn reverse(&mut self, _: Bound<K>, _: Bound<K>, _: Visit<K, V>) -> Result<usize, String> {
+        Ok(0)
+    }
+
+    fn scan(&mut self, _start: &K, _limit: usize, _: Visit<K, V>) -> Result<usize, String> {
+        Ok(0)
+    }
+
+    fn reverse_scan(&mut self, _start: &K, _limit: usize, _: Visit<K, V>) -> Result<usize, String> {
+        Ok(0)
+    }
+
+    fn iter(&mut self, _visit: Visit<K, V>) -> Result<usize, String> {
+        Ok(0)
+    }
+}
+
+impl<K, V> WriterAdapter<K, V> for NullIndex {
+    fn set(&mut self, _key: K, _value: V) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn delete(&mut self, _key: &K) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn read_modify_write(
+        &mut self,
+        _key: K,
+        _modify: &mut dyn FnMut(Option<&V>) -> V,
+    ) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn set_if_absent(&mut self, _key: K, _value: V) -> Result<bool, String> {
+        Ok(true)
+    }
+
+    fn set_if_present(&mut self, _key: K, _value: V) -> Result<bool, String> {
+        Ok(false)
+    }
+}
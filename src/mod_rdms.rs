@@ -1,8 +1,7 @@
-use log::{debug, info};
-
+use log::{info, warn};
 use rdms::{
     self,
-    core::{Diff, Footprint, Index, Reader, Serialize, Writer},
+    core::{Diff, Entry, Footprint, Index, Reader, Serialize, Writer},
     croaring::CRoaring,
     nobitmap::NoBitmap,
 };
@@ -11,12 +10,12 @@ use std::{
     convert::{TryFrom, TryInto},
     fmt,
     hash::Hash,
-    thread,
-    time::{Duration, SystemTime},
+    ops::Bound,
 };
 
-use crate::generator::{Cmd, IncrementalLoad, IncrementalRead, IncrementalWrite};
-use crate::generator::{InitialLoad, RandomKV};
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit, WriterAdapter};
+use crate::differential::{DiffOpt, DiffReader, Differ};
+use crate::generator::RandomKV;
 use crate::mod_rdms_dgm as mod_dgm;
 use crate::mod_rdms_llrb as mod_llrb;
 use crate::mod_rdms_mvcc as mod_mvcc;
@@ -35,16 +34,6 @@ pub struct RdmsOpt {
     pub writers: usize,
 }
 
-impl RdmsOpt {
-    fn concur_threads(&self) -> usize {
-        self.readers + self.writers
-    }
-
-    fn initial_threads(&self) -> usize {
-        self.initial
-    }
-}
-
 impl TryFrom<toml::Value> for RdmsOpt {
     type Error = String;
 
@@ -82,16 +71,16 @@ impl TryFrom<toml::Value> for RdmsOpt {
 pub fn do_rdms_index(p: Profile) -> Result<(), String> {
     let name = p.rdms.name.clone();
     match (p.key_type.as_str(), p.val_type.as_str()) {
-        ("i32", "i32") => Ok(perf::<i32, i32>(&name, p)),
-        ("i32", "i64") => Ok(perf::<i32, i64>(&name, p)),
-        ("i32", "array") => Ok(perf::<i32, [u8; 20]>(&name, p)),
-        ("i32", "bytes") => Ok(perf::<i32, Vec<u8>>(&name, p)),
-        ("i64", "i64") => Ok(perf::<i64, i64>(&name, p)),
-        ("i64", "array") => Ok(perf::<i64, [u8; 20]>(&name, p)),
-        ("i64", "bytes") => Ok(perf::<i64, Vec<u8>>(&name, p)),
-        ("array", "array") => Ok(perf::<[u8; 20], [u8; 20]>(&name, p)),
-        ("array", "bytes") => Ok(perf::<[u8; 20], Vec<u8>>(&name, p)),
-        ("bytes", "bytes") => Ok(perf::<Vec<u8>, Vec<u8>>(&name, p)),
+        ("i32", "i32") => perf::<i32, i32>(&name, p),
+        ("i32", "i64") => perf::<i32, i64>(&name, p),
+        ("i32", "array") => perf::<i32, [u8; 20]>(&name, p),
+        ("i32", "bytes") => perf::<i32, Vec<u8>>(&name, p),
+        ("i64", "i64") => perf::<i64, i64>(&name, p),
+        ("i64", "array") => perf::<i64, [u8; 20]>(&name, p),
+        ("i64", "bytes") => perf::<i64, Vec<u8>>(&name, p),
+        ("array", "array") => perf::<[u8; 20], [u8; 20]>(&name, p),
+        ("array", "bytes") => perf::<[u8; 20], Vec<u8>>(&name, p),
+        ("bytes", "bytes") => perf::<Vec<u8>, Vec<u8>>(&name, p),
         _ => Err(format!(
             "unsupported key/value types {}/{}",
             p.key_type, p.val_type
@@ -102,7 +91,7 @@ pub fn do_rdms_index(p: Profile) -> Result<(), String> {
 pub fn do_rdms_index(p: Profile) -> Result<(), String> {
     let name = p.rdms.name.clone();
     match (p.key_type.as_str(), p.val_type.as_str()) {
-        ("array", "bytes") => Ok(perf::<[u8; 20], Vec<u8>>(&name, p)),
+        ("array", "bytes") => perf::<[u8; 20], Vec<u8>>(&name, p),
         _ => Err(format!(
             "unsupported key/value types {}/{}",
             p.key_type, p.val_type
@@ -110,7 +99,7 @@ pub fn do_rdms_index(p: Profile) -> Result<(), String> {
     }
 }
 
-fn perf<K, V>(name: &str, p: Profile)
+fn perf<K, V>(name: &str, p: Profile) -> Result<(), String>
 where
     K: 'static
         + Clone
@@ -123,7 +112,17 @@ where
         + fmt::Debug
         + RandomKV
         + Hash,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + Serialize + RandomKV,
+    V: 'static
+        + Clone
+        + Default
+        + Send
+        + Sync
+        + PartialEq
+        + fmt::Debug
+        + Diff
+        + Footprint
+        + Serialize
+        + RandomKV,
     <V as Diff>::D: Send + Default + Serialize,
 {
     match p.rdms.index.as_str() {
@@ -145,345 +144,286 @@ where
     }
 }
 
-pub(crate) fn do_perf<K, V, I>(index: &mut rdms::Rdms<K, V, I>, p: &Profile) -> stats::Ops
+pub(crate) fn do_perf<K, V, I>(
+    index: &mut rdms::Rdms<K, V, I>,
+    p: &Profile,
+) -> Result<stats::Ops, String>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + Footprint + RandomKV + Hash,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + RandomKV,
+    K: 'static + Clone + Default + Send + Sync + Ord + fmt::Debug + Footprint + RandomKV + Hash,
+    V: 'static
+        + Clone
+        + Default
+        + Send
+        + Sync
+        + PartialEq
+        + fmt::Debug
+        + Diff
+        + Footprint
+        + RandomKV,
     I: Index<K, V>,
     <I as Index<K, V>>::R: 'static + Send + Sync,
     <I as Index<K, V>>::W: 'static + Send + Sync,
 {
-    let mut fstats = do_initial_load(index, &p);
-
-    let (iter_elapsed, iter_count) = if p.g.iters {
-        let start = SystemTime::now();
-        let mut r = index.to_reader().unwrap();
-        let count = r.iter().unwrap().map(|_| true).collect::<Vec<bool>>().len();
-        (
-            Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64),
-            count,
-        )
-    } else {
-        (Default::default(), Default::default())
-    };
-
-    let total_ops = p.g.read_ops() + p.g.write_ops();
-    if p.rdms.concur_threads() == 0 && total_ops > 0 {
-        fstats.merge(&do_incremental(index, &p))
-    } else if (p.g.read_ops() + p.g.write_ops()) > 0 {
-        let mut w_threads = vec![];
-        for i in 0..p.rdms.writers {
-            let w = index.to_writer().unwrap();
-            let pr = p.clone();
-            w_threads.push(thread::spawn(move || do_write(i, w, pr)));
-        }
-        let mut r_threads = vec![];
-        for i in 0..p.rdms.readers {
-            let r = index.to_reader().unwrap();
-            let pr = p.clone();
-            r_threads.push(thread::spawn(move || do_read(i, r, pr)));
-        }
-
-        fstats.merge(&{
-            let mut fstats = stats::Ops::new();
-            for t in w_threads {
-                fstats.merge(&t.join().unwrap());
-            }
-            stats!(&p.cmd_opts, "ixperf", "all-writers stats\n{:?}", fstats);
-            fstats
-        });
-        fstats.merge(&{
-            let mut fstats = stats::Ops::new();
-            for t in r_threads {
-                fstats.merge(&t.join().unwrap());
-            }
-            stats!(&p.cmd_opts, "ixperf", "all-readers stats\n{:?}", fstats);
-            fstats
-        });
-    }
+    let (initial, readers, writers) = (p.rdms.initial, p.rdms.readers, p.rdms.writers);
+    adapter::do_concur_perf(index, initial, readers, writers, p)
+}
 
-    if p.g.iters {
-        info!(
-            target: "ixperf",
-            "took {:?} to iter over {} items", iter_elapsed, iter_count
-        );
-    }
+/// Diff mode for indexes that are read-only after the initial load.
+/// Read ops are applied in the calling thread using reader `r`, and
+/// the full table scan of index is compared at the end, refer
+/// [crate::differential].
+pub(crate) fn do_diff_read<K, V, I>(
+    index: &mut rdms::Rdms<K, V, I>,
+    r: <I as Index<K, V>>::R,
+    opt: DiffOpt,
+    p: Profile,
+) -> Result<stats::Ops, String>
+where
+    K: 'static + Clone + Default + Send + Ord + fmt::Debug + Footprint + RandomKV,
+    V: 'static + Clone + Default + Send + PartialEq + fmt::Debug + Diff + Footprint + RandomKV,
+    I: Index<K, V>,
+{
+    warn!(target: "ixperf", "diff mode, latency includes the comparison");
 
-    fstats
+    let r = DiffReader::new(RdmsReader(r), opt.clone());
+    let fstats = adapter::do_read(0, r, p)?;
+    let n = Differ::new(index, opt.clone()).validate::<K, V>()?;
+    info!(target: "ixperf", "diff {} entries, {:?}", n, opt.mode);
+    Ok(fstats)
 }
 
-fn do_initial_load<K, V, I>(
-    index: &mut rdms::Rdms<K, V, I>, // index
-    p: &Profile,
-) -> stats::Ops
+impl<K, V, I> IndexAdapter<K, V> for rdms::Rdms<K, V, I>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + Footprint + RandomKV,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + RandomKV,
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
     I: Index<K, V>,
-    <I as Index<K, V>>::W: 'static + Send + Sync,
 {
-    if p.g.loads == 0 {
-        return stats::Ops::new();
-    }
-
-    let n_threads = p.rdms.initial_threads();
+    type R = RdmsReader<<I as Index<K, V>>::R>;
+    type W = RdmsWriter<<I as Index<K, V>>::W, <I as Index<K, V>>::R>;
 
-    let mut threads = vec![];
-    for i in 0..n_threads {
-        let w = index.to_writer().unwrap();
-        let pr = p.clone();
-        threads.push(thread::spawn(move || do_initial(i, w, pr)));
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        let r = rdms::Rdms::to_reader(self).map_err(to_error)?;
+        Ok(RdmsReader(r))
     }
 
-    let mut fstats = stats::Ops::new();
-    for t in threads {
-        fstats.merge(&t.join().unwrap());
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        let w = rdms::Rdms::to_writer(self).map_err(to_error)?;
+        let r = rdms::Rdms::to_reader(self).map_err(to_error)?;
+        Ok(RdmsWriter(w, r, 0))
     }
-
-    stats!(&p.cmd_opts, "ixperf", "initial stats\n{:?}\n", fstats);
-    fstats
 }
 
-fn do_initial<W, K, V>(id: usize, mut w: W, mut p: Profile) -> stats::Ops
+/// Adapt rdms Reader handle.
+pub(crate) struct RdmsReader<R>(pub R);
+
+impl<K, V, R> ReaderAdapter<K, V> for RdmsReader<R>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + Footprint + RandomKV,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + RandomKV,
-    W: Writer<K, V>,
+    K: Clone + Ord,
+    V: Clone + Diff,
+    R: Reader<K, V>,
 {
-    p.g.seed += (id * 100) as u128; // change the seed
-
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = InitialLoad::<K, V>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Load { key, value } => {
-                    lstats.load.sample_start(false);
-                    let items = w.set(key, value).unwrap().map_or(0, |_| 1);
-                    lstats.load.sample_end(items);
+    // deleted entries, in lsm and sticky mode, are a miss.
+    fn get(&mut self, key: &K, mut visit: Visit<K, V>) -> Result<bool, String> {
+        match self.0.get(key) {
+            Ok(entry) => match entry.to_native_value() {
+                Some(value) => {
+                    adapter::do_visit(&mut visit, entry.as_key(), &value);
+                    Ok(true)
                 }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    &p.cmd_opts,
-                    "ixperf",
-                    "initial-{} periodic-stats\n{}",
-                    id,
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
+                None => Ok(false),
+            },
+            Err(rdms::error::Error::KeyNotFound) => Ok(false),
+            Err(err) => Err(to_error(err)),
+        }
+    }
+
+    // all keys are looked up using the same reader handle, deleted
+    // entries are a miss.
+    fn multi_get(&mut self, keys: &[K], mut visit: Visit<K, V>) -> Result<usize, String> {
+        let mut n = 0;
+        for key in keys.iter() {
+            match self.0.get(key) {
+                Ok(entry) => {
+                    if let Some(value) = entry.to_native_value() {
+                        adapter::do_visit(&mut visit, entry.as_key(), &value);
+                        n += 1;
+                    }
+                }
+                Err(rdms::error::Error::KeyNotFound) => (),
+                Err(err) => return Err(to_error(err)),
             }
         }
-        fstats.merge(&lstats);
+        Ok(n)
+    }
+
+    fn range(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let iter = self.0.range((low, high)).map_err(to_error)?;
+        visit_entries(iter, visit)
+    }
 
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+    fn reverse(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let iter = self.0.reverse((low, high)).map_err(to_error)?;
+        visit_entries(iter, visit)
+    }
+
+    fn scan(&mut self, start: &K, limit: usize, visit: Visit<K, V>) -> Result<usize, String> {
+        let range = (Bound::Included(start.clone()), Bound::Unbounded);
+        let iter = self.0.range(range).map_err(to_error)?;
+        scan_entries(iter, limit, visit)
+    }
 
-    stats!(&p.cmd_opts, "ixperf", "initial-{} stats\n{:?}", id, fstats);
-    info!(
-        target: "ixperf", "initial-{} load_ops:{} elapsed:{:?}",
-        id, p.g.loads, elapsed
-    );
+    fn reverse_scan(
+        &mut self,
+        start: &K,
+        limit: usize,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let range = (Bound::Unbounded, Bound::Included(start.clone()));
+        let iter = self.0.reverse(range).map_err(to_error)?;
+        scan_entries(iter, limit, visit)
+    }
 
-    fstats
+    fn iter(&mut self, visit: Visit<K, V>) -> Result<usize, String> {
+        let iter = self.0.iter().map_err(to_error)?;
+        visit_entries(iter, visit)
+    }
 }
 
-fn do_incremental<K, V, I>(
-    index: &mut rdms::Rdms<K, V, I>, // index
-    p: &Profile,
-) -> stats::Ops
+/// Adapt rdms Writer handle, along with a Reader handle to fetch the
+/// current seqno for read-modify-write and conditional sets, and the
+/// number of compare-and-set conflicts not yet reported.
+pub(crate) struct RdmsWriter<W, R>(pub W, pub R, pub usize);
+
+impl<K, V, W, R> WriterAdapter<K, V> for RdmsWriter<W, R>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + Footprint + RandomKV + Hash,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + RandomKV,
-    I: Index<K, V>,
+    K: Clone + Ord,
+    V: Clone + Diff,
+    W: Writer<K, V>,
+    R: Reader<K, V>,
 {
-    if (p.g.read_ops() + p.g.write_ops()) == 0 {
-        return stats::Ops::new();
+    fn set(&mut self, key: K, value: V) -> Result<bool, String> {
+        let old = self.0.set(key, value).map_err(to_error)?;
+        Ok(old.is_some())
     }
 
-    let mut w = index.to_writer().unwrap();
-    let mut r = index.to_reader().unwrap();
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = IncrementalLoad::<K, V>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Set { key, value } => {
-                    lstats.set.sample_start(false);
-                    let n = w.set(key, value.clone()).unwrap().map_or(0, |_| 1);
-                    lstats.set.sample_end(n);
-                }
-                Cmd::Delete { key } => {
-                    lstats.delete.sample_start(false);
-                    let items = w.delete(&key).unwrap().map_or(1, |_| 0);
-                    lstats.delete.sample_end(items);
-                }
-                Cmd::Get { key } => {
-                    lstats.get.sample_start(false);
-                    let items = r.get(&key).ok().map_or(1, |_| 0);
-                    lstats.get.sample_end(items);
-                }
-                Cmd::Range { low, high } => {
-                    let iter = r.range((low, high)).unwrap();
-                    lstats.range.sample_start(true);
-                    lstats.range.sample_end(iter.fold(0, |acc, _| acc + 1));
-                }
-                Cmd::Reverse { low, high } => {
-                    let iter = r.reverse((low, high)).unwrap();
-                    lstats.reverse.sample_start(true);
-                    lstats.reverse.sample_end(iter.fold(0, |acc, _| acc + 1));
-                }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    &p.cmd_opts,
-                    "ixperf",
-                    "incremental periodic-stats\n{}",
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
+    fn delete(&mut self, key: &K) -> Result<bool, String> {
+        let old = self.0.delete(key).map_err(to_error)?;
+        Ok(old.is_some())
+    }
+
+    // set_cas fails if the entry was modified after the get, retry
+    // with the latest value.
+    fn read_modify_write(
+        &mut self,
+        key: K,
+        modify: &mut dyn FnMut(Option<&V>) -> V,
+    ) -> Result<bool, String> {
+        loop {
+            let (old, cas) = self.get_cas(&key)?;
+            let value = modify(old.as_ref());
+            match self.0.set_cas(key.clone(), value, cas) {
+                Ok(_) => break Ok(old.is_some()),
+                Err(rdms::error::Error::InvalidCAS(_)) => self.2 += 1,
+                Err(err) => break Err(to_error(err)),
             }
         }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
-
-    stats!(&p.cmd_opts, "ixperf", "incremental stats\n{:?}", fstats);
-    info!(
-        target: "ixperf",
-        "incremental-load r_ops:{} w_ops:{} elapsed:{:?}",
-        p.g.read_ops(), p.g.write_ops(), elapsed
-    );
-
-    fstats
-}
-
-fn do_write<W, K, V>(id: usize, mut w: W, mut p: Profile) -> stats::Ops
-where
-    K: 'static + Clone + Default + Send + Sync + Ord + Footprint + RandomKV,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + RandomKV,
-    W: Writer<K, V>,
-{
-    p.g.seed += (id * 100) as u128; // change the seed
+    }
 
-    if p.g.write_ops() == 0 {
-        return stats::Ops::new();
+    // cas of ZERO, sets the entry only if it is not already present.
+    fn set_if_absent(&mut self, key: K, value: V) -> Result<bool, String> {
+        match self.0.set_cas(key, value, 0) {
+            Ok(_) => Ok(true),
+            Err(rdms::error::Error::InvalidCAS(_)) => Ok(false),
+            Err(err) => Err(to_error(err)),
+        }
     }
 
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = IncrementalWrite::<K, V>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Set { key, value } => {
-                    lstats.set.sample_start(false);
-                    let n = w.set(key, value.clone()).unwrap().map_or(0, |_| 1);
-                    lstats.set.sample_end(n);
-                }
-                Cmd::Delete { key } => {
-                    lstats.delete.sample_start(false);
-                    let items = w.delete(&key).unwrap().map_or(1, |_| 0);
-                    lstats.delete.sample_end(items);
+    fn set_if_present(&mut self, key: K, value: V) -> Result<bool, String> {
+        match self.get_cas(&key)? {
+            (Some(_), cas) => match self.0.set_cas(key, value, cas) {
+                Ok(_) => Ok(true),
+                Err(rdms::error::Error::InvalidCAS(_)) => {
+                    self.2 += 1;
+                    Ok(false)
                 }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    &p.cmd_opts,
-                    "ixperf",
-                    "writer-{} periodic-stats\n{}",
-                    id,
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
-            }
+                Err(err) => Err(to_error(err)),
+            },
+            (None, _) => Ok(false),
         }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+    }
 
-    stats!(&p.cmd_opts, "ixperf", "writer-{} stats\n{:?}", id, fstats);
-    info!(
-        target: "ixperf", "writer-{} w_ops:{} elapsed:{:?}",
-        id, p.g.write_ops(), elapsed
-    );
+    fn to_conflicts(&mut self) -> usize {
+        std::mem::replace(&mut self.2, 0)
+    }
+}
 
-    fstats
+impl<W, R> RdmsWriter<W, R> {
+    // return the current value, if any, and the seqno to be used as cas.
+    fn get_cas<K, V>(&mut self, key: &K) -> Result<(Option<V>, u64), String>
+    where
+        K: Clone + Ord,
+        V: Clone + Diff,
+        R: Reader<K, V>,
+    {
+        match self.1.get(key) {
+            Ok(entry) => Ok((entry.to_native_value(), entry.to_seqno())),
+            Err(rdms::error::Error::KeyNotFound) => Ok((None, 0)),
+            Err(err) => Err(to_error(err)),
+        }
+    }
 }
 
-pub(crate) fn do_read<R, K, V>(id: usize, mut r: R, mut p: Profile) -> stats::Ops
+// Visit and count entries holding a value, deleted entries are
+// skipped.
+fn visit_entries<K, V, I>(iter: I, mut visit: Visit<K, V>) -> Result<usize, String>
 where
-    K: 'static + Clone + Default + Send + Sync + Ord + Footprint + RandomKV + Hash,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + RandomKV,
-    R: Reader<K, V>,
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = rdms::core::Result<Entry<K, V>>>,
 {
-    p.g.seed += (id * 100) as u128; // change the seed
-
-    if p.g.read_ops() == 0 {
-        return stats::Ops::new();
+    let mut n = 0;
+    for entry in iter {
+        let entry = entry.map_err(to_error)?;
+        if let Some(value) = entry.to_native_value() {
+            adapter::do_visit(&mut visit, entry.as_key(), &value);
+            n += 1;
+        }
     }
+    Ok(n)
+}
 
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = IncrementalRead::<K, V>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Get { key } => {
-                    lstats.get.sample_start(false);
-                    let items = r.get(&key).ok().map_or(1, |_| 0);
-                    lstats.get.sample_end(items);
-                }
-                Cmd::Range { low, high } => {
-                    let iter = r.range((low, high)).unwrap();
-                    lstats.range.sample_start(true);
-                    lstats.range.sample_end(iter.fold(0, |acc, _| acc + 1));
-                }
-                Cmd::Reverse { low, high } => {
-                    let iter = r.reverse((low, high)).unwrap();
-                    lstats.reverse.sample_start(true);
-                    lstats.reverse.sample_end(iter.fold(0, |acc, _| acc + 1));
-                }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    &p.cmd_opts,
-                    "ixperf",
-                    "reader-{} periodic-stats\n{}",
-                    id,
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
-            }
+// Visit and count upto limit entries holding a value, deleted entries
+// are skipped.
+fn scan_entries<K, V, I>(iter: I, limit: usize, mut visit: Visit<K, V>) -> Result<usize, String>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = rdms::core::Result<Entry<K, V>>>,
+{
+    let mut n = 0;
+    for entry in iter {
+        if n >= limit {
+            break;
         }
-        fstats.merge(&lstats);
-
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
-
-    stats!(&p.cmd_opts, "ixperf", "reader-{} stats\n{:?}", id, fstats);
-    info!(
-        target: "ixperf", "reader-{} r_ops:{} elapsed:{:?}",
-        id, p.g.read_ops(), elapsed
-    );
+        let entry = entry.map_err(to_error)?;
+        if let Some(value) = entry.to_native_value() {
+            adapter::do_visit(&mut visit, entry.as_key(), &value);
+            n += 1;
+        }
+    }
+    Ok(n)
+}
 
-    fstats
+fn to_error(err: rdms::error::Error) -> String {
+    format!("rdms: {:?}", err)
 }
@@ -21,7 +21,7 @@ use crate::Profile;
 
 #[derive(Default, Clone)]
 pub struct DgmOpt {
-    dir: ffi::OsString,
+    pub dir: ffi::OsString,
     mem_index: String,
     disk_index: String,
     lsm: bool,
@@ -111,7 +111,7 @@ impl DgmOpt {
     }
 }
 
-pub(crate) fn perf<K, V>(name: &str, p: Profile)
+pub(crate) fn perf<K, V>(name: &str, p: Profile) -> Result<(), String>
 where
     K: 'static
         + Clone
@@ -124,7 +124,17 @@ where
         + fmt::Debug
         + RandomKV
         + Hash,
-    V: 'static + Clone + Default + Send + Sync + Diff + Serialize + Footprint + RandomKV,
+    V: 'static
+        + Clone
+        + Default
+        + Send
+        + Sync
+        + PartialEq
+        + fmt::Debug
+        + Diff
+        + Serialize
+        + Footprint
+        + RandomKV,
     <V as Diff>::D: Send + Default + Serialize,
 {
     let m = p.rdms_dgm.mem_index.clone();
@@ -136,7 +146,7 @@ where
                 let d = p.rdms_robt.new_factory::<K, V, CRoaring>(name);
                 rdms::Rdms::new(name, p.rdms_dgm.new(name, m, d)).unwrap()
             };
-            let _fstats = mod_rdms::do_perf::<K, V, _>(&mut index, &p);
+            mod_rdms::do_perf::<K, V, _>(&mut index, &p)?;
             index.validate().unwrap()
         }
         ("llrb", "robt", "nobitmap") => {
@@ -145,11 +155,12 @@ where
                 let d = p.rdms_robt.new_factory::<K, V, CRoaring>(name);
                 rdms::Rdms::new(name, p.rdms_dgm.new(name, m, d)).unwrap()
             };
-            let _fstats = mod_rdms::do_perf::<K, V, _>(&mut index, &p);
+            mod_rdms::do_perf::<K, V, _>(&mut index, &p)?;
             index.validate().unwrap()
         }
         _ => unreachable!(),
     };
 
     info!(target: "ixperf", "rdms shllrb stats\n{}", istats);
+    Ok(())
 }
@@ -1,11 +1,11 @@
-use log::info;
+use log::{info, warn};
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 
 use rdms::{
     self,
     core::{
         Bloom, CommitIter, Cutoff, Diff, DiskIndexFactory, Entry, Footprint, Index, Reader,
-        Serialize, Validate, Writer,
+        Serialize, Validate,
     },
     llrb::Llrb,
     robt::{self, Robt, RobtFactory},
@@ -17,11 +17,12 @@ use std::{
     hash::Hash,
     ops::Bound,
     thread,
-    time::{Duration, SystemTime},
+    time::{Duration, Instant},
 };
 
+use crate::adapter;
+use crate::generator::IncrementalWrite;
 use crate::generator::RandomKV;
-use crate::generator::{Cmd, IncrementalWrite};
 use crate::mod_rdms;
 use crate::stats;
 use crate::Profile;
@@ -118,7 +119,7 @@ impl RobtOpt {
     }
 }
 
-pub(crate) fn perf<K, V, B>(name: &str, mut p: Profile)
+pub(crate) fn perf<K, V, B>(name: &str, mut p: Profile) -> Result<(), String>
 where
     K: 'static
         + Clone
@@ -131,21 +132,47 @@ where
         + fmt::Debug
         + RandomKV
         + Hash,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + Serialize + RandomKV,
+    V: 'static
+        + Clone
+        + Default
+        + Send
+        + Sync
+        + PartialEq
+        + fmt::Debug
+        + Diff
+        + Footprint
+        + Serialize
+        + RandomKV,
     <V as Diff>::D: Send + Default + Serialize,
     B: 'static + Bloom + Send + Sync,
 {
+    if p.cmd_opts.verify {
+        return Err("rdms robt does not support verify".to_string());
+    } else if p.diff.is_some() && p.rdms.readers > 1 {
+        let msg = format!("diff mode needs single reader, readers:{}", p.rdms.readers);
+        return Err(msg);
+    }
+
+    // load batches below change the seed, diff mode reads with the
+    // same seed as the other index.
+    let seed = p.g.seed;
+
     info!(target: "ixperf", "for type <{},{}>", p.key_type, p.val_type);
 
     let robt_index = p.rdms_robt.new(name);
     let mut index = rdms::Rdms::new(name, robt_index).unwrap();
 
     // load initial data.
+    let amp = p.amplify.as_ref().map(|amplify| amplify.start());
     let mut total_elapsed: Duration = Default::default();
     let mut fstats = stats::Ops::new();
     let mut rng = SmallRng::from_seed(p.g.seed.to_le_bytes());
     let mut seqno = 0;
     for i in 0..(p.g.loads / p.g.write_ops()) {
+        // on stop, batch so far is already committed, skip the rest.
+        if adapter::is_stopped() {
+            break;
+        }
         let mut mem_index = if p.rdms_robt.delta_ok {
             Llrb::new_lsm("load-robt")
         } else {
@@ -155,21 +182,21 @@ where
         mem_index.set_seqno(seqno).unwrap();
         p.g.seed += i as u128 * 100;
         let gen = IncrementalWrite::<K, V>::new(p.g.clone());
-        let mut w = mem_index.to_writer().unwrap();
+        let mut w = mod_rdms::RdmsWriter(
+            mem_index.to_writer().unwrap(),
+            mem_index.to_reader().unwrap(),
+            0,
+        );
         for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Set { key, value } => {
-                    fstats.set.sample_start(false);
-                    let n = w.set(key, value.clone()).unwrap().map_or(0, |_| 1);
-                    fstats.set.sample_end(n);
-                }
-                Cmd::Delete { key } => {
-                    fstats.delete.sample_start(false);
-                    let items = w.delete(&key).unwrap().map_or(1, |_| 0);
-                    fstats.delete.sample_end(items);
-                }
-                _ => unreachable!(),
-            };
+            if adapter::is_stopped() {
+                break;
+            }
+            let is_insert = cmd.is_insert();
+            match adapter::do_write_op(&mut w, cmd, &mut fstats) {
+                Ok(_) if is_insert => p.g.insert_applied(),
+                Ok(_) => (),
+                Err(err) => p.error_policy.check(fstats.to_total_errors(), err)?,
+            }
         }
 
         seqno = mem_index.to_seqno().unwrap();
@@ -177,14 +204,14 @@ where
         std::mem::drop(w);
 
         let elapsed = {
-            let start = SystemTime::now();
+            let start = Instant::now();
             index
                 .commit(
                     CommitIter::new(mem_index, (Bound::Unbounded, Bound::Included(seqno))),
                     |meta| meta,
                 )
                 .unwrap();
-            Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
+            start.elapsed()
         };
         info!(
             target: "ixperf",
@@ -196,46 +223,63 @@ where
     info!(target: "ixperf", "Total elapsed for commits {:?}", total_elapsed);
 
     let elapsed = {
-        let start = SystemTime::now();
+        let start = Instant::now();
         let cutoff = Cutoff::new_lsm(Bound::Excluded(0));
         index.compact(cutoff).unwrap();
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
+        start.elapsed()
     };
     info!(target: "ixperf", "Took {:?} to compact", elapsed);
+    if let (Some(amplify), Some(amp)) = (&p.amplify, amp) {
+        let report = amplify.finish(amp, &fstats, None);
+        info!(target: "ixperf", "initial amplification\n{}", report);
+    }
 
     // validate
     let mut r = index.to_reader().unwrap();
     validate_robt::<K, V, B>(&mut r, &fstats, &p);
 
     // optional iteration
-    let (start, mut iter_count) = (SystemTime::now(), 0);
+    let mut istats = stats::Ops::new();
     if p.g.iters {
-        for _ in r.iter().unwrap() {
-            iter_count += 1
-        }
+        let start = Instant::now();
+        istats.iter.sample_start(true);
+        istats.iter.sample_scan(r.iter().unwrap().count());
+        istats.set_elapsed(start.elapsed());
     }
-    let idur = Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64);
 
-    // concurrent readers
-    let mut fstats = stats::Ops::new();
-    let mut threads = vec![];
-    for i in 0..p.rdms.readers {
-        let mut r = index.to_reader().unwrap();
-        r.set_mmap(p.rdms_robt.mmap).unwrap();
-        let pr = p.clone();
-        threads.push(thread::spawn(move || mod_rdms::do_read(i, r, pr)));
-    }
-    for t in threads {
-        fstats.merge(&t.join().unwrap());
-    }
+    // concurrent readers, in diff mode a single reader in this thread.
+    let amp = p.amplify.as_ref().map(|amplify| amplify.start());
+    let fstats = match p.diff.clone() {
+        Some(opt) => {
+            let mut r = index.to_reader().unwrap();
+            r.set_mmap(p.rdms_robt.mmap).unwrap();
+            let mut pr = p.clone();
+            pr.g.seed = seed;
+            mod_rdms::do_diff_read(&mut index, r, opt, pr)?
+        }
+        None => {
+            let mut threads = vec![];
+            for i in 0..p.rdms.readers {
+                let mut r = index.to_reader().unwrap();
+                r.set_mmap(p.rdms_robt.mmap).unwrap();
+                let pr = p.clone();
+                threads.push(thread::spawn(move || {
+                    adapter::do_read(i, mod_rdms::RdmsReader(r), pr)
+                }));
+            }
+            adapter::join_threads(threads)?
+        }
+    };
 
     if p.g.iters {
-        info!(
-            target: "ixperf",
-            "rdms took {:?} to iter over {} items", idur, iter_count
-        );
+        info!(target: "ixperf", "iter stats\n{:?}", istats);
     }
     info!(target: "ixperf", "concurrent stats\n{:?}", fstats);
+    if let (Some(amplify), Some(amp)) = (&p.amplify, amp) {
+        let report = amplify.finish(amp, &fstats, None);
+        info!(target: "ixperf", "concurrent amplification\n{}", report);
+    }
+    Ok(())
 }
 
 fn validate_robt<K, V, B>(r: &mut robt::Snapshot<K, V, B>, fstats: &stats::Ops, p: &Profile)
@@ -248,6 +292,15 @@ where
     info!(target: "ixperf", "validating robt index ...");
 
     let stats: robt::Stats = r.validate().unwrap();
+
+    // failed ops, under continue policy, may or may not have touched the
+    // index, counts won't add up.
+    let errors = fstats.to_total_errors();
+    if errors > 0 {
+        warn!(target: "ixperf", "skipping validation, {} ops failed", errors);
+        return;
+    }
+
     if p.rdms_robt.delta_ok {
         let (mut n_muts, iter) = (0, r.iter_with_versions().unwrap());
         for entry in iter {
@@ -255,7 +308,13 @@ where
             let versions: Vec<Entry<K, V>> = entry.versions().collect();
             n_muts += versions.len();
         }
-        assert_eq!(n_muts, fstats.to_total_writes());
+        // set_if_absent on an existing key, set_if_present on a missing
+        // key and a conflicting set_if_present don't write.
+        let n_writes = fstats.to_total_writes()
+            - fstats.set_if_absent.hit
+            - fstats.set_if_present.missed
+            - fstats.set_if_present.conflicts;
+        assert_eq!(n_muts, n_writes);
     }
 
     let footprint: isize = (stats.m_bytes + stats.z_bytes + stats.v_bytes + stats.n_abytes)
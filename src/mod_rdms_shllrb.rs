@@ -77,10 +77,19 @@ impl ShllrbOpt {
     }
 }
 
-pub(crate) fn perf<K, V>(name: &str, p: Profile)
+pub(crate) fn perf<K, V>(name: &str, p: Profile) -> Result<(), String>
 where
     K: 'static + Clone + Default + Send + Sync + Ord + Footprint + fmt::Debug + RandomKV + Hash,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + RandomKV,
+    V: 'static
+        + Clone
+        + Default
+        + Send
+        + Sync
+        + PartialEq
+        + fmt::Debug
+        + Diff
+        + Footprint
+        + RandomKV,
     <V as Diff>::D: Send,
 {
     info!(target: "ixperf", "for type <{},{}>", p.key_type, p.val_type);
@@ -88,11 +97,12 @@ where
     let index = p.rdms_shllrb.new(name);
     let mut index = rdms::Rdms::new(name, index).unwrap();
 
-    let fstats = mod_rdms::do_perf::<K, V, Box<shllrb::ShLlrb<K, V>>>(&mut index, &p);
+    let fstats = mod_rdms::do_perf::<K, V, Box<shllrb::ShLlrb<K, V>>>(&mut index, &p)?;
 
     let istats = index.validate().unwrap();
     info!(target: "ixperf", "rdms shllrb stats\n{}", istats);
     validate_shllrb::<K, V>(&istats, &fstats, &p);
+    Ok(())
 }
 
 fn validate_shllrb<K, V>(stats: &LlrbStats, fstats: &stats::Ops, p: &Profile)
@@ -101,14 +111,11 @@ where
     V: Clone + Diff + Default + Footprint + RandomKV,
 {
     if p.rdms_shllrb.lsm || p.rdms_shllrb.sticky {
-        let expected_entries = (fstats.load.count - fstats.load.items)
-            + (fstats.set.count - fstats.set.items)
-            + fstats.delete.items;
+        // deleting a missing key adds a tombstone entry.
+        let expected_entries = fstats.to_total_inserted() + fstats.delete.missed;
         assert_eq!(stats.entries, expected_entries);
     } else {
-        let expected_entries = (fstats.load.count - fstats.load.items)
-            + (fstats.set.count - fstats.set.items)
-            - (fstats.delete.count - fstats.delete.items);
+        let expected_entries = fstats.to_total_inserted() - fstats.delete.hit;
         assert_eq!(stats.entries, expected_entries);
     }
 
@@ -1,11 +1,11 @@
-use log::info;
+use log::{info, warn};
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 
 use rdms::{
     self,
     core::{
         Bloom, CommitIter, Cutoff, Diff, DiskIndexFactory, Entry, Footprint, Index, Reader,
-        Serialize, Validate, Writer,
+        Serialize, Validate,
     },
     llrb::Llrb,
     robt,
@@ -18,10 +18,11 @@ use std::{
     hash::Hash,
     ops::Bound,
     thread,
-    time::{Duration, SystemTime},
+    time::Instant,
 };
 
-use crate::generator::{Cmd, IncrementalWrite, RandomKV};
+use crate::adapter;
+use crate::generator::{IncrementalWrite, RandomKV};
 use crate::mod_rdms;
 use crate::stats;
 use crate::Profile;
@@ -125,7 +126,7 @@ impl ShrobtOpt {
     }
 }
 
-pub(crate) fn perf<K, V, B>(name: &str, mut p: Profile)
+pub(crate) fn perf<K, V, B>(name: &str, mut p: Profile) -> Result<(), String>
 where
     K: 'static
         + Clone
@@ -138,10 +139,31 @@ where
         + fmt::Debug
         + RandomKV
         + Hash,
-    V: 'static + Clone + Default + Send + Sync + Diff + Footprint + Serialize + RandomKV,
+    V: 'static
+        + Clone
+        + Default
+        + Send
+        + Sync
+        + PartialEq
+        + fmt::Debug
+        + Diff
+        + Footprint
+        + Serialize
+        + RandomKV,
     <V as Diff>::D: Send + Default + Serialize,
     B: 'static + Bloom + Send + Sync,
 {
+    if p.cmd_opts.verify {
+        return Err("rdms shrobt does not support verify".to_string());
+    } else if p.diff.is_some() && p.rdms.readers > 1 {
+        let msg = format!("diff mode needs single reader, readers:{}", p.rdms.readers);
+        return Err(msg);
+    }
+
+    // load batches below change the seed, diff mode reads with the
+    // same seed as the other index.
+    let seed = p.g.seed;
+
     let srindex = p.rdms_shrobt.new(name);
     let mut index = rdms::Rdms::new(name, srindex).unwrap();
 
@@ -150,6 +172,10 @@ where
     let mut rng = SmallRng::from_seed(p.g.seed.to_le_bytes());
     let mut seqno = 0;
     for i in 0..(p.g.loads / p.g.write_ops()) {
+        // on stop, batch so far is already committed, skip the rest.
+        if adapter::is_stopped() {
+            break;
+        }
         let mut mem_index = if p.rdms_shrobt.delta_ok {
             Llrb::new_lsm("load-shrobt")
         } else {
@@ -159,21 +185,21 @@ where
         mem_index.set_seqno(seqno).unwrap();
         p.g.seed += i as u128 * 100;
         let gen = IncrementalWrite::<K, V>::new(p.g.clone());
-        let mut w = mem_index.to_writer().unwrap();
+        let mut w = mod_rdms::RdmsWriter(
+            mem_index.to_writer().unwrap(),
+            mem_index.to_reader().unwrap(),
+            0,
+        );
         for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Set { key, value } => {
-                    fstats.set.sample_start(false);
-                    let n = w.set(key, value.clone()).unwrap().map_or(0, |_| 1);
-                    fstats.set.sample_end(n);
-                }
-                Cmd::Delete { key } => {
-                    fstats.delete.sample_start(false);
-                    let items = w.delete(&key).unwrap().map_or(1, |_| 0);
-                    fstats.delete.sample_end(items);
-                }
-                _ => unreachable!(),
-            };
+            if adapter::is_stopped() {
+                break;
+            }
+            let is_insert = cmd.is_insert();
+            match adapter::do_write_op(&mut w, cmd, &mut fstats) {
+                Ok(_) if is_insert => p.g.insert_applied(),
+                Ok(_) => (),
+                Err(err) => p.error_policy.check(fstats.to_total_errors(), err)?,
+            }
         }
         seqno = mem_index.to_seqno().unwrap();
         std::mem::drop(w);
@@ -194,33 +220,40 @@ where
 
     // optional iteration
     let mut r = index.to_reader().unwrap();
-    let (start, mut iter_count) = (SystemTime::now(), 0);
+    let mut istats = stats::Ops::new();
     if p.g.iters {
-        for _ in r.iter().unwrap() {
-            iter_count += 1
-        }
+        let start = Instant::now();
+        istats.iter.sample_start(true);
+        istats.iter.sample_scan(r.iter().unwrap().count());
+        istats.set_elapsed(start.elapsed());
     }
-    let idur = Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64);
 
-    // concurrent readers
-    let mut fstats = stats::Ops::new();
-    let mut threads = vec![];
-    for i in 0..p.rdms.readers {
-        let r = index.to_reader().unwrap();
-        let pr = p.clone();
-        threads.push(thread::spawn(move || mod_rdms::do_read(i, r, pr)));
-    }
-    for t in threads {
-        fstats.merge(&t.join().unwrap());
-    }
+    // concurrent readers, in diff mode a single reader in this thread.
+    let fstats = match p.diff.clone() {
+        Some(opt) => {
+            let r = index.to_reader().unwrap();
+            let mut pr = p.clone();
+            pr.g.seed = seed;
+            mod_rdms::do_diff_read(&mut index, r, opt, pr)?
+        }
+        None => {
+            let mut threads = vec![];
+            for i in 0..p.rdms.readers {
+                let r = index.to_reader().unwrap();
+                let pr = p.clone();
+                threads.push(thread::spawn(move || {
+                    adapter::do_read(i, mod_rdms::RdmsReader(r), pr)
+                }));
+            }
+            adapter::join_threads(threads)?
+        }
+    };
 
     if p.g.iters {
-        info!(
-            target: "ixperf",
-            "rdms took {:?} to iter over {} items", idur, iter_count
-        );
+        info!(target: "ixperf", "iter stats\n{:?}", istats);
     }
     info!(target: "ixperf", "concurrent stats\n{:?}", fstats);
+    Ok(())
 }
 
 fn validate_shrobt<K, V, B>(
@@ -237,6 +270,15 @@ fn validate_shrobt<K, V, B>(
     info!(target: "ixperf", "validating shrobt index ...");
 
     let stats: robt::Stats = index.validate().unwrap();
+
+    // failed ops, under continue policy, may or may not have touched the
+    // index, counts won't add up.
+    let errors = fstats.to_total_errors();
+    if errors > 0 {
+        warn!(target: "ixperf", "skipping validation, {} ops failed", errors);
+        return;
+    }
+
     if p.rdms_shrobt.delta_ok {
         let (mut n_muts, iter) = (0, r.iter_with_versions().unwrap());
         for entry in iter {
@@ -244,7 +286,13 @@ fn validate_shrobt<K, V, B>(
             let versions: Vec<Entry<K, V>> = entry.versions().collect();
             n_muts += versions.len();
         }
-        assert_eq!(n_muts, fstats.to_total_writes());
+        // set_if_absent on an existing key, set_if_present on a missing
+        // key and a conflicting set_if_present don't write.
+        let n_writes = fstats.to_total_writes()
+            - fstats.set_if_absent.hit
+            - fstats.set_if_present.missed
+            - fstats.set_if_present.conflicts;
+        assert_eq!(n_muts, n_writes);
     }
 
     let footprint: isize = (stats.m_bytes + stats.z_bytes + stats.v_bytes + stats.n_abytes)
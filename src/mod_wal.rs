@@ -1,5 +1,3 @@
-use log::{debug, info};
-
 use rdms::{self, core::Serialize, dlog, wal};
 
 use std::{
@@ -7,17 +5,16 @@ use std::{
     convert::{TryFrom, TryInto},
     ffi,
     hash::{BuildHasher, Hash},
-    thread,
-    time::{Duration, SystemTime},
 };
 
-use crate::generator::{Cmd, IncrementalWrite, RandomKV};
+use crate::adapter::{self, IndexAdapter, WriteOnly, WriterAdapter};
+use crate::generator::RandomKV;
 use crate::stats;
 use crate::Profile;
 
 #[derive(Default, Clone)]
 pub struct WalOpt {
-    dir: ffi::OsString,
+    pub dir: ffi::OsString,
     name: String,
     writers: usize,
     nshards: usize,
@@ -111,8 +108,8 @@ pub(crate) fn perf(name: &str, p: Profile) -> Result<(), String> {
             p.key_type,
             p.val_type,
             p.wal.build_hasher.as_str(),
-        ))?,
-    };
+        )),
+    }?;
 
     Ok(())
 }
@@ -131,88 +128,79 @@ pub(crate) fn perf(name: &str, p: Profile) -> Result<(), String> {
             p.key_type,
             p.val_type,
             p.wal.build_hasher.as_str(),
-        ))?,
-    };
+        )),
+    }?;
 
     Ok(())
 }
 
-pub(crate) fn do_perf<K, V, H>(name: &str, p: Profile, build_hasher: H) -> stats::Ops
+pub(crate) fn do_perf<K, V, H>(
+    name: &str,
+    p: Profile,
+    build_hasher: H,
+) -> Result<stats::Ops, String>
 where
     K: 'static + Clone + Default + Send + Sync + Ord + Hash + Serialize + RandomKV,
     V: 'static + Clone + Default + Send + Sync + Serialize + RandomKV,
     H: 'static + Send + Clone + BuildHasher,
 {
-    let mut wl = p.wal.new::<K, V, H>(name, build_hasher);
-
-    let mut w_threads = vec![];
-    for i in 0..p.wal.writers {
-        let w = wl.to_writer().unwrap();
-        let pr = p.clone();
-        w_threads.push(thread::spawn(move || do_write(i, w, pr)));
-    }
-    let mut fstats = stats::Ops::new();
-    for t in w_threads {
-        fstats.merge(&t.join().unwrap());
+    if p.cmd_opts.verify || p.diff.is_some() {
+        return Err("wal does not support verify/diff".to_string());
     }
-    stats!(&p.cmd_opts, "ixperf", "all-writers stats\n{:?}", fstats);
 
-    fstats
+    let mut index = WalIndex(p.wal.new::<K, V, H>(name, build_hasher));
+    adapter::do_concurrent(&mut index, 0, p.wal.writers, &p)
 }
 
-fn do_write<K, V, H>(id: usize, mut w: wal::Writer<K, V, H>, mut p: Profile) -> stats::Ops
+/// Write ahead log is write-only.
+struct WalIndex<K, V, H>(wal::Wal<K, V, H>)
+where
+    K: 'static + Clone + Default + Send + Sync + Ord + Hash + Serialize + RandomKV,
+    V: 'static + Clone + Default + Send + Sync + Serialize + RandomKV,
+    H: 'static + Send + Clone + BuildHasher;
+
+impl<K, V, H> IndexAdapter<K, V> for WalIndex<K, V, H>
 where
     K: 'static + Clone + Default + Send + Sync + Ord + Hash + Serialize + RandomKV,
     V: 'static + Clone + Default + Send + Sync + Serialize + RandomKV,
     H: 'static + Send + Clone + BuildHasher,
 {
-    p.g.seed += (id * 100) as u128; // change the seed
+    type R = WriteOnly;
+    type W = WalWriter<K, V, H>;
 
-    if p.g.write_ops() == 0 {
-        return stats::Ops::new();
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(WriteOnly)
     }
 
-    let mut fstats = stats::Ops::new();
-    let elapsed = {
-        let start = SystemTime::now();
-
-        let mut lstats = stats::Ops::new();
-        let gen = IncrementalWrite::<K, V>::new(p.g.clone());
-        for (_i, cmd) in gen.enumerate() {
-            match cmd {
-                Cmd::Set { key, value } => {
-                    lstats.set.sample_start(false);
-                    w.set(key, value.clone()).unwrap();
-                    lstats.set.sample_end(0);
-                }
-                Cmd::Delete { key } => {
-                    lstats.delete.sample_start(false);
-                    w.delete(&key).unwrap();
-                    lstats.delete.sample_end(0);
-                }
-                _ => unreachable!(),
-            };
-            if lstats.is_sec_elapsed() {
-                stats!(
-                    &p.cmd_opts,
-                    "ixperf",
-                    "writer-{} periodic-stats\n{}",
-                    id,
-                    lstats
-                );
-                fstats.merge(&lstats);
-                lstats = stats::Ops::new();
-            }
-        }
-        fstats.merge(&lstats);
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
-    };
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        let w = self.0.to_writer().map_err(|e| format!("wal: {:?}", e))?;
+        Ok(WalWriter(w))
+    }
+}
+
+struct WalWriter<K, V, H>(wal::Writer<K, V, H>)
+where
+    K: 'static + Clone + Default + Send + Sync + Ord + Hash + Serialize + RandomKV,
+    V: 'static + Clone + Default + Send + Sync + Serialize + RandomKV,
+    H: 'static + Send + Clone + BuildHasher;
 
-    stats!(&p.cmd_opts, "ixperf", "writer-{} stats\n{:?}", id, fstats);
-    info!(
-        target: "ixperf", "writer-{} w_ops:{} elapsed:{:?}",
-        id, p.g.write_ops(), elapsed
-    );
+// Writes are appended to the log, hence set never updates an older value
+// and delete is always treated as found.
+impl<K, V, H> WriterAdapter<K, V> for WalWriter<K, V, H>
+where
+    K: 'static + Clone + Default + Send + Sync + Ord + Hash + Serialize + RandomKV,
+    V: 'static + Clone + Default + Send + Sync + Serialize + RandomKV,
+    H: 'static + Send + Clone + BuildHasher,
+{
+    fn set(&mut self, key: K, value: V) -> Result<bool, String> {
+        self.0
+            .set(key, value)
+            .map_err(|e| format!("wal: {:?}", e))?;
+        Ok(false)
+    }
 
-    fstats
+    fn delete(&mut self, key: &K) -> Result<bool, String> {
+        self.0.delete(key).map_err(|e| format!("wal: {:?}", e))?;
+        Ok(true)
+    }
 }
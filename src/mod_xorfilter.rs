@@ -1,6 +1,6 @@
 use log::{debug, info};
 use rand::{rngs::SmallRng, Rng, SeedableRng};
-use std::time::{Duration, SystemTime};
+use std::time::Instant;
 use xorfilter::Xor8;
 
 use std::fs;
@@ -18,9 +18,9 @@ pub fn perf(p: Profile) -> Result<(), String> {
     let mut filter = Xor8::new();
     filter.populate_keys(&keys);
     let elapsed = {
-        let start = SystemTime::now();
+        let start = Instant::now();
         filter.build();
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
+        start.elapsed()
     };
     info!(
         target: "xorf  ", "Took {:?} to build {} keys, {:?} per key",
@@ -41,12 +41,12 @@ pub fn perf(p: Profile) -> Result<(), String> {
     }
 
     let elapsed = {
-        let start = SystemTime::now();
+        let start = Instant::now();
         for _i in 0..p.g.gets {
             let off: usize = rng.gen::<usize>() % keys.len();
             filter.contains_key(keys[off]);
         }
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
+        start.elapsed()
     };
     info!(
         target: "xorf  ",
@@ -60,7 +60,7 @@ pub fn perf(p: Profile) -> Result<(), String> {
 fn generate_keys(p: &Profile, rng: &mut SmallRng) -> Vec<u64> {
     let mut keys = vec![];
     let elapsed = {
-        let start = SystemTime::now();
+        let start = Instant::now();
 
         let mut lookup: Vec<u8> = Vec::with_capacity((p.g.loads / 8) + 1);
         lookup.resize(lookup.capacity(), Default::default());
@@ -81,7 +81,7 @@ fn generate_keys(p: &Profile, rng: &mut SmallRng) -> Vec<u64> {
                 key += 1;
             }
         }
-        Duration::from_nanos(start.elapsed().unwrap().as_nanos() as u64)
+        start.elapsed()
     };
 
     debug!(
@@ -39,12 +39,20 @@ impl PlotData {
 
         self.render_cpu_load(opt, path_dir.clone());
         self.render_mem_rss(opt, path_dir.clone());
+        self.render_threads(opt, path_dir.clone());
+        self.render_page_faults(opt, path_dir.clone());
+        self.render_ctxt_switches(opt, path_dir.clone());
+        self.render_disk_io(opt, path_dir.clone());
+        self.render_index_dir_size(opt, path_dir.clone());
         self.render_load_throughput(opt, path_dir.clone());
         self.render_load_latency(opt, path_dir.clone());
+        self.render_load_counters(opt, path_dir.clone());
         self.render_incr_throughput(opt, path_dir.clone());
         self.render_incr_latency(opt, path_dir.clone());
+        self.render_incr_counters(opt, path_dir.clone());
         self.render_concur_throughput(opt, path_dir.clone());
         self.render_concur_latency(opt, path_dir.clone());
+        self.render_concur_counters(opt, path_dir.clone());
     }
 
     fn render_cpu_load(&self, _opt: &Opt, path_dir: path::PathBuf) {
@@ -61,28 +69,108 @@ impl PlotData {
         };
         vals.sort_by(|x, y| x.0.cmp(&y.0));
 
-        let y_values = vec![normalize_to_secs(vals)];
+        let y_values = vec![gauge_to_secs(vals)];
         let dir = &path_dir.join(file);
         do_render(dir, title, names, x_axis, y_axis, y_values)
     }
 
     fn render_mem_rss(&self, _opt: &Opt, path_dir: path::PathBuf) {
-        let stats = self.title_system.clone();
         let x_axis = "Seconds";
-        let y_axis = "RSS in MB";
+        let y_axis = "Memory in MB";
         let file = "memory-utilization.png";
-        let title = "memory rss utilization";
-        let names = vec!["rss".to_string()];
+        let title = "memory rss and virtual";
+        let names = vec!["rss".to_string(), "virt".to_string()];
+
+        let y_values = vec![
+            gauge_to_secs(self.to_system("mem_rss")),
+            gauge_to_secs(self.to_system("mem_virt")),
+        ];
+        let dir = &path_dir.join(file);
+        do_render(dir, title, names, x_axis, y_axis, y_values)
+    }
+
+    fn render_threads(&self, _opt: &Opt, path_dir: path::PathBuf) {
+        let x_axis = "Seconds";
+        let y_axis = "Threads";
+        let file = "threads.png";
+        let title = "process threads";
+        let names = vec!["threads".to_string()];
+
+        let y_values = vec![gauge_to_secs(self.to_system("threads"))];
+        let dir = &path_dir.join(file);
+        do_render(dir, title, names, x_axis, y_axis, y_values)
+    }
+
+    fn render_page_faults(&self, _opt: &Opt, path_dir: path::PathBuf) {
+        let x_axis = "Seconds";
+        let y_axis = "Page faults / Sec";
+        let file = "page-faults.png";
+        let title = "page faults";
+        let names = vec!["minor".to_string(), "major".to_string()];
+
+        let y_values = vec![
+            counter_to_secs(self.to_system("minor_faults")),
+            counter_to_secs(self.to_system("major_faults")),
+        ];
+        let dir = &path_dir.join(file);
+        do_render(dir, title, names, x_axis, y_axis, y_values)
+    }
+
+    fn render_ctxt_switches(&self, _opt: &Opt, path_dir: path::PathBuf) {
+        let x_axis = "Seconds";
+        let y_axis = "Context switches / Sec";
+        let file = "context-switches.png";
+        let title = "context switches";
+        let names = vec!["voluntary".to_string(), "involuntary".to_string()];
+
+        let y_values = vec![
+            counter_to_secs(self.to_system("voluntary_ctxt_switches")),
+            counter_to_secs(self.to_system("involuntary_ctxt_switches")),
+        ];
+        let dir = &path_dir.join(file);
+        do_render(dir, title, names, x_axis, y_axis, y_values)
+    }
+
+    fn render_disk_io(&self, _opt: &Opt, path_dir: path::PathBuf) {
+        let x_axis = "Seconds";
+        let y_axis = "KB / Sec";
+        let file = "disk-io.png";
+        let title = "disk io";
+        let names = vec!["read".to_string(), "write".to_string()];
+
+        let to_kb = |vals: Vec<u64>| -> Vec<u64> { vals.into_iter().map(|v| v / 1024).collect() };
+        let y_values = vec![
+            to_kb(counter_to_secs(self.to_system("read_bytes"))),
+            to_kb(counter_to_secs(self.to_system("write_bytes"))),
+        ];
+        let dir = &path_dir.join(file);
+        do_render(dir, title, names, x_axis, y_axis, y_values)
+    }
+
+    fn render_index_dir_size(&self, _opt: &Opt, path_dir: path::PathBuf) {
+        let x_axis = "Seconds";
+        let y_axis = "Size in MB";
+        let file = "index-dir-size.png";
+        let title = "index directory size";
+        let names = vec!["index-dir".to_string()];
+
+        let y_values = {
+            let vals = gauge_to_secs(self.to_system("index_dir_size"));
+            vec![vals.into_iter().map(|v| v / (1024 * 1024)).collect()]
+        };
+        let dir = &path_dir.join(file);
+        do_render(dir, title, names, x_axis, y_axis, y_values)
+    }
 
+    // samples for named system stat, sorted by time.
+    fn to_system(&self, name: &str) -> Vec<(i64, u64)> {
+        let stats = self.title_system.clone();
         let mut vals: Vec<(i64, u64)> = {
-            let iter = stats.iter().flatten().filter_map(|s| s.to_mem_rss());
+            let iter = stats.iter().flatten().filter_map(|s| s.to_system(name));
             iter.collect()
         };
         vals.sort_by(|x, y| x.0.cmp(&y.0));
-
-        let y_values = vec![normalize_to_secs(vals)];
-        let dir = &path_dir.join(file);
-        do_render(dir, title, names, x_axis, y_axis, y_values)
+        vals
     }
 
     fn render_load_throughput(&self, _opt: &Opt, path_dir: path::PathBuf) {
@@ -124,6 +212,11 @@ impl PlotData {
         do_render(dir, &title, names, x_axis, y_axis, y_values)
     }
 
+    fn render_load_counters(&self, _opt: &Opt, path_dir: path::PathBuf) {
+        let stats = self.title_initial.clone();
+        render_counters(stats, "initial-load", "load", &path_dir)
+    }
+
     fn render_incr_throughput(&self, _opt: &Opt, path_dir: path::PathBuf) {
         let stats = self.title_incrmnt.clone();
         let x_axis = "Seconds";
@@ -180,6 +273,13 @@ impl PlotData {
         do_render(dir, &title, names, x_axis, y_axis, y_values)
     }
 
+    fn render_incr_counters(&self, _opt: &Opt, path_dir: path::PathBuf) {
+        for op_name in vec!["set", "delete", "get"].into_iter() {
+            let stats = self.title_incrmnt.clone();
+            render_counters(stats, "incremental", op_name, &path_dir)
+        }
+    }
+
     fn render_concur_throughput(&self, _opt: &Opt, path_dir: path::PathBuf) {
         let x_axis = "Seconds";
         let y_axis = "Throughput kilo-ops / Sec";
@@ -294,6 +394,47 @@ impl PlotData {
         let dir = &path_dir.join(file);
         do_render(dir, &title, names, x_axis, y_axis, y_values)
     }
+
+    fn render_concur_counters(&self, _opt: &Opt, path_dir: path::PathBuf) {
+        for op_name in vec!["set", "delete"].into_iter() {
+            let stats = self.title_writers.clone();
+            render_counters(stats, "concurrent", op_name, &path_dir)
+        }
+        let stats = self.title_readers.clone();
+        render_counters(stats, "concurrent", "get", &path_dir)
+    }
+}
+
+// outcome counters for op, per second, from periodic-stats of all the
+// threads in `stats`, refer to stats::Op.
+fn render_counters(
+    stats: Vec<Vec<StatLine>>,
+    phase: &str,
+    op_name: &str,
+    path_dir: &path::PathBuf,
+) {
+    let x_axis = "Seconds";
+    let y_axis = "Count / Sec";
+    let file = format!("{}-{}-counters.png", phase, op_name);
+    let title = format!("{} {} counters", phase, op_name);
+    let counters = match op_name {
+        "load" | "set" => vec!["inserted", "updated", "errors"],
+        _ => vec!["hit", "missed", "errors"],
+    };
+    let names = counters.iter().map(|s| s.to_string()).collect();
+
+    let mut y_values: Vec<Vec<u64>> = vec![];
+    for counter in counters.into_iter() {
+        let mut vals: Vec<(i64, u64)> = {
+            let iter = stats.iter().flatten();
+            let iter = iter.filter_map(|s| s.to_counter(op_name, counter));
+            iter.collect()
+        };
+        vals.sort_by(|x, y| x.0.cmp(&y.0));
+        y_values.push(normalize_to_secs(vals));
+    }
+    let dir = &path_dir.join(file);
+    do_render(dir, &title, names, x_axis, y_axis, y_values)
 }
 
 fn do_render(
@@ -311,6 +452,10 @@ fn do_render(
     info!(target: "plot", "plotting throughput for {} at {:?}", title, file);
 
     let color_for = move |name: &str| match name {
+        "inserted" | "hit" => GREEN,
+        "updated" => BLUE,
+        "missed" => RED,
+        "errors" => MAGENTA,
         name if name.contains("load") => BLUE,
         name if name.contains("set") => GREEN,
         name if name.contains("delete") => RED,
@@ -319,6 +464,15 @@ fn do_render(
         name if name.contains("reverse") => MAGENTA,
         name if name.contains("cpu") => BLUE,
         name if name.contains("rss") => BLUE,
+        name if name.contains("virt") => RED,
+        name if name.contains("threads") => BLUE,
+        name if name.contains("minor") => BLUE,
+        name if name.contains("major") => RED,
+        name if name.contains("involuntary") => RED,
+        name if name.contains("voluntary") => BLUE,
+        name if name.contains("read") => GREEN,
+        name if name.contains("write") => RED,
+        name if name.contains("index-dir") => BLUE,
         name => panic!("unreachable {}", name),
     };
     let clrs: Vec<RGBColor> = names.iter().map(|n| color_for(n)).collect();
@@ -382,6 +536,13 @@ fn do_render(
 #[derive(Debug, Default)]
 pub struct PlotFiles(pub Vec<fs::File>);
 
+impl Clone for PlotFiles {
+    fn clone(&self) -> Self {
+        let files = self.0.iter().map(|f| f.try_clone().unwrap());
+        PlotFiles(files.collect())
+    }
+}
+
 impl FromStr for PlotFiles {
     type Err = String;
 
@@ -432,7 +593,9 @@ impl FromStr for PlotOps {
         let mut ops = vec![];
         for op in s.split(",") {
             match op {
-                "load" | "set" | "delete" | "get" | "range" | "reverse" => {
+                "load" | "set" | "insert" | "update" | "delete" | "get" | "multi_get" | "range"
+                | "reverse" | "scan" | "reverse_scan" | "seek" | "rmw" | "set_if_absent"
+                | "set_if_present" => {
                     // something something
                     ops.push(op.to_string())
                 }
@@ -609,11 +772,18 @@ impl StatLine {
     }
 
     fn to_ops(&self, op_name: &str) -> Option<(i64, u64)> {
+        self.to_counter(op_name, "ops")
+    }
+
+    // named counter for op, one of ops, inserted, updated, hit, missed,
+    // items_scanned or errors, refer to stats::Op. None if the counter
+    // is missing, like conflicts, which is logged only when non-zero.
+    fn to_counter(&self, op_name: &str, counter: &str) -> Option<(i64, u64)> {
         match self.value.as_table() {
             Some(table) => match table.get(op_name) {
                 Some(table) => {
-                    let ops = table["ops"].as_integer().unwrap();
-                    Some((self.millis, ops.try_into().unwrap()))
+                    let value = table.get(counter)?.as_integer().unwrap();
+                    Some((self.millis, value.try_into().unwrap()))
                 }
                 None => None,
             },
@@ -634,12 +804,14 @@ impl StatLine {
         }
     }
 
-    fn to_mem_rss(&self) -> Option<(i64, u64)> {
+    // named stat from system periodic-stats, refer system::ProcStats.
+    // Older logs might not have all of them.
+    fn to_system(&self, name: &str) -> Option<(i64, u64)> {
         match self.value.as_table() {
             Some(table) => match table.get("system") {
                 Some(table) => {
-                    let load = table["mem_rss"].as_integer().unwrap();
-                    Some((self.millis, load.try_into().unwrap()))
+                    let value = table.get(name)?.as_integer().unwrap();
+                    Some((self.millis, value.try_into().unwrap()))
                 }
                 None => None,
             },
@@ -673,6 +845,38 @@ impl StatLine {
     }
 }
 
+// gauges are sampled every --system-interval, pick the last sample in
+// every second, carrying forward when there is none.
+fn gauge_to_secs(items: Vec<(i64, u64)>) -> Vec<u64> {
+    let t0 = match items.first() {
+        Some((t0, _)) => *t0,
+        None => return vec![],
+    };
+    let mut acc: Vec<u64> = vec![];
+    for (t, v) in items.into_iter() {
+        let sec = ((t - t0) / 1000) as usize;
+        while acc.len() <= sec {
+            let last = acc.last().cloned().unwrap_or(v);
+            acc.push(last);
+        }
+        acc[sec] = v;
+    }
+    acc
+}
+
+// counters are cumulative, convert them to rate per second between
+// successive samples.
+fn counter_to_secs(items: Vec<(i64, u64)>) -> Vec<u64> {
+    let rates: Vec<(i64, u64)> = items
+        .windows(2)
+        .map(|w| {
+            let (dt, dv) = (w[1].0 - w[0].0, w[1].1.saturating_sub(w[0].1));
+            (w[1].0, dv * 1000 / (std::cmp::max(dt, 1) as u64))
+        })
+        .collect();
+    gauge_to_secs(rates)
+}
+
 fn normalize_to_secs(mut items: Vec<(i64, u64)>) -> Vec<u64> {
     if items.len() == 0 {
         vec![]
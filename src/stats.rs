@@ -1,30 +1,106 @@
-use std::fmt;
+use std::{collections::BTreeMap, fmt, time::Duration};
 
 use crate::latency::Latency;
 
+/// Latency and outcome of an operation type. Every outcome is
+/// accounted under a named counter, irrespective of the operation:
+///
+/// * inserted, writes that created a new entry.
+/// * updated, writes that replaced an existing entry.
+/// * hit, lookups that found the key, including set_if_absent skipped
+///   for an existing key.
+/// * missed, lookups that did not find the key, including
+///   set_if_present skipped for a missing key.
+/// * items_scanned, entries visited by range, scan and iter operations.
+/// * errors, operations that failed, also counted by error kind.
+///
+/// Additionally, bytes counts the logical size of keys and values
+/// written by successful write operations, refer [crate::amplify].
 pub struct Op {
     pub name: String,
     pub latency: Latency,
     pub count: usize,
-    pub items: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    pub hit: usize,
+    pub missed: usize,
+    pub items_scanned: usize,
+    pub errors: usize,
+    pub error_kinds: BTreeMap<String, usize>,
+    // compare-and-set conflicts, retried by rmw, and failing
+    // set_if_present, refer [crate::adapter::WriterAdapter::to_conflicts].
+    pub conflicts: usize,
+    pub bytes: usize,
     pub force: bool,
+    pub scans: Option<Scans>,
+    // wall-clock time of the phase, or thread, that applied this op.
+    pub elapsed: Duration,
 }
 
 impl Op {
     pub fn new(name: &str) -> Op {
+        let scans = match name {
+            "range" | "reverse" | "scan" | "reverse_scan" | "multi_get" => Some(Scans::new(name)),
+            _ => None,
+        };
         Op {
             name: name.to_string(),
             latency: Latency::new(name),
             count: Default::default(),
-            items: Default::default(),
+            inserted: Default::default(),
+            updated: Default::default(),
+            hit: Default::default(),
+            missed: Default::default(),
+            items_scanned: Default::default(),
+            errors: Default::default(),
+            error_kinds: Default::default(),
+            conflicts: Default::default(),
+            bytes: Default::default(),
             force: Default::default(),
+            scans,
+            elapsed: Default::default(),
+        }
+    }
+
+    /// Operations per second, over the wall-clock time of the phase.
+    pub fn to_throughput(&self) -> u128 {
+        match self.elapsed.as_nanos() {
+            0 => 0,
+            elapsed => ((self.count as u128) * 1_000_000_000) / elapsed,
+        }
+    }
+
+    /// Keys looked up per second by multi_get, derived from the mean
+    /// batch size and the mean latency of a batch.
+    pub fn to_key_throughput(&self) -> u128 {
+        let keys = (self.hit + self.missed) as u128;
+        match self.latency.to_mean() {
+            0 => 0,
+            mean => (keys * 1_000_000_000) / ((self.count as u128) * mean),
         }
     }
 
     fn merge(&mut self, other: &Self) {
         self.count += other.count;
-        self.items += other.items;
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.hit += other.hit;
+        self.missed += other.missed;
+        self.items_scanned += other.items_scanned;
+        self.errors += other.errors;
+        for (kind, n) in other.error_kinds.iter() {
+            *self.error_kinds.entry(kind.clone()).or_insert(0) += n;
+        }
+        self.conflicts += other.conflicts;
+        self.bytes += other.bytes;
         self.latency.merge(&other.latency);
+        // merged stats are either from concurrent threads, or from
+        // phases applying different ops, wall-clock is the longest.
+        self.elapsed = std::cmp::max(self.elapsed, other.elapsed);
+        match (&mut self.scans, &other.scans) {
+            (Some(scans), Some(other)) => scans.merge(other),
+            _ => (),
+        }
     }
 
     #[inline]
@@ -36,46 +112,138 @@ impl Op {
         }
     }
 
+    /// Whether latency is sampled for the op started last.
     #[inline]
-    pub fn sample_end(&mut self, items: usize) {
-        if self.force || (self.count % 8) == 0 {
+    pub fn is_sampled(&self) -> bool {
+        self.force || (self.count % 8) == 0
+    }
+
+    #[inline]
+    fn sample_end(&mut self) {
+        if self.is_sampled() {
             self.latency.stop();
         }
-        self.items += items;
         self.force = false;
     }
 
+    /// End a write sample, that either updated an existing entry or
+    /// inserted a new entry.
+    #[inline]
+    pub fn sample_write(&mut self, updated: bool) {
+        self.sample_end();
+        if updated {
+            self.updated += 1;
+        } else {
+            self.inserted += 1;
+        }
+    }
+
+    /// End a lookup sample, that either found the key or missed it.
+    #[inline]
+    pub fn sample_read(&mut self, found: bool) {
+        self.sample_end();
+        if found {
+            self.hit += 1;
+        } else {
+            self.missed += 1;
+        }
+    }
+
+    /// End a batch lookup sample, that found `hits` out of `keys`.
+    #[inline]
+    pub fn sample_batch(&mut self, hits: usize, keys: usize) {
+        self.sample_end();
+        self.hit += hits;
+        self.missed += keys - hits;
+        if let Some(scans) = &mut self.scans {
+            scans.sample(keys);
+        }
+    }
+
+    /// End a scan sample, that visited `items` entries.
+    #[inline]
+    pub fn sample_scan(&mut self, items: usize) {
+        self.sample_end();
+        self.items_scanned += items;
+        if let Some(scans) = &mut self.scans {
+            scans.sample(items);
+        }
+    }
+
+    /// End a conditional write sample, that lost a compare-and-set race
+    /// with another writer, neither a hit nor a miss.
+    #[inline]
+    pub fn sample_conflict(&mut self) {
+        self.sample_end();
+        self.conflicts += 1;
+    }
+
+    /// End a failed sample, return the error as is.
+    #[inline]
+    pub fn sample_error(&mut self, err: String) -> String {
+        self.sample_end();
+        self.errors += 1;
+        *self.error_kinds.entry(to_error_kind(&err)).or_insert(0) += 1;
+        err
+    }
+
     pub fn to_json(&self) -> String {
         if self.count == 0 {
             return "".to_string();
         }
-        match self.name.as_str() {
-            "load" => format!(
-                r#""load": {{ "ops": {}, "updates": {}, "latency": {}}}"#,
-                self.count, self.items, self.latency
-            ),
-            "set" => format!(
-                r#""set": {{ "ops": {}, "updates": {}, "latency": {}}}"#,
-                self.count, self.items, self.latency
-            ),
-            "delete" => format!(
-                r#""delete": {{ "ops": {}, "updates": {}, "latency": {}}}"#,
-                self.count, self.items, self.latency
-            ),
-            "get" => format!(
-                r#""get": {{ "ops": {}, "updates": {}, "latency": {}}}"#,
-                self.count, self.items, self.latency
-            ),
-            "range" => format!(
-                r#""range": {{ "ops": {}, "updates": {}, "latency": {}}}"#,
-                self.count, self.items, self.latency
-            ),
-            "reverse" => format!(
-                r#""reverse": {{ "ops": {}, "updates": {}, "latency": {}}}"#,
-                self.count, self.items, self.latency
-            ),
-            _ => unreachable!(),
+        let mut fields = vec![
+            format!(r#""ops": {}"#, self.count),
+            format!(r#""inserted": {}"#, self.inserted),
+            format!(r#""updated": {}"#, self.updated),
+            format!(r#""hit": {}"#, self.hit),
+            format!(r#""missed": {}"#, self.missed),
+            format!(r#""items_scanned": {}"#, self.items_scanned),
+            format!(r#""errors": {}"#, self.errors),
+        ];
+        if self.error_kinds.len() > 0 {
+            let kinds: Vec<String> = self
+                .error_kinds
+                .iter()
+                .map(|(kind, n)| format!(r#""{}": {}"#, kind, n))
+                .collect();
+            fields.push(format!(r#""error_kinds": {{ {} }}"#, kinds.join(", ")));
+        }
+        if self.conflicts > 0 {
+            fields.push(format!(r#""conflicts": {}"#, self.conflicts));
+        }
+        if self.name == "multi_get" {
+            fields.push(format!(r#""key_throughput": {}"#, self.to_key_throughput()));
         }
+        fields.push(format!(r#""throughput": {}"#, self.to_throughput()));
+        fields.push(format!(r#""latency": {}"#, self.latency));
+        format!(r#""{}": {{ {}}}"#, self.name, fields.join(", "))
+    }
+
+    fn fmt_counters(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} = {{ ops={}, inserted={}, updated={}, hit={}, missed={}, items_scanned={}, errors={}",
+            self.name,
+            self.count,
+            self.inserted,
+            self.updated,
+            self.hit,
+            self.missed,
+            self.items_scanned,
+            self.errors
+        )?;
+        if self.error_kinds.len() > 0 {
+            let kinds: Vec<String> = self
+                .error_kinds
+                .iter()
+                .map(|(kind, n)| format!(r#""{}"={}"#, kind, n))
+                .collect();
+            write!(f, ", error_kinds={{ {} }}", kinds.join(", "))?;
+        }
+        if self.conflicts > 0 {
+            write!(f, ", conflicts={}", self.conflicts)?;
+        }
+        Ok(())
     }
 }
 
@@ -85,24 +253,16 @@ impl fmt::Display for Op {
             return Ok(());
         }
 
-        match self.name.as_str() {
-            "load" | "set" => write!(
-                f,
-                "{} = {{ ops={}, updates={}",
-                self.name, self.count, self.items
-            )?,
-            "delete" | "get" => write!(
-                f,
-                "{} = {{ ops={}, missing={}",
-                self.name, self.count, self.items
-            )?,
-            "range" | "reverse" => write!(
-                f,
-                "{} = {{ ops={}, items={}",
-                self.name, self.count, self.items
-            )?,
-            _ => unreachable!(),
-        };
+        self.fmt_counters(f)?;
+        if let Some(scans) = &self.scans {
+            write!(f, ", scans={}", scans)?;
+        }
+        if self.name == "multi_get" {
+            write!(f, ", key_throughput={}", self.to_key_throughput())?;
+        }
+        if self.elapsed.as_nanos() > 0 {
+            write!(f, ", throughput={}", self.to_throughput())?;
+        }
         if self.latency.to_samples() > 0 {
             write!(f, ", latency={} }}", self.latency)
         } else {
@@ -117,35 +277,176 @@ impl fmt::Debug for Op {
             return Ok(());
         }
 
-        match self.name.as_str() {
-            "load" | "set" => write!(
-                f,
-                "{} = {{ ops={}, updates={} }}\n",
-                self.name, self.count, self.items,
-            )?,
-            "delete" | "get" => write!(
-                f,
-                "{} = {{ ops={}, missing={} }}\n",
-                self.name, self.count, self.items,
-            )?,
-            "range" | "reverse" => write!(
-                f,
-                "{} = {{ ops={}, items={} }}\n",
-                self.name, self.count, self.items,
-            )?,
-            _ => unreachable!(),
+        self.fmt_counters(f)?;
+        if self.name == "multi_get" {
+            write!(f, ", key_throughput={}/s", self.to_key_throughput())?;
+        }
+        if self.elapsed.as_nanos() > 0 {
+            write!(f, ", throughput={}/s", self.to_throughput())?;
+        }
+        write!(f, " }}\n")?;
+        if let Some(scans) = &self.scans {
+            write!(f, "{:?}\n", scans)?;
         }
         write!(f, "{:?}", self.latency)
     }
 }
 
+// Error kind is the adapter prefix, if any, and the leading identifier
+// of the error, like "lmdb:MDB_MAP_FULL" or "rdms:InvalidCAS".
+fn to_error_kind(err: &str) -> String {
+    let is_ident = |ch: char| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-';
+    let leading = |s: &str| -> String { s.trim().chars().take_while(|ch| is_ident(*ch)).collect() };
+    let kind = match err.find(": ") {
+        Some(n) if err[..n].chars().all(is_ident) => {
+            format!("{}:{}", &err[..n], leading(&err[n + 2..]))
+        }
+        _ => leading(err),
+    };
+    match kind.as_str() {
+        "" => "other".to_string(),
+        _ => kind,
+    }
+}
+
+/// Distribution of items scanned by range and scan operations, and of
+/// keys looked up by multi_get operations, in power-of-two buckets.
+/// Percentiles are reported as the upper limit of the bucket.
+pub struct Scans {
+    name: String,
+    samples: usize,
+    total: usize,
+    min: usize,
+    max: usize,
+    buckets: Vec<usize>,
+}
+
+impl Scans {
+    pub fn new(name: &str) -> Scans {
+        let mut buckets = Vec::with_capacity(65);
+        buckets.resize(buckets.capacity(), 0);
+        Scans {
+            name: name.to_string(),
+            samples: Default::default(),
+            total: Default::default(),
+            min: std::usize::MAX,
+            max: std::usize::MIN,
+            buckets,
+        }
+    }
+
+    pub fn sample(&mut self, items: usize) {
+        self.samples += 1;
+        self.total += items;
+        self.min = std::cmp::min(self.min, items);
+        self.max = std::cmp::max(self.max, items);
+        let bucket = 64 - (items as u64).leading_zeros() as usize;
+        self.buckets[bucket] += 1;
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.samples += other.samples;
+        self.total += other.total;
+        self.min = std::cmp::min(self.min, other.min);
+        self.max = std::cmp::max(self.max, other.max);
+        self.buckets
+            .iter_mut()
+            .zip(other.buckets.iter())
+            .for_each(|(x, y)| *x = *x + *y);
+    }
+
+    pub fn to_mean(&self) -> usize {
+        if self.samples > 0 {
+            self.total / self.samples
+        } else {
+            0
+        }
+    }
+
+    pub fn to_percentiles(&self) -> Vec<(u8, usize)> {
+        let mut percentiles: Vec<(u8, usize)> = vec![];
+        let (mut bucket, mut acc) = (0, self.buckets[0]);
+        for perc in [50_u8, 90, 99, 100].iter() {
+            let want = (self.samples * (*perc as usize) + 99) / 100;
+            while acc < want && bucket < (self.buckets.len() - 1) {
+                bucket += 1;
+                acc += self.buckets[bucket];
+            }
+            let limit = std::cmp::min((1_u128 << bucket) - 1, self.max as u128);
+            percentiles.push((*perc, limit as usize));
+        }
+        percentiles
+    }
+}
+
+impl fmt::Display for Scans {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if self.samples == 0 {
+            return write!(f, "{{}}");
+        }
+
+        let props: Vec<String> = self
+            .to_percentiles()
+            .into_iter()
+            .map(|(perc, items)| format!(r#""{}"={}"#, perc, items))
+            .collect();
+        write!(
+            f,
+            "{{ min={}, mean={}, max={}, percentiles={{ {} }} }}",
+            self.min,
+            self.to_mean(),
+            self.max,
+            props.join(", ")
+        )
+    }
+}
+
+impl fmt::Debug for Scans {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let props: Vec<String> = self
+            .to_percentiles()
+            .into_iter()
+            .map(|(perc, items)| format!(r#""{}"={}"#, perc, items))
+            .collect();
+        write!(
+            f,
+            "{}.scans = {{ n={}, min={}, mean={}, max={} }}\n",
+            self.name,
+            self.samples,
+            std::cmp::min(self.min, self.max),
+            self.to_mean(),
+            self.max
+        )?;
+        write!(
+            f,
+            "{}.scans.percentiles = {{ {} }}",
+            self.name,
+            props.join(", ")
+        )
+    }
+}
+
 pub struct Ops {
     pub load: Op,
     pub set: Op,
+    pub insert: Op,
+    pub update: Op,
     pub delete: Op,
+    pub rmw: Op,
+    pub set_if_absent: Op,
+    pub set_if_present: Op,
     pub get: Op,
+    pub multi_get: Op,
     pub range: Op,
     pub reverse: Op,
+    pub scan: Op,
+    pub reverse_scan: Op,
+    pub seek: Op,
+    pub iter: Op,
+    // commands dispatched, and time spent outside the index calls,
+    // refer [Ops::set_dispatch].
+    cmds: usize,
+    dispatch: Duration,
 }
 
 impl Ops {
@@ -153,38 +454,172 @@ impl Ops {
         Ops {
             load: Op::new("load"),
             set: Op::new("set"),
+            insert: Op::new("insert"),
+            update: Op::new("update"),
             delete: Op::new("delete"),
+            rmw: Op::new("rmw"),
+            set_if_absent: Op::new("set_if_absent"),
+            set_if_present: Op::new("set_if_present"),
             get: Op::new("get"),
+            multi_get: Op::new("multi_get"),
             range: Op::new("range"),
             reverse: Op::new("reverse"),
+            scan: Op::new("scan"),
+            reverse_scan: Op::new("reverse_scan"),
+            seek: Op::new("seek"),
+            iter: Op::new("iter"),
+            cmds: Default::default(),
+            dispatch: Default::default(),
         }
     }
 
     pub fn to_total_reads(&self) -> usize {
-        self.get.count + self.range.count + self.reverse.count
+        self.get.count
+            + self.multi_get.count
+            + self.range.count
+            + self.reverse.count
+            + self.scan.count
+            + self.reverse_scan.count
+    }
+
+    /// Number of new entries created by all the write ops.
+    pub fn to_total_inserted(&self) -> usize {
+        self.to_ops().iter().map(|op| op.inserted).sum()
+    }
+
+    /// Logical bytes written by all the write ops.
+    pub fn to_total_bytes(&self) -> usize {
+        self.to_ops().iter().map(|op| op.bytes).sum()
+    }
+
+    pub fn to_total_errors(&self) -> usize {
+        self.to_ops().iter().map(|op| op.errors).sum()
     }
 
     pub fn to_total_writes(&self) -> usize {
-        self.load.count + self.set.count + self.delete.count
+        self.load.count
+            + self.set.count
+            + self.insert.count
+            + self.update.count
+            + self.delete.count
+            + self.rmw.count
+            + self.set_if_absent.count
+            + self.set_if_present.count
     }
 
     pub fn is_sec_elapsed(&self) -> bool {
         let mut elapsed = self.load.latency.elapsed() * 8;
         elapsed += self.set.latency.elapsed() * 8;
+        elapsed += self.insert.latency.elapsed() * 8;
+        elapsed += self.update.latency.elapsed() * 8;
         elapsed += self.delete.latency.elapsed() * 8;
+        elapsed += self.rmw.latency.elapsed() * 8;
+        elapsed += self.set_if_absent.latency.elapsed() * 8;
+        elapsed += self.set_if_present.latency.elapsed() * 8;
         elapsed += self.get.latency.elapsed() * 8;
+        elapsed += self.multi_get.latency.elapsed() * 8;
         elapsed += self.range.latency.elapsed() * 8;
         elapsed += self.reverse.latency.elapsed() * 8;
+        elapsed += self.scan.latency.elapsed() * 8;
+        elapsed += self.reverse_scan.latency.elapsed() * 8;
         elapsed > 1_000_000_000
     }
 
     pub fn merge(&mut self, other: &Self) {
         self.load.merge(&other.load);
         self.set.merge(&other.set);
+        self.insert.merge(&other.insert);
+        self.update.merge(&other.update);
         self.delete.merge(&other.delete);
+        self.rmw.merge(&other.rmw);
+        self.set_if_absent.merge(&other.set_if_absent);
+        self.set_if_present.merge(&other.set_if_present);
         self.get.merge(&other.get);
+        self.multi_get.merge(&other.multi_get);
         self.range.merge(&other.range);
         self.reverse.merge(&other.reverse);
+        self.scan.merge(&other.scan);
+        self.reverse_scan.merge(&other.reverse_scan);
+        self.seek.merge(&other.seek);
+        self.iter.merge(&other.iter);
+        self.cmds += other.cmds;
+        self.dispatch += other.dispatch;
+    }
+
+    /// Account `cmds` commands applied in `elapsed` time. Time spent in
+    /// the index calls is estimated from the sampled latencies, rest of
+    /// it is the harness, generator, command dispatch and stats.
+    pub fn set_dispatch(&mut self, cmds: usize, elapsed: Duration) {
+        // seek is timed within scans.
+        let index: u128 = self
+            .to_ops()
+            .into_iter()
+            .filter(|op| op.name != "seek" && op.latency.to_samples() > 0)
+            .map(|op| op.latency.to_mean() * (op.count as u128))
+            .sum();
+        let dispatch = elapsed.as_nanos().saturating_sub(index);
+        self.cmds += cmds;
+        self.dispatch += Duration::from_nanos(dispatch as u64);
+    }
+
+    /// Subtract harness overhead from the latency of every op.
+    pub fn calibrate(&mut self, overhead: &Overhead) {
+        for op in self.to_mut_ops().into_iter() {
+            op.latency.calibrate(overhead.to_op_overhead(&op.name));
+        }
+    }
+
+    /// Set wall-clock time for all the ops applied in a phase, or thread,
+    /// for computing throughput.
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        for op in self.to_mut_ops().into_iter() {
+            if op.count > 0 {
+                op.elapsed = elapsed;
+            }
+        }
+    }
+
+    /// Return all ops, in the same order as they are reported.
+    pub fn to_ops(&self) -> Vec<&Op> {
+        vec![
+            &self.load,
+            &self.set,
+            &self.insert,
+            &self.update,
+            &self.delete,
+            &self.rmw,
+            &self.set_if_absent,
+            &self.set_if_present,
+            &self.get,
+            &self.multi_get,
+            &self.range,
+            &self.reverse,
+            &self.scan,
+            &self.reverse_scan,
+            &self.seek,
+            &self.iter,
+        ]
+    }
+
+    fn to_mut_ops(&mut self) -> Vec<&mut Op> {
+        vec![
+            &mut self.load,
+            &mut self.set,
+            &mut self.insert,
+            &mut self.update,
+            &mut self.delete,
+            &mut self.rmw,
+            &mut self.set_if_absent,
+            &mut self.set_if_present,
+            &mut self.get,
+            &mut self.multi_get,
+            &mut self.range,
+            &mut self.reverse,
+            &mut self.scan,
+            &mut self.reverse_scan,
+            &mut self.seek,
+            &mut self.iter,
+        ]
     }
 
     #[allow(dead_code)] // TODO: remove this once ixperf stabilizes.
@@ -192,10 +627,20 @@ impl Ops {
         let strs = [
             self.load.to_json(),
             self.set.to_json(),
+            self.insert.to_json(),
+            self.update.to_json(),
             self.delete.to_json(),
+            self.rmw.to_json(),
+            self.set_if_absent.to_json(),
+            self.set_if_present.to_json(),
             self.get.to_json(),
+            self.multi_get.to_json(),
             self.range.to_json(),
             self.reverse.to_json(),
+            self.scan.to_json(),
+            self.reverse_scan.to_json(),
+            self.seek.to_json(),
+            self.iter.to_json(),
         ];
         let strs: Vec<String> = strs
             .iter()
@@ -216,10 +661,20 @@ impl fmt::Display for Ops {
         let items: Vec<String> = [
             &self.load,
             &self.set,
+            &self.insert,
+            &self.update,
             &self.delete,
+            &self.rmw,
+            &self.set_if_absent,
+            &self.set_if_present,
             &self.get,
+            &self.multi_get,
             &self.range,
             &self.reverse,
+            &self.scan,
+            &self.reverse_scan,
+            &self.seek,
+            &self.iter,
         ]
         .iter()
         .filter_map(|item| {
@@ -243,18 +698,106 @@ impl fmt::Debug for Ops {
         if self.set.count > 0 {
             lines.push(format!("{:?}", self.set));
         }
+        if self.insert.count > 0 {
+            lines.push(format!("{:?}", self.insert));
+        }
+        if self.update.count > 0 {
+            lines.push(format!("{:?}", self.update));
+        }
         if self.delete.count > 0 {
             lines.push(format!("{:?}", self.delete));
         }
+        if self.rmw.count > 0 {
+            lines.push(format!("{:?}", self.rmw));
+        }
+        if self.set_if_absent.count > 0 {
+            lines.push(format!("{:?}", self.set_if_absent));
+        }
+        if self.set_if_present.count > 0 {
+            lines.push(format!("{:?}", self.set_if_present));
+        }
         if self.get.count > 0 {
             lines.push(format!("{:?}", self.get));
         }
+        if self.multi_get.count > 0 {
+            lines.push(format!("{:?}", self.multi_get));
+        }
         if self.range.count > 0 {
             lines.push(format!("{:?}", self.range));
         }
         if self.reverse.count > 0 {
             lines.push(format!("{:?}", self.reverse));
         }
+        if self.scan.count > 0 {
+            lines.push(format!("{:?}", self.scan));
+        }
+        if self.reverse_scan.count > 0 {
+            lines.push(format!("{:?}", self.reverse_scan));
+        }
+        if self.seek.count > 0 {
+            lines.push(format!("{:?}", self.seek));
+        }
+        if self.iter.count > 0 {
+            lines.push(format!("{:?}", self.iter));
+        }
         write!(f, "{}", lines.join("\n"))
     }
 }
+
+/// Harness overhead measured by running the same profile against the
+/// null index. Mean latency of every op, in nanoseconds, is subtracted
+/// from the latency samples, and the mean time to generate and dispatch
+/// a command is subtracted from the elapsed time of a phase, hence both
+/// the percentiles and the throughput are calibrated.
+#[derive(Default, Clone)]
+pub struct Overhead {
+    ops: Vec<(String, u128)>,
+    dispatch: u128,
+}
+
+impl Overhead {
+    pub fn new(ops: &Ops) -> Overhead {
+        let iter = ops.to_ops().into_iter();
+        let ops_overhead = iter
+            .filter(|op| op.latency.to_samples() > 0)
+            .map(|op| (op.name.clone(), op.latency.to_mean()))
+            .collect();
+        let dispatch = match ops.cmds {
+            0 => 0,
+            cmds => ops.dispatch.as_nanos() / (cmds as u128),
+        };
+        Overhead {
+            ops: ops_overhead,
+            dispatch,
+        }
+    }
+
+    fn to_op_overhead(&self, name: &str) -> u128 {
+        let item = self.ops.iter().find(|(op, _)| op == name);
+        item.map(|(_, overhead)| *overhead).unwrap_or(0)
+    }
+
+    /// Return `elapsed` time, for applying `cmds` commands, with the
+    /// dispatch overhead subtracted.
+    pub fn to_elapsed(&self, cmds: usize, elapsed: Duration) -> Duration {
+        let overhead = self.dispatch * (cmds as u128);
+        let elapsed = elapsed.as_nanos().saturating_sub(overhead);
+        Duration::from_nanos(elapsed as u64)
+    }
+}
+
+impl fmt::Debug for Overhead {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let mut lines: Vec<String> = self
+            .ops
+            .iter()
+            .map(|(name, overhead)| format!("{}.overhead = {}", name, overhead))
+            .collect();
+        lines.push(format!("dispatch.overhead = {}", self.dispatch));
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+#[path = "stats_test.rs"]
+mod stats_test;
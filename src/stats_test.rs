@@ -0,0 +1,66 @@
+use super::*;
+
+#[test]
+fn test_to_error_kind() {
+    let testcases = vec![
+        (
+            "lmdb: MDB_MAP_FULL: mapsize limit reached",
+            "lmdb:MDB_MAP_FULL",
+        ),
+        ("rdms: InvalidCAS(10)", "rdms:InvalidCAS"),
+        ("external op:4 key not found", "external"),
+        ("write-only index", "write-only"),
+        ("aborting after 3 errors, lmdb: MDB_MAP_FULL", "aborting"),
+        ("  ", "other"),
+        ("", "other"),
+    ];
+    for (err, kind) in testcases.into_iter() {
+        assert_eq!(to_error_kind(err), kind, "{:?}", err);
+    }
+
+    let mut op = Op::new("set");
+    op.sample_start(true);
+    op.sample_error("lmdb: MDB_MAP_FULL".to_string());
+    op.sample_start(true);
+    op.sample_error("lmdb: MDB_MAP_FULL".to_string());
+    op.sample_start(true);
+    op.sample_error("rdms: InvalidCAS(10)".to_string());
+    assert_eq!(op.errors, 3);
+    let kinds: Vec<(&str, usize)> = op
+        .error_kinds
+        .iter()
+        .map(|(kind, n)| (kind.as_str(), *n))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![("lmdb:MDB_MAP_FULL", 2), ("rdms:InvalidCAS", 1)]
+    );
+}
+
+#[test]
+fn test_scans_percentiles() {
+    let mut scans = Scans::new("range");
+    assert_eq!(
+        scans.to_percentiles(),
+        vec![(50, 0), (90, 0), (99, 0), (100, 0)]
+    );
+
+    // percentiles are the upper limit of power-of-two buckets, capped by
+    // the largest sample.
+    for items in 0..100 {
+        scans.sample(items);
+    }
+    assert_eq!(scans.to_mean(), 49);
+    assert_eq!(
+        scans.to_percentiles(),
+        vec![(50, 63), (90, 99), (99, 99), (100, 99)]
+    );
+
+    let mut other = Scans::new("range");
+    other.sample(1000);
+    scans.merge(&other);
+    assert_eq!(
+        scans.to_percentiles(),
+        vec![(50, 63), (90, 127), (99, 127), (100, 1000)]
+    );
+}
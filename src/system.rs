@@ -0,0 +1,168 @@
+//! Process stats, sampled periodically by the system thread and logged
+//! as `system periodic-stats`. CPU load and rss are sampled with sysinfo
+//! for the current pid, rest of the stats are read from `/proc/self`,
+//! hence available only on Linux. For disk based indexes, size of the
+//! index directory is also sampled, refer [crate::amplify].
+
+use sysinfo::{ProcessExt, SystemExt};
+
+use std::ffi;
+
+use crate::amplify;
+
+/// Snapshot of process stats. Memory is in MB. Faults, context switches
+/// and io bytes are cumulative since the process started.
+#[derive(Default, Clone, Debug)]
+pub struct ProcStats {
+    pub cpu_load: f32,
+    pub mem_rss: u64,
+    pub mem_virt: u64,
+    pub threads: u64,
+    pub minor_faults: u64,
+    pub major_faults: u64,
+    pub voluntary_ctxt_switches: u64,
+    pub involuntary_ctxt_switches: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub index_dir_size: Option<u64>,
+}
+
+impl ProcStats {
+    /// Format stats as a toml line, parsed back by `plot`.
+    pub fn to_toml_line(&self) -> String {
+        let line = format!(
+            concat!(
+                "system = {{ cpu_load={:.2}, mem_rss={}, mem_virt={}, ",
+                "threads={}, minor_faults={}, major_faults={}, ",
+                "voluntary_ctxt_switches={}, involuntary_ctxt_switches={}, ",
+                "read_bytes={}, write_bytes={}"
+            ),
+            self.cpu_load,
+            self.mem_rss,
+            self.mem_virt,
+            self.threads,
+            self.minor_faults,
+            self.major_faults,
+            self.voluntary_ctxt_switches,
+            self.involuntary_ctxt_switches,
+            self.read_bytes,
+            self.write_bytes,
+        );
+        match self.index_dir_size {
+            Some(size) => format!("{}, index_dir_size={} }}", line, size),
+            None => line + " }",
+        }
+    }
+}
+
+/// Sample stats for the current process.
+pub struct Sampler {
+    sys: sysinfo::System,
+    pid: sysinfo::Pid,
+    dir: Option<ffi::OsString>,
+}
+
+impl Sampler {
+    /// Sample stats for the current process, and the size of index
+    /// directory `dir`, if supplied.
+    pub fn new(dir: Option<ffi::OsString>) -> Result<Sampler, String> {
+        let pid = sysinfo::get_current_pid().map_err(|e| e.to_string())?;
+        Ok(Sampler {
+            sys: sysinfo::System::new(),
+            pid,
+            dir,
+        })
+    }
+
+    pub fn sample(&mut self) -> Result<ProcStats, String> {
+        let mut stats: ProcStats = Default::default();
+
+        self.sys.refresh_process(self.pid);
+        match self.sys.get_process(self.pid) {
+            Some(p) => {
+                stats.cpu_load = p.cpu_usage();
+                stats.mem_rss = p.memory() / 1024;
+            }
+            None => return Err(format!("process {} not found", self.pid)),
+        }
+
+        read_proc(&mut stats)?;
+        stats.index_dir_size = self.dir.as_ref().map(|dir| amplify::dir_size(dir));
+        Ok(stats)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_proc(stats: &mut ProcStats) -> Result<(), String> {
+    use std::fs;
+
+    let read = |file: &str| fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e));
+
+    // fields after the command name, which is within parenthesis and can
+    // contain spaces, starting with field-3 (state), refer proc(5).
+    let stat = read("/proc/self/stat")?;
+    let fields: Vec<&str> = match stat.rfind(')') {
+        Some(n) => stat[n + 1..].split_whitespace().collect(),
+        None => return Err("invalid /proc/self/stat".to_string()),
+    };
+    let field = |n: usize| -> Result<u64, String> {
+        match fields.get(n - 3).map(|f| f.parse::<u64>()) {
+            Some(Ok(val)) => Ok(val),
+            _ => Err(format!("invalid field {} in /proc/self/stat", n)),
+        }
+    };
+    stats.minor_faults = field(10)?;
+    stats.major_faults = field(12)?;
+    stats.threads = field(20)?;
+    stats.mem_virt = field(23)? / (1024 * 1024);
+
+    // context switches in /proc/self/status are for the main thread,
+    // add them up for all live threads.
+    let (mut voluntary, mut involuntary) = (0, 0);
+    let dir = fs::read_dir("/proc/self/task").map_err(|e| e.to_string())?;
+    for entry in dir.filter_map(|entry| entry.ok()) {
+        // thread might have exited since read_dir.
+        let status = match fs::read_to_string(entry.path().join("status")) {
+            Ok(status) => status,
+            Err(_) => continue,
+        };
+        voluntary += to_value(&status, "voluntary_ctxt_switches:").unwrap_or(0);
+        involuntary += to_value(&status, "nonvoluntary_ctxt_switches:").unwrap_or(0);
+    }
+    stats.voluntary_ctxt_switches = voluntary;
+    stats.involuntary_ctxt_switches = involuntary;
+
+    let (read_bytes, write_bytes) = read_io()?;
+    stats.read_bytes = read_bytes;
+    stats.write_bytes = write_bytes;
+
+    Ok(())
+}
+
+/// Return bytes read from, and written to, the storage layer by the
+/// process, so far.
+#[cfg(target_os = "linux")]
+pub fn read_io() -> Result<(u64, u64), String> {
+    let io =
+        std::fs::read_to_string("/proc/self/io").map_err(|e| format!("/proc/self/io: {}", e))?;
+    let read_bytes = to_value(&io, "read_bytes:").unwrap_or(0);
+    let write_bytes = to_value(&io, "write_bytes:").unwrap_or(0);
+    Ok((read_bytes, write_bytes))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_io() -> Result<(u64, u64), String> {
+    Err("process io is supported only on linux".to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_proc(_stats: &mut ProcStats) -> Result<(), String> {
+    Ok(())
+}
+
+// value for `key: value` line in /proc files.
+#[cfg(target_os = "linux")]
+fn to_value(text: &str, key: &str) -> Option<u64> {
+    let line = text.lines().find(|l| l.starts_with(key))?;
+    line[key.len()..].trim().parse().ok()
+}
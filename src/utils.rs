@@ -1,3 +1,5 @@
+use log::info;
+
 use std::convert::TryInto;
 
 use toml;
@@ -16,10 +18,31 @@ pub fn toml_to_u128(val: &toml::Value) -> u128 {
         .map_or(Default::default(), |x| x.try_into().unwrap())
 }
 
+pub fn toml_to_f64(val: &toml::Value) -> f64 {
+    match val {
+        toml::Value::Integer(x) => *x as f64,
+        val => val.as_float().map_or(Default::default(), |x| x),
+    }
+}
+
 pub fn toml_to_string(val: &toml::Value) -> String {
     val.as_str().map_or(Default::default(), |x| x).to_string()
 }
 
+/// Log memory estimated for an in-memory index holding `entries`, as
+/// `structure` bytes for nodes or buckets, including the keys and values
+/// held inline, and `heap` bytes owned by keys and values, like Vec<u8>.
+pub fn log_memory(name: &str, entries: usize, structure: usize, heap: usize) {
+    if entries == 0 {
+        return;
+    }
+    info!(
+        target: "ixperf",
+        "{} memory entries:{} structure:{} heap:{} bytes, {} bytes per entry",
+        name, entries, structure, heap, (structure + heap) / entries
+    );
+}
+
 #[macro_export]
 macro_rules! stats {
     ($o:expr, $target:expr, $($arg:tt),+) => {
@@ -0,0 +1,399 @@
+//! Verify an index against a shadow BTreeMap.
+//!
+//! [Verify] wraps an index adapter, every write is applied to both the
+//! index and the shadow, and every read result from the index, including
+//! the final full table scan, is compared with the shadow. Divergence is
+//! returned as error, along with the offending command and key.
+//!
+//! Only the values visible to readers are compared. Return values of
+//! writes, and the count of entries returned by range and reverse,
+//! can differ across indexes, say for lsm and sticky modes, hence they
+//! are not compared.
+
+use std::{cell::RefCell, collections::BTreeMap, fmt, ops::Bound, rc::Rc};
+
+use crate::adapter::{self, IndexAdapter, ReaderAdapter, Visit, WriterAdapter};
+
+pub struct Verify<'a, I, K, V> {
+    index: &'a mut I,
+    shadow: Rc<RefCell<BTreeMap<K, V>>>,
+}
+
+impl<'a, I, K, V> Verify<'a, I, K, V>
+where
+    I: IndexAdapter<K, V>,
+    K: Clone + Ord + fmt::Debug,
+    V: Clone + PartialEq + fmt::Debug,
+{
+    pub fn new(index: &'a mut I) -> Verify<'a, I, K, V> {
+        Verify {
+            index,
+            shadow: Rc::new(RefCell::new(BTreeMap::new())),
+        }
+    }
+
+    /// Compare the full table scan of index with the shadow.
+    pub fn validate(&mut self) -> Result<usize, String> {
+        let mut r = self.to_reader()?;
+        let mut entries = vec![];
+        {
+            let mut visit = |key: &K, value: &V| entries.push((key.clone(), value.clone()));
+            r.inner.iter(Some(&mut visit))?;
+        }
+
+        let shadow = self.shadow.borrow();
+        let what = "verify iter".to_string();
+        compare(what, NAMES, &entries, shadow.iter().collect())?;
+        Ok(entries.len())
+    }
+}
+
+impl<'a, I, K, V> IndexAdapter<K, V> for Verify<'a, I, K, V>
+where
+    I: IndexAdapter<K, V>,
+    K: Clone + Ord + fmt::Debug,
+    V: Clone + PartialEq + fmt::Debug,
+{
+    type R = VerifyReader<I::R, K, V>;
+    type W = VerifyWriter<I::W, K, V>;
+
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        Ok(VerifyReader {
+            inner: self.index.to_reader()?,
+            shadow: Rc::clone(&self.shadow),
+        })
+    }
+
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        Ok(VerifyWriter {
+            inner: self.index.to_writer()?,
+            shadow: Rc::clone(&self.shadow),
+        })
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        self.index.len()
+    }
+}
+
+pub struct VerifyReader<R, K, V> {
+    inner: R,
+    shadow: Rc<RefCell<BTreeMap<K, V>>>,
+}
+
+impl<R, K, V> VerifyReader<R, K, V>
+where
+    R: ReaderAdapter<K, V>,
+    K: Clone + Ord + fmt::Debug,
+    V: Clone + PartialEq + fmt::Debug,
+{
+    fn scan(
+        &mut self,
+        reverse: bool,
+        low: Bound<K>,
+        high: Bound<K>,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let (n, entries) = {
+            let mut entries = vec![];
+            let mut collect = |key: &K, value: &V| {
+                entries.push((key.clone(), value.clone()));
+                adapter::do_visit(&mut visit, key, value);
+            };
+            let n = if reverse {
+                let (l, h) = (low.clone(), high.clone());
+                self.inner.reverse(l, h, Some(&mut collect))?
+            } else {
+                let (l, h) = (low.clone(), high.clone());
+                self.inner.range(l, h, Some(&mut collect))?
+            };
+            (n, entries)
+        };
+
+        let shadow = self.shadow.borrow();
+        let what = format!(
+            "verify {} low:{:?} high:{:?}",
+            if reverse { "reverse" } else { "range" },
+            low,
+            high
+        );
+        let expected = if adapter::is_empty_range(&low, &high) {
+            vec![]
+        } else if reverse {
+            shadow.range((low, high)).rev().collect()
+        } else {
+            shadow.range((low, high)).collect()
+        };
+        compare(what, NAMES, &entries, expected)?;
+        Ok(n)
+    }
+
+    fn scan_limit(
+        &mut self,
+        reverse: bool,
+        start: &K,
+        limit: usize,
+        mut visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        let (n, entries) = {
+            let mut entries = vec![];
+            let mut collect = |key: &K, value: &V| {
+                entries.push((key.clone(), value.clone()));
+                adapter::do_visit(&mut visit, key, value);
+            };
+            let n = if reverse {
+                self.inner.reverse_scan(start, limit, Some(&mut collect))?
+            } else {
+                self.inner.scan(start, limit, Some(&mut collect))?
+            };
+            (n, entries)
+        };
+
+        let shadow = self.shadow.borrow();
+        let what = format!(
+            "verify {} start:{:?} limit:{}",
+            if reverse { "reverse_scan" } else { "scan" },
+            start,
+            limit
+        );
+        let expected = if reverse {
+            let range = (Bound::Unbounded, Bound::Included(start));
+            shadow.range::<K, _>(range).rev().take(limit).collect()
+        } else {
+            let range = (Bound::Included(start), Bound::Unbounded);
+            shadow.range::<K, _>(range).take(limit).collect()
+        };
+        compare(what, NAMES, &entries, expected)?;
+        Ok(n)
+    }
+}
+
+impl<R, K, V> ReaderAdapter<K, V> for VerifyReader<R, K, V>
+where
+    R: ReaderAdapter<K, V>,
+    K: Clone + Ord + fmt::Debug,
+    V: Clone + PartialEq + fmt::Debug,
+{
+    fn get(&mut self, key: &K, mut visit: Visit<K, V>) -> Result<bool, String> {
+        let (found, value) = {
+            let mut value = None;
+            let mut collect = |key: &K, val: &V| {
+                value = Some(val.clone());
+                adapter::do_visit(&mut visit, key, val);
+            };
+            let found = self.inner.get(key, Some(&mut collect))?;
+            (found, value)
+        };
+
+        let shadow = self.shadow.borrow();
+        match (value.as_ref(), shadow.get(key)) {
+            (Some(v1), Some(v2)) if v1 == v2 => Ok(found),
+            (None, None) => Ok(found),
+            (v1, v2) => Err(format!(
+                "verify get key:{:?} index:{:?} shadow:{:?}",
+                key, v1, v2
+            )),
+        }
+    }
+
+    // forward the batch, so that adapter's own multi_get is verified.
+    fn multi_get(&mut self, keys: &[K], mut visit: Visit<K, V>) -> Result<usize, String> {
+        let (n, entries) = {
+            let mut entries = vec![];
+            let mut collect = |key: &K, value: &V| {
+                entries.push((key.clone(), value.clone()));
+                adapter::do_visit(&mut visit, key, value);
+            };
+            let n = self.inner.multi_get(keys, Some(&mut collect))?;
+            (n, entries)
+        };
+
+        let shadow = self.shadow.borrow();
+        let expected: Vec<(&K, &V)> = keys
+            .iter()
+            .filter_map(|key| shadow.get(key).map(|value| (key, value)))
+            .collect();
+        if n != expected.len() {
+            let (n0, n1) = NAMES;
+            return Err(format!(
+                "verify multi_get keys:{} {}:{} {}:{}",
+                keys.len(),
+                n0,
+                n,
+                n1,
+                expected.len()
+            ));
+        }
+        let what = format!("verify multi_get keys:{}", keys.len());
+        compare(what, NAMES, &entries, expected)?;
+        Ok(n)
+    }
+
+    fn range(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        self.scan(false /*reverse*/, low, high, visit)
+    }
+
+    fn reverse(
+        &mut self,
+        low: Bound<K>,
+        high: Bound<K>,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        self.scan(true /*reverse*/, low, high, visit)
+    }
+
+    fn scan(&mut self, start: &K, limit: usize, visit: Visit<K, V>) -> Result<usize, String> {
+        self.scan_limit(false /*reverse*/, start, limit, visit)
+    }
+
+    fn reverse_scan(
+        &mut self,
+        start: &K,
+        limit: usize,
+        visit: Visit<K, V>,
+    ) -> Result<usize, String> {
+        self.scan_limit(true /*reverse*/, start, limit, visit)
+    }
+
+    fn iter(&mut self, visit: Visit<K, V>) -> Result<usize, String> {
+        self.inner.iter(visit)
+    }
+}
+
+pub struct VerifyWriter<W, K, V> {
+    inner: W,
+    shadow: Rc<RefCell<BTreeMap<K, V>>>,
+}
+
+impl<W, K, V> WriterAdapter<K, V> for VerifyWriter<W, K, V>
+where
+    W: WriterAdapter<K, V>,
+    K: Clone + Ord + fmt::Debug,
+    V: Clone + PartialEq + fmt::Debug,
+{
+    // shadow is updated only after the index has accepted the write,
+    // failed writes, under continue policy, leave both untouched.
+    fn set(&mut self, key: K, value: V) -> Result<bool, String> {
+        let updated = self.inner.set(key.clone(), value.clone())?;
+        self.shadow.borrow_mut().insert(key, value);
+        Ok(updated)
+    }
+
+    fn delete(&mut self, key: &K) -> Result<bool, String> {
+        let found = self.inner.delete(key)?;
+        self.shadow.borrow_mut().remove(key);
+        Ok(found)
+    }
+
+    fn load(&mut self, key: K, value: V) -> Result<bool, String> {
+        let updated = self.inner.load(key.clone(), value.clone())?;
+        self.shadow.borrow_mut().insert(key, value);
+        Ok(updated)
+    }
+
+    // the value read by the index must match the shadow's value.
+    fn read_modify_write(
+        &mut self,
+        key: K,
+        modify: &mut dyn FnMut(Option<&V>) -> V,
+    ) -> Result<bool, String> {
+        let mut seen = None;
+        let found = self.inner.read_modify_write(key.clone(), &mut |old| {
+            let value = modify(old);
+            seen = Some((old.cloned(), value.clone()));
+            value
+        })?;
+        let (old, value) = seen.ok_or_else(|| format!("verify rmw key:{:?} not modified", key))?;
+
+        let mut shadow = self.shadow.borrow_mut();
+        let expected = shadow.get(&key);
+        if old.as_ref() != expected {
+            let (n0, n1) = NAMES;
+            return Err(format!(
+                "verify rmw key:{:?} {}:{:?} {}:{:?}",
+                key, n0, old, n1, expected
+            ));
+        }
+        shadow.insert(key, value);
+        Ok(found)
+    }
+
+    fn set_if_absent(&mut self, key: K, value: V) -> Result<bool, String> {
+        let ok = self.inner.set_if_absent(key.clone(), value.clone())?;
+        let mut shadow = self.shadow.borrow_mut();
+        check_cond("set_if_absent", &key, ok, !shadow.contains_key(&key))?;
+        if ok {
+            shadow.insert(key, value);
+        }
+        Ok(ok)
+    }
+
+    fn set_if_present(&mut self, key: K, value: V) -> Result<bool, String> {
+        let ok = self.inner.set_if_present(key.clone(), value.clone())?;
+        let mut shadow = self.shadow.borrow_mut();
+        check_cond("set_if_present", &key, ok, shadow.contains_key(&key))?;
+        if ok {
+            shadow.insert(key, value);
+        }
+        Ok(ok)
+    }
+
+    fn commit(&mut self) -> Result<(), String> {
+        self.inner.commit()
+    }
+
+    fn to_conflicts(&mut self) -> usize {
+        self.inner.to_conflicts()
+    }
+}
+
+const NAMES: (&str, &str) = ("index", "shadow");
+
+fn check_cond<K>(what: &str, key: &K, ok: bool, expected: bool) -> Result<(), String>
+where
+    K: fmt::Debug,
+{
+    if ok != expected {
+        let (n0, n1) = NAMES;
+        return Err(format!(
+            "verify {} key:{:?} {}:{} {}:{}",
+            what, key, n0, ok, n1, expected
+        ));
+    }
+    Ok(())
+}
+
+// compare entries returned by index with the entries from shadow, and
+// report the first mismatch.
+fn compare<K, V>(
+    what: String,
+    names: (&str, &str),
+    entries: &[(K, V)],
+    expected: Vec<(&K, &V)>,
+) -> Result<(), String>
+where
+    K: PartialEq + fmt::Debug,
+    V: PartialEq + fmt::Debug,
+{
+    let n = std::cmp::max(entries.len(), expected.len());
+    for i in 0..n {
+        let x = entries.get(i).map(|(k, v)| (k, v));
+        let y = expected.get(i).cloned();
+        if x != y {
+            return Err(format!(
+                "{} at:{} {}:{:?} {}:{:?}",
+                what, i, names.0, x, names.1, y
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "verify_test.rs"]
+mod verify_test;
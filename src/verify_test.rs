@@ -0,0 +1,134 @@
+use std::{cell::RefCell, collections::BTreeMap, ops::Bound, rc::Rc};
+
+use crate::mod_btree_map::BTreeMapIndex;
+
+use super::*;
+
+#[test]
+fn test_verify() {
+    let mut inner = BTreeMapIndex(Rc::new(RefCell::new(BTreeMap::new())));
+    let mut index = Verify::new(&mut inner);
+    let mut w = index.to_writer().unwrap();
+    let mut r = index.to_reader().unwrap();
+
+    for key in 0..100_u64 {
+        w.load(key, key * 10).unwrap();
+    }
+    w.commit().unwrap();
+    assert_eq!(w.set(5, 55).unwrap(), true);
+    assert_eq!(w.delete(&6).unwrap(), true);
+    let mut modify = |old: Option<&u64>| old.map(|v| v + 1).unwrap_or(0);
+    assert_eq!(w.read_modify_write(7, &mut modify).unwrap(), true);
+    assert_eq!(w.set_if_absent(7, 0).unwrap(), false);
+    assert_eq!(w.set_if_present(200, 0).unwrap(), false);
+
+    assert_eq!(r.get(&5, None).unwrap(), true);
+    assert_eq!(r.get(&6, None).unwrap(), false);
+    assert_eq!(r.multi_get(&[5, 6, 7], None).unwrap(), 2);
+    let (low, high) = (Bound::Excluded(3), Bound::Included(8));
+    assert_eq!(r.range(low, high, None).unwrap(), 4);
+    let (low, high) = (Bound::Unbounded, Bound::Excluded(3));
+    assert_eq!(r.reverse(low, high, None).unwrap(), 3);
+    let (low, high) = (Bound::Included(8), Bound::Excluded(8));
+    assert_eq!(r.range(low, high, None).unwrap(), 0);
+    assert_eq!(ReaderAdapter::scan(&mut r, &98, 5, None).unwrap(), 2);
+    assert_eq!(r.reverse_scan(&1, 5, None).unwrap(), 2);
+
+    assert_eq!(index.validate().unwrap(), 99);
+}
+
+#[test]
+fn test_verify_mismatch() {
+    let mut inner = BTreeMapIndex(Rc::new(RefCell::new(BTreeMap::new())));
+    let map = Rc::clone(&inner.0);
+    let mut index = Verify::new(&mut inner);
+    let mut w = index.to_writer().unwrap();
+    let mut r = index.to_reader().unwrap();
+
+    for key in 0..10_u64 {
+        w.set(key, key).unwrap();
+    }
+    // index diverges from the shadow.
+    map.borrow_mut().insert(5, 50);
+    map.borrow_mut().insert(20, 20);
+
+    let err = r.get(&5, None).unwrap_err();
+    assert_eq!(err, "verify get key:5 index:Some(50) shadow:Some(5)");
+    let (low, high) = (Bound::Included(4), Bound::Excluded(6));
+    let err = r.range(low, high, None).unwrap_err();
+    assert_eq!(
+        err,
+        "verify range low:Included(4) high:Excluded(6) at:1 index:Some((5, 50)) shadow:Some((5, 5))"
+    );
+    let err = r.multi_get(&[5, 20], None).unwrap_err();
+    assert_eq!(err, "verify multi_get keys:2 index:2 shadow:1");
+    let err = ReaderAdapter::scan(&mut r, &9, 5, None).unwrap_err();
+    assert_eq!(
+        err,
+        "verify scan start:9 limit:5 at:1 index:Some((20, 20)) shadow:None"
+    );
+
+    let err = w.set_if_present(20, 0).unwrap_err();
+    assert_eq!(err, "verify set_if_present key:20 index:true shadow:false");
+    let mut modify = |old: Option<&u64>| old.cloned().unwrap_or(0);
+    let err = w.read_modify_write(5, &mut modify).unwrap_err();
+    assert_eq!(err, "verify rmw key:5 index:Some(50) shadow:Some(5)");
+
+    let err = index.validate().unwrap_err();
+    assert_eq!(
+        err,
+        "verify iter at:5 index:Some((5, 50)) shadow:Some((5, 5))"
+    );
+}
+
+#[test]
+fn test_verify_failed_write() {
+    let inner = BTreeMapIndex(Rc::new(RefCell::new(BTreeMap::new())));
+    let mut inner = OddFails(inner);
+    let mut index = Verify::new(&mut inner);
+    let mut w = index.to_writer().unwrap();
+    let mut r = index.to_reader().unwrap();
+
+    for key in 0..10_u64 {
+        assert_eq!(w.set(key, key).is_ok(), key % 2 == 0);
+    }
+    assert!(w.delete(&4).is_ok());
+    assert!(w.delete(&5).is_err());
+
+    // failed writes leave both the index and the shadow untouched.
+    assert_eq!(r.get(&3, None).unwrap(), false);
+    assert_eq!(r.get(&4, None).unwrap(), false);
+    assert_eq!(index.validate().unwrap(), 4);
+}
+
+// index that fails writes to odd keys.
+struct OddFails(BTreeMapIndex<u64, u64>);
+
+impl IndexAdapter<u64, u64> for OddFails {
+    type R = BTreeMapIndex<u64, u64>;
+    type W = OddFails;
+
+    fn to_reader(&mut self) -> Result<Self::R, String> {
+        self.0.to_reader()
+    }
+
+    fn to_writer(&mut self) -> Result<Self::W, String> {
+        Ok(OddFails(self.0.to_writer()?))
+    }
+}
+
+impl WriterAdapter<u64, u64> for OddFails {
+    fn set(&mut self, key: u64, value: u64) -> Result<bool, String> {
+        match key % 2 {
+            0 => self.0.set(key, value),
+            _ => Err(format!("odd key {}", key)),
+        }
+    }
+
+    fn delete(&mut self, key: &u64) -> Result<bool, String> {
+        match key % 2 {
+            0 => self.0.delete(key),
+            _ => Err(format!("odd key {}", key)),
+        }
+    }
+}